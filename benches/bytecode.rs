@@ -0,0 +1,62 @@
+use hrm::bytecode::{Bytecode, BytecodeInterpreter};
+use hrm::interpreter::SimpleInterpreter;
+use hrm::lexer::Lexer;
+use std::time::Instant;
+
+const RUNS: usize = 20_000;
+
+/// Deterministic inboxes of small values, so that both interpreters see the same input.
+fn inboxes() -> Vec<Vec<i16>> {
+    let mut seed: u32 = 7874;
+    (0..RUNS)
+        .map(|_| {
+            (0..8)
+                .map(|_| {
+                    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                    ((seed >> 16) % 41) as i16 - 20
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn bench(name: &str, source: &str, inboxes: &[Vec<i16>]) {
+    let program = Lexer::lex(source);
+
+    let start = Instant::now();
+    let mut expected = Vec::with_capacity(inboxes.len());
+    for inbox in inboxes.iter() {
+        let mut interpreter = SimpleInterpreter::new();
+        interpreter.set_print_outbox(false);
+        interpreter.set_inbox_values(inbox);
+        let result = interpreter.eval(&program);
+        expected.push((result, interpreter.report().outbox));
+    }
+    let simple = start.elapsed();
+
+    let start = Instant::now();
+    let code = Bytecode::compile(&program).unwrap();
+    let mut interpreter = BytecodeInterpreter::new();
+    for (inbox, expected) in inboxes.iter().zip(expected.iter()) {
+        interpreter.set_inbox_values(inbox);
+        let result = interpreter.eval(&code);
+        assert_eq!((result, interpreter.report().outbox), *expected);
+    }
+    let bytecode = start.elapsed();
+
+    println!(
+        "{}: SimpleInterpreter {:?}, BytecodeInterpreter {:?} ({:.1}x)",
+        name,
+        simple,
+        bytecode,
+        simple.as_secs_f64() / bytecode.as_secs_f64()
+    );
+}
+
+fn main() {
+    let inboxes = inboxes();
+    bench("06", include_str!("../sample_program/06.hrm"), &inboxes);
+    bench("09", include_str!("../sample_program/09.hrm"), &inboxes);
+    bench("12", include_str!("../sample_program/12.hrm"), &inboxes);
+    bench("19", include_str!("../sample_program/19.hrm"), &inboxes);
+}
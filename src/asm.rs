@@ -0,0 +1,379 @@
+//! Preprocessor over `Lexer` adding directives to the source language.
+//!
+//! ```text
+//! include "common.hrm"     # macros and aliases from another file
+//! alias zero = 5           # name a floor tile
+//! macro countdown tile     # define a macro with parameters
+//!     jump_target loop     # labels inside a macro are unique per expansion
+//!     bump_minus tile
+//!     outbox
+//!     jump_if_zero done
+//!     jump loop
+//!     jump_target done
+//! endmacro
+//! countdown zero           # expand it
+//! ```
+//!
+//! `#` starts a comment. Every instruction keeps the location it was written at, and like the
+//! lexer, an instruction may continue on the next line.
+
+use crate::lexer::{canonical_mnemonic, Lexer, Location, Program};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Nesting limit of macro expansions, to stop recursive macros.
+const MAX_EXPANSION_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AssembleErrorKind {
+    FileNotFound(PathBuf),
+    RecursiveInclude(PathBuf),
+    RecursiveMacro(String),
+    UnterminatedMacro(String),
+    UnexpectedEndMacro,
+    DuplicateMacro(String),
+    ArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    InvalidDirective(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AssembleError {
+    pub kind: AssembleErrorKind,
+    pub file: PathBuf,
+    pub location: Location,
+}
+
+/// Expanded program with the file each instruction comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Assembly {
+    pub program: Program,
+    pub files: Vec<PathBuf>,
+    /// Index into `files` for each instruction of `program`.
+    pub origins: Vec<usize>,
+}
+
+/// Whitespace separated chunk of a line, outside of comments.
+#[derive(Debug, Clone)]
+struct Word {
+    text: String,
+    file: usize,
+    location: Location,
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    text: String,
+    words: Vec<Word>,
+}
+
+impl Line {
+    fn new(text: &str, file: usize, line: usize) -> Self {
+        let mut words: Vec<Word> = Vec::new();
+        let mut word: Option<Word> = None;
+        // columns count characters, as in the lexer.
+        for (i, c) in strip_comment(text).chars().chain(Some(' ')).enumerate() {
+            if c.is_ascii_whitespace() {
+                words.extend(word.take());
+            } else if let Some(word) = &mut word {
+                word.text.push(c);
+            } else {
+                word = Some(Word {
+                    text: c.to_string(),
+                    file,
+                    location: Location { line, col: i + 1 },
+                });
+            }
+        }
+        Self {
+            text: text.to_string(),
+            words,
+        }
+    }
+
+    fn chunks(&self) -> Vec<&str> {
+        self.words.iter().map(|word| word.text.as_str()).collect()
+    }
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<Line>,
+    labels: HashSet<String>,
+}
+
+fn is_jump(mnemonic: &str) -> bool {
+    matches!(
+        canonical_mnemonic(mnemonic),
+        Some("jump" | "jump_if_zero" | "jump_if_neg" | "jump_target")
+    )
+}
+
+fn strip_comment(text: &str) -> &str {
+    match text.find('#') {
+        Some(comment) => &text[..comment],
+        None => text,
+    }
+}
+
+struct Assembler {
+    assembly: Assembly,
+    aliases: HashMap<String, String>,
+    macros: HashMap<String, Macro>,
+    include_stack: Vec<PathBuf>,
+    expansions: usize,
+    depth: usize,
+    /// Words of an instruction still waiting for its argument on a later line.
+    pending: Vec<Word>,
+}
+
+impl Assembler {
+    /// Error located at the `word`th word of `line`.
+    fn error(&self, kind: AssembleErrorKind, line: &Line, word: usize) -> AssembleError {
+        let word = &line.words[word.min(line.words.len() - 1)];
+        AssembleError {
+            kind,
+            file: self.assembly.files[word.file].clone(),
+            location: word.location,
+        }
+    }
+
+    fn source(&mut self, path: &Path, source: &str) -> Result<(), AssembleError> {
+        let file = self.assembly.files.len();
+        self.assembly.files.push(path.to_path_buf());
+        self.include_stack.push(path.to_path_buf());
+        let lines: Vec<Line> = source
+            .lines()
+            .enumerate()
+            .map(|(i, text)| Line::new(text, file, i + 1))
+            .collect();
+        self.lines(&lines)?;
+        self.include_stack.pop();
+        Ok(())
+    }
+
+    fn include(&mut self, name: &str, line: &Line) -> Result<(), AssembleError> {
+        let current = &self.assembly.files[line.words[0].file];
+        let path = current.parent().unwrap_or_else(|| Path::new("")).join(name);
+        if self.include_stack.contains(&path) {
+            return Err(self.error(AssembleErrorKind::RecursiveInclude(path), line, 1));
+        }
+        match fs::read_to_string(&path) {
+            Ok(source) => self.source(&path, &source),
+            Err(_) => Err(self.error(AssembleErrorKind::FileNotFound(path), line, 1)),
+        }
+    }
+
+    fn lines(&mut self, lines: &[Line]) -> Result<(), AssembleError> {
+        let mut i = 0;
+        while i < lines.len() {
+            let line = &lines[i];
+            let chunks = line.chunks();
+            i += 1;
+            match chunks.first() {
+                None => {}
+                Some(&"alias") => match chunks.as_slice() {
+                    [_, name, "=", tile] | [_, name, tile] => {
+                        self.aliases.insert(name.to_string(), tile.to_string());
+                    }
+                    _ => {
+                        return Err(self.error(
+                            AssembleErrorKind::InvalidDirective(line.text.clone()),
+                            line,
+                            0,
+                        ))
+                    }
+                },
+                Some(&"include") => {
+                    let name = strip_comment(&line.text).trim()["include".len()..].trim();
+                    if name.len() < 2 || !name.starts_with('"') || !name.ends_with('"') {
+                        return Err(self.error(
+                            AssembleErrorKind::InvalidDirective(line.text.clone()),
+                            line,
+                            0,
+                        ));
+                    }
+                    self.include(&name[1..name.len() - 1], line)?;
+                }
+                Some(&"macro") => {
+                    let name = match chunks.get(1) {
+                        Some(name) => name.to_string(),
+                        None => {
+                            return Err(self.error(
+                                AssembleErrorKind::InvalidDirective(line.text.clone()),
+                                line,
+                                0,
+                            ))
+                        }
+                    };
+                    if self.macros.contains_key(&name) {
+                        return Err(self.error(AssembleErrorKind::DuplicateMacro(name), line, 1));
+                    }
+                    let params: Vec<String> = chunks[2..].iter().map(|s| s.to_string()).collect();
+                    let mut body = Vec::new();
+                    let mut labels = HashSet::new();
+                    loop {
+                        let body_line = match lines.get(i) {
+                            Some(body_line) => body_line,
+                            None => {
+                                return Err(self.error(
+                                    AssembleErrorKind::UnterminatedMacro(name),
+                                    line,
+                                    0,
+                                ))
+                            }
+                        };
+                        i += 1;
+                        match body_line.chunks().as_slice() {
+                            ["endmacro"] => break,
+                            [mnemonic, label]
+                                if canonical_mnemonic(mnemonic) == Some("jump_target")
+                                    && !params.iter().any(|p| p == label) =>
+                            {
+                                labels.insert(label.to_string());
+                            }
+                            _ => {}
+                        }
+                        body.push(body_line.clone());
+                    }
+                    self.macros.insert(
+                        name,
+                        Macro {
+                            params,
+                            body,
+                            labels,
+                        },
+                    );
+                }
+                Some(&"endmacro") => {
+                    return Err(self.error(AssembleErrorKind::UnexpectedEndMacro, line, 0));
+                }
+                Some(name) if self.macros.contains_key(*name) => {
+                    self.expand(name, &chunks[1..], line)?;
+                }
+                Some(_) => self.instruction(&line.words),
+            }
+        }
+        Ok(())
+    }
+
+    fn expand(&mut self, name: &str, args: &[&str], line: &Line) -> Result<(), AssembleError> {
+        if self.depth >= MAX_EXPANSION_DEPTH {
+            return Err(self.error(AssembleErrorKind::RecursiveMacro(name.to_string()), line, 0));
+        }
+        self.expansions += 1;
+        let suffix = format!("{}_{}", name, self.expansions);
+        let definition = &self.macros[name];
+        if definition.params.len() != args.len() {
+            return Err(self.error(
+                AssembleErrorKind::ArgumentCount {
+                    name: name.to_string(),
+                    expected: definition.params.len(),
+                    found: args.len(),
+                },
+                line,
+                0,
+            ));
+        }
+        let body: Vec<Line> = definition
+            .body
+            .iter()
+            .map(|body_line| {
+                let mnemonic = body_line.chunks().first().copied().unwrap_or("");
+                let words = body_line
+                    .words
+                    .iter()
+                    .enumerate()
+                    .map(|(i, word)| {
+                        let text = if i == 0 {
+                            word.text.clone()
+                        } else if let Some(p) =
+                            definition.params.iter().position(|p| *p == word.text)
+                        {
+                            args[p].to_string()
+                        } else if is_jump(mnemonic) && definition.labels.contains(&word.text) {
+                            format!("{}__{}", word.text, suffix)
+                        } else {
+                            word.text.clone()
+                        };
+                        Word {
+                            text,
+                            ..word.clone()
+                        }
+                    })
+                    .collect();
+                Line {
+                    text: body_line.text.clone(),
+                    words,
+                }
+            })
+            .collect();
+        self.depth += 1;
+        self.lines(&body)?;
+        self.depth -= 1;
+        Ok(())
+    }
+
+    /// Reads instructions from `words`, which may end with a mnemonic whose argument is on a
+    /// later line, as the lexer allows.
+    fn instruction(&mut self, words: &[Word]) {
+        self.pending.extend_from_slice(words);
+        while let Some(first) = self.pending.first() {
+            let width = match canonical_mnemonic(&first.text) {
+                Some("inbox" | "outbox") => 1,
+                Some(_) => 2,
+                None => {
+                    self.pending.remove(0);
+                    continue;
+                }
+            };
+            if self.pending.len() < width {
+                return;
+            }
+            let last = &self.pending[width - 1];
+            let text = match self.aliases.get(&last.text) {
+                Some(tile) if width == 2 && !is_jump(&first.text) => {
+                    format!("{} {}", first.text, tile)
+                }
+                _ if width == 2 => format!("{} {}", first.text, last.text),
+                _ => first.text.clone(),
+            };
+            match Lexer::lex(&text).pop() {
+                Some(mut token) => {
+                    token.location = last.location;
+                    self.assembly.program.push(token);
+                    self.assembly.origins.push(last.file);
+                    self.pending.drain(..width);
+                }
+                // like the lexer, skip a mnemonic with an invalid argument.
+                None => {
+                    self.pending.remove(0);
+                }
+            }
+        }
+    }
+}
+
+/// Expands the directives of `source`, read from `path`.
+/// Files are included relative to the directory of the including file.
+pub fn assemble(path: &Path, source: &str) -> Result<Assembly, AssembleError> {
+    let mut assembler = Assembler {
+        assembly: Assembly {
+            program: Vec::new(),
+            files: Vec::new(),
+            origins: Vec::new(),
+        },
+        aliases: HashMap::new(),
+        macros: HashMap::new(),
+        include_stack: Vec::new(),
+        expansions: 0,
+        depth: 0,
+        pending: Vec::new(),
+    };
+    assembler.source(path, source)?;
+    Ok(assembler.assembly)
+}
@@ -0,0 +1,226 @@
+//! Language server for `.hrm` files, speaking the Language Server Protocol over stdio.
+
+use hrm::lsp::{CompletionKind, Document, Position, Range, Severity};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+fn read_message(input: &mut impl BufRead) -> Option<Value> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let mut body = vec![0; length?];
+    input.read_exact(&mut body).ok()?;
+    serde_json::from_slice(&body).ok()
+}
+
+fn send(output: &mut impl Write, message: &Value) {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)
+        .and_then(|_| output.flush())
+        .expect("something went wrong writing the message");
+}
+
+fn position(params: &Value) -> Position {
+    Position {
+        line: params["position"]["line"].as_u64().unwrap_or(0) as usize,
+        character: params["position"]["character"].as_u64().unwrap_or(0) as usize,
+    }
+}
+
+fn range(range: Range) -> Value {
+    json!({
+        "start": { "line": range.start.line, "character": range.start.character },
+        "end": { "line": range.end.line, "character": range.end.character },
+    })
+}
+
+fn uri(params: &Value) -> String {
+    params["textDocument"]["uri"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn publish_diagnostics(output: &mut impl Write, uri: &str, source: &str) {
+    let diagnostics: Vec<Value> = Document::new(source)
+        .problems()
+        .iter()
+        .map(|problem| {
+            json!({
+                "range": range(problem.range),
+                "severity": match problem.severity {
+                    Severity::Error => 1,
+                    Severity::Warning => 2,
+                },
+                "source": "hrm",
+                "message": problem.message,
+            })
+        })
+        .collect();
+    send(
+        output,
+        &json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        }),
+    );
+}
+
+/// Answers a request, returning its result or an error code and message.
+fn respond(
+    documents: &HashMap<String, String>,
+    method: &str,
+    params: &Value,
+) -> Result<Value, (i64, String)> {
+    let uri = uri(params);
+    let document = || Document::new(documents.get(&uri).map_or("", |s| s.as_str()));
+    let result = match method {
+        "initialize" => json!({
+            "capabilities": {
+                "textDocumentSync": 1,
+                "definitionProvider": true,
+                "referencesProvider": true,
+                "renameProvider": true,
+                "hoverProvider": true,
+                "completionProvider": {},
+            },
+            "serverInfo": { "name": "hrm-lsp", "version": env!("CARGO_PKG_VERSION") },
+        }),
+        "shutdown" => Value::Null,
+        "textDocument/definition" => match document().definition(position(params)) {
+            Some(target) => json!({ "uri": uri, "range": range(target) }),
+            None => Value::Null,
+        },
+        "textDocument/references" => {
+            let include_declaration = params["context"]["includeDeclaration"]
+                .as_bool()
+                .unwrap_or(true);
+            let references: Vec<Value> = document()
+                .references(position(params), include_declaration)
+                .into_iter()
+                .map(|reference| json!({ "uri": uri, "range": range(reference) }))
+                .collect();
+            json!(references)
+        }
+        "textDocument/rename" => {
+            let name = params["newName"].as_str().unwrap_or_default();
+            let edits: Vec<Value> = document()
+                .rename(position(params), name)
+                .map_err(|message| (INVALID_PARAMS, message))?
+                .into_iter()
+                .map(|target| json!({ "range": range(target), "newText": name }))
+                .collect();
+            json!({ "changes": { uri.as_str(): edits } })
+        }
+        "textDocument/hover" => match document().hover(position(params)) {
+            Some((target, text)) => json!({
+                "contents": { "kind": "markdown", "value": text },
+                "range": range(target),
+            }),
+            None => Value::Null,
+        },
+        "textDocument/completion" => {
+            let items: Vec<Value> = document()
+                .completions(position(params))
+                .into_iter()
+                .map(|completion| {
+                    json!({
+                        "label": completion.label,
+                        "kind": match completion.kind {
+                            CompletionKind::Mnemonic => 14,
+                            CompletionKind::Label => 18,
+                            CompletionKind::Tile => 12,
+                        },
+                        "detail": completion.detail,
+                    })
+                })
+                .collect();
+            json!(items)
+        }
+        _ => return Err((METHOD_NOT_FOUND, format!("unknown method {}", method))),
+    };
+    Ok(result)
+}
+
+fn main() {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+    let mut documents: HashMap<String, String> = HashMap::new();
+    let mut shutdown = false;
+
+    while let Some(message) = read_message(&mut input) {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        match method {
+            "textDocument/didOpen" => {
+                let uri = uri(params);
+                let text = params["textDocument"]["text"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+                publish_diagnostics(&mut output, &uri, &text);
+                documents.insert(uri, text);
+                continue;
+            }
+            "textDocument/didChange" => {
+                let uri = uri(params);
+                // the server asks for full document sync.
+                if let Some(text) = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                {
+                    publish_diagnostics(&mut output, &uri, text);
+                    documents.insert(uri, text.to_string());
+                }
+                continue;
+            }
+            "textDocument/didClose" => {
+                let uri = uri(params);
+                documents.remove(&uri);
+                publish_diagnostics(&mut output, &uri, "");
+                continue;
+            }
+            "exit" => process::exit(if shutdown { 0 } else { 1 }),
+            _ => {}
+        }
+        // notifications have no id and get no response.
+        let id = match message.get("id") {
+            Some(id) => id.clone(),
+            None => continue,
+        };
+        if method == "shutdown" {
+            shutdown = true;
+        }
+        let response = match respond(&documents, method, params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        send(&mut output, &response);
+    }
+}
@@ -0,0 +1,254 @@
+use crate::interpreter::{
+    checked_value, InterpreterError, InterpreterErrorKind, RunReport, FLOOR_SIZE,
+};
+use crate::lexer::{Location, Program, TokenKind};
+use std::collections::HashMap;
+
+/// An instruction with its jump target resolved to an index into `Bytecode::ops`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Op {
+    InBox,
+    OutBox,
+    CopyFrom(usize),
+    CopyTo(usize),
+    Add(usize),
+    Sub(usize),
+    BumpPlus(usize),
+    BumpMinus(usize),
+    Jump(usize),
+    JumpIfZero(usize),
+    JumpIfNeg(usize),
+}
+
+/// A `Program` lowered to a flat list of `Op`s.
+/// `jump_target`s are dropped since they take no step.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Bytecode {
+    pub ops: Vec<Op>,
+    /// Source location of each op, used to report errors.
+    pub locations: Vec<Location>,
+}
+
+impl Bytecode {
+    pub fn compile(program: &Program) -> Result<Self, InterpreterError> {
+        let mut targets = HashMap::new();
+        let mut offset = 0;
+        for token in program.iter() {
+            if let TokenKind::JumpTarget(label) = &token.value {
+                targets.insert(label, offset);
+            } else {
+                offset += 1;
+            }
+        }
+        let resolve = |label: &String, location: Location| match targets.get(label) {
+            Some(&target) => Ok(target),
+            None => Err(InterpreterError {
+                value: InterpreterErrorKind::UnexistedJumpTarget,
+                location,
+            }),
+        };
+
+        let mut ops = Vec::with_capacity(offset);
+        let mut locations = Vec::with_capacity(offset);
+        for token in program.iter() {
+            let op = match &token.value {
+                TokenKind::InBox => Op::InBox,
+                TokenKind::OutBox => Op::OutBox,
+                TokenKind::CopyFrom(index) => Op::CopyFrom(*index),
+                TokenKind::CopyTo(index) => Op::CopyTo(*index),
+                TokenKind::Add(index) => Op::Add(*index),
+                TokenKind::Sub(index) => Op::Sub(*index),
+                TokenKind::BumpPlus(index) => Op::BumpPlus(*index),
+                TokenKind::BumpMinus(index) => Op::BumpMinus(*index),
+                TokenKind::Jump(label) => Op::Jump(resolve(label, token.location)?),
+                TokenKind::JumpIfZero(label) => Op::JumpIfZero(resolve(label, token.location)?),
+                TokenKind::JumpIfNeg(label) => Op::JumpIfNeg(resolve(label, token.location)?),
+                TokenKind::JumpTarget(_) => continue,
+            };
+            ops.push(op);
+            locations.push(token.location);
+        }
+        Ok(Self { ops, locations })
+    }
+}
+
+/// Runs `Bytecode` with the same results and errors as `SimpleInterpreter`.
+///
+/// The outbox is only recorded, never printed, and `RunReport::hits` and `RunReport::branches`
+/// are left empty.
+pub struct BytecodeInterpreter {
+    hand: Option<i16>,
+    cells: Vec<Option<i16>>,
+    inbox: Option<Vec<i16>>,
+    inbox_cursor: usize,
+    outbox: Vec<i16>,
+    step_counter: usize,
+    /// Floor at the start of every run.
+    initial_floor: Vec<Option<i16>>,
+    step_limit: Option<usize>,
+}
+
+impl Default for BytecodeInterpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BytecodeInterpreter {
+    pub fn new() -> Self {
+        Self {
+            hand: None,
+            cells: Vec::new(),
+            inbox: None,
+            inbox_cursor: 0,
+            outbox: Vec::new(),
+            step_counter: 0,
+            initial_floor: vec![None; FLOOR_SIZE],
+            step_limit: None,
+        }
+    }
+    pub fn set_inbox_values(&mut self, values: &[i16]) {
+        self.inbox = Some(values.to_vec());
+    }
+    /// Puts `value` on tile `index` before every run.
+    pub fn set_tile(&mut self, index: usize, value: Option<i16>) {
+        self.initial_floor[index] = value;
+    }
+    /// Fails a run with `StepLimitExceeded` once it takes more than `limit` steps.
+    pub fn set_step_limit(&mut self, limit: Option<usize>) {
+        self.step_limit = limit;
+    }
+    pub fn report(&self) -> RunReport {
+        RunReport {
+            steps: self.step_counter,
+            outbox: self.outbox.clone(),
+            hits: Vec::new(),
+            branches: Vec::new(),
+        }
+    }
+
+    /// Runs `code` from a fresh state, consuming the inbox set beforehand.
+    pub fn eval(&mut self, code: &Bytecode) -> Result<usize, InterpreterError> {
+        self.hand = None;
+        self.cells.clone_from(&self.initial_floor);
+        self.inbox_cursor = 0;
+        self.outbox.clear();
+        self.step_counter = 0;
+
+        let error = |value, cursor: usize| InterpreterError {
+            value,
+            location: code.locations[cursor],
+        };
+        let step_limit = self.step_limit.unwrap_or(usize::MAX);
+        let mut cursor = 0;
+        while cursor < code.ops.len() {
+            if self.step_counter >= step_limit {
+                return Err(error(InterpreterErrorKind::StepLimitExceeded, cursor));
+            }
+            if let Op::CopyFrom(index)
+            | Op::CopyTo(index)
+            | Op::Add(index)
+            | Op::Sub(index)
+            | Op::BumpPlus(index)
+            | Op::BumpMinus(index) = code.ops[cursor]
+            {
+                if index >= FLOOR_SIZE {
+                    return Err(error(InterpreterErrorKind::FloorOutOfRange, cursor));
+                }
+            }
+            match code.ops[cursor] {
+                Op::InBox => {
+                    let inbox = match self.inbox {
+                        Some(ref inbox) => inbox,
+                        None => return Err(error(InterpreterErrorKind::UndefinedInputBox, cursor)),
+                    };
+                    match inbox.get(self.inbox_cursor) {
+                        Some(&value) => {
+                            self.inbox_cursor += 1;
+                            self.hand = Some(value);
+                        }
+                        // if the inbox is empty, worker will exit with status 0.
+                        None => return Ok(0),
+                    }
+                    cursor += 1;
+                }
+                Op::OutBox => match self.hand.take() {
+                    Some(value) => {
+                        self.outbox.push(value);
+                        cursor += 1;
+                    }
+                    None => return Err(error(InterpreterErrorKind::EmptyHandValue, cursor)),
+                },
+                Op::CopyFrom(index) => match self.cells[index] {
+                    Some(value) => {
+                        self.hand = Some(value);
+                        cursor += 1;
+                    }
+                    None => return Err(error(InterpreterErrorKind::EmptyFloorValue, cursor)),
+                },
+                Op::CopyTo(index) => match self.hand {
+                    Some(value) => {
+                        self.cells[index] = Some(value);
+                        cursor += 1;
+                    }
+                    None => return Err(error(InterpreterErrorKind::EmptyHandValue, cursor)),
+                },
+                Op::Add(index) | Op::Sub(index) => {
+                    let floor_value = match self.cells[index] {
+                        Some(value) => value,
+                        None => return Err(error(InterpreterErrorKind::EmptyFloorValue, cursor)),
+                    };
+                    match self.hand {
+                        Some(hand_value) => {
+                            let value = if let Op::Add(_) = code.ops[cursor] {
+                                hand_value as i32 + floor_value as i32
+                            } else {
+                                hand_value as i32 - floor_value as i32
+                            };
+                            match checked_value(value) {
+                                Some(value) => self.hand = Some(value),
+                                None => return Err(error(InterpreterErrorKind::Overflow, cursor)),
+                            }
+                        }
+                        None => return Err(error(InterpreterErrorKind::EmptyHandValue, cursor)),
+                    }
+                    cursor += 1;
+                }
+                Op::BumpPlus(index) | Op::BumpMinus(index) => {
+                    let delta = if let Op::BumpPlus(_) = code.ops[cursor] {
+                        1
+                    } else {
+                        -1
+                    };
+                    match self.cells[index] {
+                        Some(floor_value) => match checked_value(floor_value as i32 + delta) {
+                            Some(value) => {
+                                self.cells[index] = Some(value);
+                                self.hand = Some(value);
+                            }
+                            None => return Err(error(InterpreterErrorKind::Overflow, cursor)),
+                        },
+                        None => return Err(error(InterpreterErrorKind::EmptyFloorValue, cursor)),
+                    }
+                    cursor += 1;
+                }
+                Op::Jump(target) => cursor = target,
+                Op::JumpIfZero(target) => {
+                    cursor = if self.hand == Some(0) {
+                        target
+                    } else {
+                        cursor + 1
+                    };
+                }
+                Op::JumpIfNeg(target) => {
+                    cursor = match self.hand {
+                        Some(value) if value < 0 => target,
+                        _ => cursor + 1,
+                    };
+                }
+            }
+            self.step_counter += 1;
+        }
+        Ok(0)
+    }
+}
@@ -0,0 +1,420 @@
+use crate::lexer::{Program, TokenKind};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    Fallthrough,
+    Jump,
+    JumpIfZero,
+    JumpIfNeg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// Instruction level control-flow graph.
+///
+/// Node `i` is `program[i]`, and node `program.len()` is the exit of the program.
+#[derive(Debug, Clone)]
+pub struct ControlFlowGraph {
+    succs: Vec<Vec<Edge>>,
+    preds: Vec<Vec<Edge>>,
+    unresolved: Vec<usize>,
+}
+
+/// Maps each label to the index of its `jump_target`.
+/// Like `SimpleInterpreter`, the last definition of a label wins.
+pub fn resolve_labels(program: &Program) -> HashMap<&str, usize> {
+    let mut targets = HashMap::new();
+    for (i, token) in program.iter().enumerate() {
+        if let TokenKind::JumpTarget(label) = &token.value {
+            targets.insert(label.as_str(), i);
+        }
+    }
+    targets
+}
+
+/// For each node of the graph given by the successors `edges`, flags the nodes on every path
+/// from `root` to it, itself included. Empty for the nodes which are not reachable from `root`.
+///
+/// Dominators over the reversed edges from the exit are post-dominators.
+pub fn dominators(edges: &[Vec<usize>], root: usize) -> Vec<Vec<bool>> {
+    let nodes = edges.len();
+    let mut preds = vec![Vec::new(); nodes];
+    let mut reachable = vec![false; nodes];
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        if reachable[node] {
+            continue;
+        }
+        reachable[node] = true;
+        for &to in edges[node].iter() {
+            preds[to].push(node);
+            stack.push(to);
+        }
+    }
+    let mut dominators: Vec<Vec<bool>> = reachable
+        .iter()
+        .map(|&reachable| vec![reachable; if reachable { nodes } else { 0 }])
+        .collect();
+    dominators[root] = (0..nodes).map(|node| node == root).collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for node in (0..nodes).filter(|&node| node != root && reachable[node]) {
+            let mut next = vec![true; nodes];
+            for &pred in preds[node].iter() {
+                for (dominator, other) in next.iter_mut().zip(dominators[pred].iter()) {
+                    *dominator &= *other;
+                }
+            }
+            next[node] = true;
+            if next != dominators[node] {
+                dominators[node] = next;
+                changed = true;
+            }
+        }
+    }
+    dominators
+}
+
+/// The strict dominator of `node` dominated by all of its other strict dominators, from the
+/// sets given by `dominators`.
+pub fn immediate_dominator(dominators: &[Vec<bool>], node: usize) -> Option<usize> {
+    let strict: Vec<usize> = (0..dominators[node].len())
+        .filter(|&other| dominators[node][other] && other != node)
+        .collect();
+    strict.iter().copied().find(|&candidate| {
+        strict
+            .iter()
+            .all(|&other| dominators[candidate].get(other).copied().unwrap_or(false))
+    })
+}
+
+impl ControlFlowGraph {
+    pub fn new(program: &Program) -> Self {
+        let targets = resolve_labels(program);
+        let exit = program.len();
+        let mut succs = vec![Vec::new(); exit + 1];
+        let mut preds = vec![Vec::new(); exit + 1];
+        let mut unresolved = Vec::new();
+        for (i, token) in program.iter().enumerate() {
+            let (label, kind) = match &token.value {
+                TokenKind::Jump(label) => (Some(label), EdgeKind::Jump),
+                TokenKind::JumpIfZero(label) => (Some(label), EdgeKind::JumpIfZero),
+                TokenKind::JumpIfNeg(label) => (Some(label), EdgeKind::JumpIfNeg),
+                _ => (None, EdgeKind::Fallthrough),
+            };
+            if let Some(label) = label {
+                if let Some(&to) = targets.get(label.as_str()) {
+                    succs[i].push(Edge { from: i, to, kind });
+                } else {
+                    unresolved.push(i);
+                }
+            }
+            if kind != EdgeKind::Jump {
+                succs[i].push(Edge {
+                    from: i,
+                    to: i + 1,
+                    kind: EdgeKind::Fallthrough,
+                });
+            }
+        }
+        for edges in succs.iter() {
+            for edge in edges.iter() {
+                preds[edge.to].push(*edge);
+            }
+        }
+        Self {
+            succs,
+            preds,
+            unresolved,
+        }
+    }
+    /// Index of the exit node.
+    pub fn exit(&self) -> usize {
+        self.succs.len() - 1
+    }
+    pub fn successors(&self, node: usize) -> &[Edge] {
+        &self.succs[node]
+    }
+    pub fn predecessors(&self, node: usize) -> &[Edge] {
+        &self.preds[node]
+    }
+    /// Instructions jumping to a label which is not defined.
+    pub fn unresolved_jumps(&self) -> &[usize] {
+        &self.unresolved
+    }
+    /// Flags the nodes which are reachable from the entry.
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut visited = vec![false; self.succs.len()];
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            if visited[node] {
+                continue;
+            }
+            visited[node] = true;
+            for edge in self.succs[node].iter() {
+                stack.push(edge.to);
+            }
+        }
+        visited
+    }
+    /// For each node, flags the nodes on every path from the entry to it, itself included.
+    /// Empty for the nodes which are not reachable.
+    pub fn dominators(&self) -> Vec<Vec<bool>> {
+        let edges: Vec<Vec<usize>> = self
+            .succs
+            .iter()
+            .map(|edges| edges.iter().map(|edge| edge.to).collect())
+            .collect();
+        dominators(&edges, 0)
+    }
+    /// Natural loops of the back edges, the edges to a node dominating their source.
+    /// Back edges to the same header make a single loop; loops are ordered by header.
+    pub fn natural_loops(&self) -> Vec<NaturalLoop> {
+        let dominators = self.dominators();
+        let reachable = self.reachable();
+        let mut loops: Vec<NaturalLoop> = Vec::new();
+        for edges in self.succs.iter() {
+            for edge in edges.iter() {
+                if !dominators[edge.from].get(edge.to).copied().unwrap_or(false) {
+                    continue;
+                }
+                match loops.iter_mut().find(|l| l.header == edge.to) {
+                    Some(natural) => natural.latches.push(edge.from),
+                    None => loops.push(NaturalLoop {
+                        header: edge.to,
+                        latches: vec![edge.from],
+                        body: Vec::new(),
+                    }),
+                }
+            }
+        }
+        for natural in loops.iter_mut() {
+            let mut body = vec![false; self.succs.len()];
+            body[natural.header] = true;
+            let mut stack = natural.latches.clone();
+            while let Some(node) = stack.pop() {
+                if body[node] {
+                    continue;
+                }
+                body[node] = true;
+                stack.extend(
+                    self.preds[node]
+                        .iter()
+                        .map(|edge| edge.from)
+                        .filter(|&from| reachable[from]),
+                );
+            }
+            natural.body = (0..body.len()).filter(|&node| body[node]).collect();
+        }
+        loops.sort_by_key(|natural| natural.header);
+        loops
+    }
+}
+
+/// Instructions repeated by the back edges to `header`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NaturalLoop {
+    pub header: usize,
+    /// Sources of the back edges.
+    pub latches: Vec<usize>,
+    /// Nodes of the loop in program order, `header` included.
+    pub body: Vec<usize>,
+}
+
+fn edge_label(kind: EdgeKind) -> Option<&'static str> {
+    match kind {
+        EdgeKind::Fallthrough => None,
+        EdgeKind::Jump => Some("jump"),
+        EdgeKind::JumpIfZero => Some("if zero"),
+        EdgeKind::JumpIfNeg => Some("if neg"),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockEdge {
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// Instructions `program[start..end]`, entered only at `start` and left only at `end - 1`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BasicBlock {
+    pub start: usize,
+    pub end: usize,
+    /// Name of the `jump_target` opening the block.
+    pub label: Option<String>,
+    pub successors: Vec<BlockEdge>,
+}
+
+/// Basic block level control-flow graph.
+///
+/// Block `blocks.len()` is the exit of the program.
+#[derive(Debug, Clone)]
+pub struct BlockGraph {
+    pub blocks: Vec<BasicBlock>,
+    block_of: Vec<usize>,
+}
+
+impl BlockGraph {
+    pub fn new(program: &Program) -> Self {
+        let cfg = ControlFlowGraph::new(program);
+        let exit = cfg.exit();
+        let mut leaders = vec![false; exit + 1];
+        leaders[0] = true;
+        leaders[exit] = true;
+        for (i, token) in program.iter().enumerate() {
+            match token.value {
+                TokenKind::JumpTarget(_) => leaders[i] = true,
+                TokenKind::Jump(_) | TokenKind::JumpIfZero(_) | TokenKind::JumpIfNeg(_) => {
+                    leaders[i + 1] = true;
+                }
+                _ => {}
+            }
+            for edge in cfg.successors(i) {
+                if edge.kind != EdgeKind::Fallthrough {
+                    leaders[edge.to] = true;
+                }
+            }
+        }
+
+        let mut blocks = Vec::new();
+        let mut block_of = vec![0; exit + 1];
+        let mut start = 0;
+        for i in 1..=exit {
+            if leaders[i] && start < i {
+                let label = match &program[start].value {
+                    TokenKind::JumpTarget(label) => Some(label.clone()),
+                    _ => None,
+                };
+                blocks.push(BasicBlock {
+                    start,
+                    end: i,
+                    label,
+                    successors: Vec::new(),
+                });
+                start = i;
+            }
+            block_of[i] = blocks.len();
+        }
+        for block in blocks.iter_mut() {
+            block.successors = cfg
+                .successors(block.end - 1)
+                .iter()
+                .map(|edge| BlockEdge {
+                    to: block_of[edge.to],
+                    kind: edge.kind,
+                })
+                .collect();
+        }
+        Self { blocks, block_of }
+    }
+    /// Index of the exit block.
+    pub fn exit(&self) -> usize {
+        self.blocks.len()
+    }
+    /// Index of the block containing `program[index]`.
+    pub fn block_of(&self, index: usize) -> usize {
+        self.block_of[index]
+    }
+    fn node_name(&self, block: usize) -> String {
+        if block == self.exit() {
+            "exit".to_string()
+        } else {
+            format!("b{}", block)
+        }
+    }
+    fn node_lines(&self, program: &Program, block: usize, hits: Option<&[usize]>) -> Vec<String> {
+        let block = &self.blocks[block];
+        let mut lines = Vec::new();
+        let mut header = match &block.label {
+            Some(label) => format!("{}:", label),
+            None => format!("b{}", self.block_of(block.start)),
+        };
+        if let Some(hits) = hits {
+            header = format!("{} (x{})", header, hits[block.start]);
+        }
+        lines.push(header);
+        for token in program[block.start..block.end].iter() {
+            if let TokenKind::JumpTarget(_) = token.value {
+                continue;
+            }
+            lines.push(format!("{}: {}", token.location.line, token.value));
+        }
+        lines
+    }
+    /// Renders the graph in Graphviz DOT.
+    /// When `hits` from a `RunReport` is given, each block shows how many times it was entered.
+    pub fn to_dot(&self, program: &Program, hits: Option<&[usize]>) -> String {
+        let mut out = String::new();
+        out.push_str("digraph program {\n");
+        out.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+        out.push_str("    entry [shape=point];\n");
+        out.push_str("    exit [shape=doublecircle, label=\"exit\"];\n");
+        for i in 0..self.blocks.len() {
+            let label: String = self
+                .node_lines(program, i, hits)
+                .iter()
+                .map(|line| format!("{}\\l", line.replace('"', "\\\"")))
+                .collect();
+            out.push_str(&format!("    b{} [label=\"{}\"];\n", i, label));
+        }
+        out.push_str(&format!("    entry -> {};\n", self.node_name(0)));
+        for (i, block) in self.blocks.iter().enumerate() {
+            for edge in block.successors.iter() {
+                let attributes = match edge_label(edge.kind) {
+                    Some(label) => format!(" [label=\"{}\"]", label),
+                    None => String::new(),
+                };
+                out.push_str(&format!(
+                    "    b{} -> {}{};\n",
+                    i,
+                    self.node_name(edge.to),
+                    attributes
+                ));
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Renders the graph as a Mermaid flowchart.
+    /// When `hits` from a `RunReport` is given, each block shows how many times it was entered.
+    pub fn to_mermaid(&self, program: &Program, hits: Option<&[usize]>) -> String {
+        let mut out = String::new();
+        out.push_str("flowchart TD\n");
+        out.push_str("    entry(( ))\n");
+        out.push_str("    exit((exit))\n");
+        for i in 0..self.blocks.len() {
+            let label = self
+                .node_lines(program, i, hits)
+                .iter()
+                .map(|line| line.replace('"', "#quot;"))
+                .collect::<Vec<_>>()
+                .join("<br/>");
+            out.push_str(&format!("    b{}[\"{}\"]\n", i, label));
+        }
+        out.push_str(&format!("    entry --> {}\n", self.node_name(0)));
+        for (i, block) in self.blocks.iter().enumerate() {
+            for edge in block.successors.iter() {
+                let arrow = match edge_label(edge.kind) {
+                    Some(label) => format!("-->|{}|", label),
+                    None => "-->".to_string(),
+                };
+                out.push_str(&format!(
+                    "    b{} {} {}\n",
+                    i,
+                    arrow,
+                    self.node_name(edge.to)
+                ));
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,262 @@
+use crate::cfg::{ControlFlowGraph, EdgeKind};
+use crate::interpreter::InterpreterErrorKind;
+use crate::lexer::{Annotation, Program, TokenKind};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Abstract content of the hand or a floor tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Content {
+    Empty,
+    Full,
+    Unknown,
+}
+impl Content {
+    fn join(self, other: Self) -> Self {
+        if self == other {
+            self
+        } else {
+            Content::Unknown
+        }
+    }
+}
+
+/// Abstract state before an instruction is executed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct State {
+    pub hand: Content,
+    pub tiles: Vec<Content>,
+}
+impl State {
+    fn join(&mut self, other: &Self) -> bool {
+        let before = self.clone();
+        self.hand = self.hand.join(other.hand);
+        for (tile, other) in self.tiles.iter_mut().zip(other.tiles.iter()) {
+            *tile = tile.join(*other);
+        }
+        *self != before
+    }
+    pub fn tile(&self, index: usize) -> Content {
+        self.tiles.get(index).copied().unwrap_or(Content::Empty)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Certainty {
+    /// The error happens whenever the instruction is executed.
+    Definite,
+    /// The error happens on some paths reaching the instruction.
+    Possible,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Finding {
+    pub certainty: Certainty,
+    pub kind: InterpreterErrorKind,
+    pub tile: Option<usize>,
+}
+pub type Diagnostic = Annotation<Finding>;
+
+impl Finding {
+    pub fn severity(&self) -> &'static str {
+        match self.certainty {
+            Certainty::Definite => "error",
+            Certainty::Possible => "warning",
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let adverb = match self.certainty {
+            Certainty::Definite => "always",
+            Certainty::Possible => "possibly",
+        };
+        let subject = match (self.kind, self.tile) {
+            (InterpreterErrorKind::EmptyFloorValue, Some(tile)) => {
+                format!("tile {} is {} empty", tile, adverb)
+            }
+            (InterpreterErrorKind::EmptyHandValue, _) => format!("hand is {} empty", adverb),
+            (InterpreterErrorKind::UnexistedJumpTarget, _) => {
+                "jump target is not defined".to_string()
+            }
+            (InterpreterErrorKind::Overflow, _) => format!("result is {} out of range", adverb),
+            (InterpreterErrorKind::FloorOutOfRange, Some(tile)) => {
+                format!("tile {} is past the end of the floor", tile)
+            }
+            (kind, _) => format!("{:?}", kind),
+        };
+        write!(f, "{} ({:?})", subject, self.kind)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.location.line,
+            self.location.col,
+            self.value.severity(),
+            self.value
+        )
+    }
+}
+
+/// Result of the emptiness analysis.
+pub struct Analysis {
+    /// State before each instruction, `None` if it is never reached.
+    pub states: Vec<Option<State>>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+fn floor_width(program: &Program) -> usize {
+    program
+        .iter()
+        .filter_map(|token| match token.value {
+            TokenKind::CopyFrom(index)
+            | TokenKind::CopyTo(index)
+            | TokenKind::Add(index)
+            | TokenKind::Sub(index)
+            | TokenKind::BumpPlus(index)
+            | TokenKind::BumpMinus(index) => Some(index + 1),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Checks that `content` holds a value, recording a finding otherwise.
+/// Returns `false` when execution can never continue past the check.
+fn require(
+    content: Content,
+    kind: InterpreterErrorKind,
+    tile: Option<usize>,
+    findings: &mut Vec<Finding>,
+) -> bool {
+    let certainty = match content {
+        Content::Full => return true,
+        Content::Empty => Certainty::Definite,
+        Content::Unknown => Certainty::Possible,
+    };
+    findings.push(Finding {
+        certainty,
+        kind,
+        tile,
+    });
+    certainty == Certainty::Possible
+}
+
+/// Applies `kind` to `state`.
+/// Returns the state after a successful execution, or `None` if it always fails.
+fn transfer(kind: &TokenKind, state: &State, findings: &mut Vec<Finding>) -> Option<State> {
+    let mut next = state.clone();
+    let hand = InterpreterErrorKind::EmptyHandValue;
+    let floor = InterpreterErrorKind::EmptyFloorValue;
+    let ok = match *kind {
+        TokenKind::InBox => {
+            next.hand = Content::Full;
+            true
+        }
+        TokenKind::OutBox => {
+            next.hand = Content::Empty;
+            require(state.hand, hand, None, findings)
+        }
+        TokenKind::CopyFrom(index) => {
+            next.hand = Content::Full;
+            next.tiles[index] = Content::Full;
+            require(state.tile(index), floor, Some(index), findings)
+        }
+        TokenKind::CopyTo(index) => {
+            next.tiles[index] = Content::Full;
+            next.hand = Content::Full;
+            require(state.hand, hand, None, findings)
+        }
+        TokenKind::Add(index) | TokenKind::Sub(index) => {
+            next.hand = Content::Full;
+            next.tiles[index] = Content::Full;
+            require(state.tile(index), floor, Some(index), findings)
+                && require(state.hand, hand, None, findings)
+        }
+        TokenKind::BumpPlus(index) | TokenKind::BumpMinus(index) => {
+            next.hand = Content::Full;
+            next.tiles[index] = Content::Full;
+            require(state.tile(index), floor, Some(index), findings)
+        }
+        _ => true,
+    };
+    if ok {
+        Some(next)
+    } else {
+        None
+    }
+}
+
+/// Tracks whether the hand and each floor tile are empty before every instruction.
+pub fn analyze(program: &Program) -> Analysis {
+    let cfg = ControlFlowGraph::new(program);
+    let mut states: Vec<Option<State>> = vec![None; program.len() + 1];
+    states[0] = Some(State {
+        hand: Content::Empty,
+        tiles: vec![Content::Empty; floor_width(program)],
+    });
+    let mut worklist = VecDeque::new();
+    worklist.push_back(0);
+    while let Some(node) = worklist.pop_front() {
+        if node == cfg.exit() {
+            continue;
+        }
+        let state = states[node].clone().unwrap();
+        let next = match transfer(&program[node].value, &state, &mut Vec::new()) {
+            Some(next) => next,
+            None => continue,
+        };
+        for edge in cfg.successors(node) {
+            let mut out = next.clone();
+            // a conditional jump is only taken when the hand holds a value.
+            if edge.kind == EdgeKind::JumpIfZero || edge.kind == EdgeKind::JumpIfNeg {
+                out.hand = Content::Full;
+            }
+            let changed = match states[edge.to].as_mut() {
+                Some(current) => current.join(&out),
+                None => {
+                    states[edge.to] = Some(out);
+                    true
+                }
+            };
+            if changed {
+                worklist.push_back(edge.to);
+            }
+        }
+    }
+
+    let mut diagnostics = Vec::new();
+    for &index in cfg.unresolved_jumps() {
+        diagnostics.push(Diagnostic {
+            value: Finding {
+                certainty: Certainty::Definite,
+                kind: InterpreterErrorKind::UnexistedJumpTarget,
+                tile: None,
+            },
+            location: program[index].location,
+        });
+    }
+    for (token, state) in program.iter().zip(states.iter()) {
+        if let Some(state) = state {
+            let mut findings = Vec::new();
+            transfer(&token.value, state, &mut findings);
+            for finding in findings {
+                diagnostics.push(Diagnostic {
+                    value: finding,
+                    location: token.location,
+                });
+            }
+        }
+    }
+    diagnostics.sort_by_key(|d| (d.location.line, d.location.col));
+    states.truncate(program.len());
+    Analysis {
+        states,
+        diagnostics,
+    }
+}
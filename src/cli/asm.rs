@@ -0,0 +1,39 @@
+//! `hrm asm`: expands the aliases, macros and includes of a source.
+
+use super::{parse_args, print_usage, read_file, write_output};
+use getopts::Options;
+use hrm::asm;
+use hrm::printer;
+use std::path::Path;
+use std::process;
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    opts.optopt(
+        "o",
+        "output",
+        "write the expanded program to this file",
+        "NAME",
+    );
+    opts.optflag("h", "help", "print this help menu");
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") || matches.free.is_empty() {
+        print_usage(program, opts);
+        process::exit(64);
+    }
+
+    let script = &matches.free[0];
+    match asm::assemble(Path::new(script), &read_file(script)) {
+        Ok(assembly) => write_output(matches.opt_str("o"), &printer::print(&assembly.program)),
+        Err(e) => {
+            eprintln!(
+                "{}:{}:{}: {:?}",
+                e.file.display(),
+                e.location.line,
+                e.location.col,
+                e.kind
+            );
+            process::exit(1);
+        }
+    }
+}
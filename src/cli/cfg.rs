@@ -0,0 +1,50 @@
+//! `hrm cfg`: prints the basic block graph as DOT or Mermaid.
+
+use super::{load_program, parse_args, print_usage, read_file};
+use getopts::Options;
+use hrm::cfg::BlockGraph;
+use hrm::interpreter::SimpleInterpreter;
+use std::process;
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    opts.optopt(
+        "f",
+        "format",
+        "output format: dot (default) or mermaid",
+        "FORMAT",
+    );
+    opts.optopt(
+        "i",
+        "input",
+        "run the program on this input and show execution counts",
+        "NAME",
+    );
+    opts.optflag("h", "help", "print this help menu");
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") || matches.free.is_empty() {
+        print_usage(program, opts);
+        process::exit(64);
+    }
+
+    let source = load_program(&matches.free[0]);
+    let hits = matches.opt_str("i").map(|input_path| {
+        let mut interpreter = SimpleInterpreter::new();
+        interpreter.set_print_outbox(false);
+        interpreter.set_inbox(read_file(&input_path));
+        if let Err(e) = interpreter.eval(&source) {
+            error!("{:?}", e);
+        }
+        interpreter.report().hits
+    });
+    let graph = BlockGraph::new(&source);
+    let hits = hits.as_deref();
+    match matches.opt_str("f").as_deref() {
+        None | Some("dot") => print!("{}", graph.to_dot(&source, hits)),
+        Some("mermaid") => print!("{}", graph.to_mermaid(&source, hits)),
+        Some(format) => {
+            eprintln!("unknown format: {}", format);
+            process::exit(64);
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! `hrm check`: reports instructions reading an empty hand or tile.
+
+use super::{load_program, parse_args, print_usage};
+use getopts::Options;
+use hrm::check;
+use hrm::check::Certainty;
+use std::process;
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    opts.optflag("h", "help", "print this help menu");
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") || matches.free.is_empty() {
+        print_usage(program, opts);
+        process::exit(64);
+    }
+
+    let script = &matches.free[0];
+    let analysis = check::analyze(&load_program(script));
+    for diagnostic in analysis.diagnostics.iter() {
+        println!("{}:{}", script, diagnostic);
+    }
+    if analysis
+        .diagnostics
+        .iter()
+        .any(|d| d.value.certainty == Certainty::Definite)
+    {
+        process::exit(1);
+    }
+}
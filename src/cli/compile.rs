@@ -0,0 +1,43 @@
+//! `hrm compile`: compiles the structured language to instructions.
+
+use super::{parse_args, print_usage, read_file, write_output};
+use getopts::Options;
+use hrm::lang;
+use hrm::optimizer;
+use hrm::printer;
+use std::process;
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    opts.optopt(
+        "o",
+        "output",
+        "write the compiled program to this file",
+        "NAME",
+    );
+    opts.optflag("O", "optimize", "apply the peephole optimizer");
+    opts.optflag("h", "help", "print this help menu");
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") || matches.free.is_empty() {
+        print_usage(program, opts);
+        process::exit(64);
+    }
+
+    let script = &matches.free[0];
+    let compiled = match lang::compile(&read_file(script)) {
+        Ok(compiled) => compiled,
+        Err(e) => {
+            eprintln!(
+                "{}:{}:{}: {:?}",
+                script, e.location.line, e.location.col, e.value
+            );
+            process::exit(1);
+        }
+    };
+    let compiled = if matches.opt_present("O") {
+        optimizer::optimize(&compiled).0
+    } else {
+        compiled
+    };
+    write_output(matches.opt_str("o"), &printer::print(&compiled));
+}
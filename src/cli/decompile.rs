@@ -0,0 +1,24 @@
+//! `hrm decompile`: prints a program as structured pseudocode.
+
+use super::{load_program, parse_args, print_usage};
+use getopts::Options;
+use hrm::decompiler;
+use std::process;
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    opts.optflag("h", "help", "print this help menu");
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") || matches.free.is_empty() {
+        print_usage(program, opts);
+        process::exit(64);
+    }
+
+    match decompiler::decompile(&load_program(&matches.free[0])) {
+        Ok(code) => print!("{}", code),
+        Err(e) => {
+            error!("{:?}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! `hrm equiv`: compares two programs on shared inboxes.
+
+use super::{load_program, parse_args, parse_floor, parse_option, print_usage};
+use getopts::Options;
+use hrm::equiv::{self, Behaviour};
+use hrm::interpreter::FLOOR_SIZE;
+use std::process;
+
+fn show_behaviour(behaviour: &Behaviour) -> String {
+    let values: Vec<String> = behaviour.outbox.iter().map(|v| v.to_string()).collect();
+    let mut text = format!("outbox [{}]", values.join(" "));
+    if let Some(e) = &behaviour.error {
+        text.push_str(&format!(
+            ", then {:?} at {}:{}",
+            e.value, e.location.line, e.location.col
+        ));
+    }
+    text
+}
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    opts.optopt(
+        "",
+        "floor",
+        "comma separated tile=value pairs on the floor at the start",
+        "LIST",
+    );
+    opts.optopt(
+        "",
+        "steps",
+        "steps after which a run is stopped (default: 10000)",
+        "N",
+    );
+    opts.optflag("h", "help", "print this help menu");
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") || matches.free.len() != 2 {
+        print_usage(program, opts);
+        process::exit(64);
+    }
+
+    let floor = matches
+        .opt_str("floor")
+        .map_or(Vec::new(), |list| parse_floor(&list, FLOOR_SIZE));
+    let steps = parse_option(&matches, "steps").unwrap_or(10_000);
+    let (left, right) = (&matches.free[0], &matches.free[1]);
+    let equivalence = match equiv::compare(&load_program(left), &load_program(right), &floor, steps)
+    {
+        Ok(equivalence) => equivalence,
+        Err(e) => {
+            error!("{:?}", e);
+            process::exit(1);
+        }
+    };
+    match equivalence.difference {
+        None => println!("equivalent on {} inboxes", equivalence.inboxes),
+        Some(difference) => {
+            let values: Vec<String> = difference.inbox.iter().map(|v| v.to_string()).collect();
+            println!("programs differ on inbox [{}]", values.join(" "));
+            println!("  {}: {}", left, show_behaviour(&difference.left));
+            println!("  {}: {}", right, show_behaviour(&difference.right));
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,45 @@
+//! `hrm fmt`: formats sources in place or checks they are formatted.
+
+use super::{parse_args, print_usage, read_file, write_output};
+use getopts::Options;
+use hrm::formatter;
+use std::process;
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    opts.optflag(
+        "",
+        "check",
+        "exit with status 1 if a file is not formatted, without rewriting it",
+    );
+    opts.optflag("h", "help", "print this help menu");
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") || matches.free.is_empty() {
+        print_usage(program, opts);
+        process::exit(64);
+    }
+
+    let mut unformatted = false;
+    for path in matches.free.iter() {
+        let source = read_file(path);
+        let formatted = match formatter::format(&source) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                error!("{}: {:?}", path, e);
+                process::exit(1);
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if matches.opt_present("check") {
+            println!("{} is not formatted", path);
+            unformatted = true;
+        } else {
+            write_output(Some(path.clone()), &formatted);
+        }
+    }
+    if unformatted {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,211 @@
+//! Subcommands of the `hrm` binary, one module each.
+
+pub mod asm;
+pub mod cfg;
+pub mod check;
+pub mod compile;
+pub mod decompile;
+pub mod equiv;
+pub mod fmt;
+pub mod opt;
+pub mod profile;
+pub mod ranges;
+pub mod repl;
+pub mod run;
+pub mod superopt;
+pub mod symbolic;
+pub mod synth;
+pub mod test;
+pub mod transpile;
+pub mod tui;
+pub mod wasm;
+pub mod workers;
+
+use getopts::Options;
+use hrm::interpreter::{checked_value, FLOOR_SIZE};
+use hrm::lexer::{self, Lexer, Program};
+use hrm::printer;
+use hrm::superopt::{Budget, Level, Search};
+use log::Level as LogLevel;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::process;
+use std::time::Duration;
+
+pub fn print_usage(program: &str, opts: Options) {
+    let brief = format!(
+        "Human Resource Machine's interpreter written in Rust.
+[Steam：Human Resource Machine](https://store.steampowered.com/app/375820/Human_Resource_Machine/)
+
+Usage: {0} source.rhm [options]
+       {0} source.rhm [-i input.txt] [--load-state state.txt] [--stop-after n] [--save-state state.txt]
+       {0} check source.rhm
+       {0} ranges source.rhm [--inbox lo..hi] [--floor tile=value,...] [-v]
+       {0} cfg source.rhm [--format dot|mermaid] [-i input]
+       {0} opt source.rhm [-o output.rhm]
+       {0} transpile source.rhm [--target rust|c] [-o output]
+       {0} wasm source.rhm [-o output.wat]
+       {0} compile source.hrml [-O] [-o output.rhm]
+       {0} asm source.rhm [-o output.rhm]
+       {0} decompile source.rhm
+       {0} fmt [--check] source.rhm...
+       {0} repl
+       {0} tui source.rhm [-i input.txt]
+       {0} test source.rhm cases.hrmtest... [--coverage] [--html coverage.html]
+       {0} profile source.rhm cases.hrmtest... [--top n]
+       {0} superopt reference.rhm [--instructions list] [--tiles n] [--floor tile=value,...] [--size n] [--time seconds] [-o output.rhm]
+       {0} equiv left.rhm right.rhm [--floor tile=value,...] [--steps n]
+       {0} symbolic source.rhm [--floor tile=value,...] [--depth n] [--paths n] [-v]
+       {0} workers worker.rhm... [-i input.txt] [--floor tile=value,...] [--schedule round-robin|random] [--quantum n] [--seed n] [--steps n]
+       {0} synth examples.hrmtest [--instructions list] [--tiles n] [--floor tile=value,...] [--size n] [--time seconds] [-o output.rhm]
+    FILE: program read from script file",
+        program
+    );
+    print!("{}", opts.usage(&brief));
+}
+
+pub fn read_file(path: &str) -> String {
+    let mut buf = String::new();
+    let mut f = File::open(path).expect("file not found");
+    f.read_to_string(&mut buf)
+        .expect("something went wrong reading the file");
+    buf
+}
+
+pub fn load_program(script: &str) -> Program {
+    debug!("Script file is {:?}.", script);
+    let p = read_file(script);
+
+    info!("START lexical analyze.");
+    let program = Lexer::lex(&p);
+    info!("END lexical analyze.");
+    if log_enabled!(LogLevel::Debug) {
+        debug!("parsed program:");
+        for p in program.iter() {
+            debug!("\t{:?}", p);
+        }
+    }
+    program
+}
+
+pub fn write_output(path: Option<String>, text: &str) {
+    if let Some(output_path) = path {
+        let mut f = File::create(output_path).expect("File was not created");
+        f.write_all(text.as_bytes())
+            .expect("something went wrong writing the file");
+    } else {
+        print!("{}", text);
+    }
+}
+
+/// Options describing the level and the budget of a search.
+pub fn search_options(opts: &mut Options) {
+    opts.optopt(
+        "",
+        "instructions",
+        "comma separated instructions of the level (default: all)",
+        "LIST",
+    );
+    opts.optopt("", "tiles", "number of floor tiles (default: 6)", "N");
+    opts.optopt(
+        "",
+        "floor",
+        "comma separated tile=value pairs on the floor at the start",
+        "LIST",
+    );
+    opts.optopt("", "size", "most instructions to try (default: 6)", "N");
+    opts.optopt("", "time", "seconds to search for (default: 60)", "SECONDS");
+}
+
+/// Parses `args` with `opts`, exiting with status 64 on an unknown or malformed option.
+pub fn parse_args(opts: &Options, args: &[String]) -> getopts::Matches {
+    match opts.parse(args) {
+        Ok(matches) => matches,
+        Err(f) => {
+            eprintln!("{}", f);
+            process::exit(64);
+        }
+    }
+}
+
+pub fn parse_option<T: std::str::FromStr>(matches: &getopts::Matches, name: &str) -> Option<T> {
+    matches.opt_str(name).map(|value| match value.parse() {
+        Ok(value) => value,
+        Err(_) => {
+            eprintln!("invalid --{}: {}", name, value);
+            process::exit(64);
+        }
+    })
+}
+
+/// Reads comma separated `tile=value` pairs of tiles below `tiles`.
+pub fn parse_floor(list: &str, tiles: usize) -> Vec<(usize, i16)> {
+    list.split(',')
+        .map(|pair| {
+            match pair
+                .split_once('=')
+                .map(|(tile, value)| (tile.trim().parse::<usize>(), value.trim().parse::<i16>()))
+            {
+                Some((Ok(tile), Ok(value)))
+                    if tile < tiles && checked_value(value as i32).is_some() =>
+                {
+                    (tile, value)
+                }
+                _ => {
+                    eprintln!("invalid floor tile: {}", pair);
+                    process::exit(64);
+                }
+            }
+        })
+        .collect()
+}
+
+pub fn search_level(matches: &getopts::Matches) -> (Level, Budget) {
+    let mut level = Level::default();
+    if let Some(list) = matches.opt_str("instructions") {
+        level.instructions = list
+            .split(',')
+            .map(|name| match lexer::canonical_mnemonic(name.trim()) {
+                Some(mnemonic) if mnemonic != "jump_target" => mnemonic,
+                _ => {
+                    eprintln!("unknown instruction: {}", name);
+                    process::exit(64);
+                }
+            })
+            .collect();
+    }
+    if let Some(tiles) = parse_option::<usize>(matches, "tiles") {
+        level.tiles = tiles.min(FLOOR_SIZE);
+    }
+    if let Some(list) = matches.opt_str("floor") {
+        level.floor = parse_floor(&list, level.tiles);
+    }
+    let mut budget = Budget::default();
+    if let Some(size) = parse_option(matches, "size") {
+        budget.max_size = size;
+    }
+    if let Some(seconds) = parse_option(matches, "time") {
+        budget.time = Duration::from_secs(seconds);
+    }
+    (level, budget)
+}
+
+pub fn write_search(search: Search, budget: &Budget, path: Option<String>) {
+    eprintln!("{} candidates tried", search.candidates);
+    match search.program {
+        Some(found) => write_output(path, &printer::print(&found)),
+        None if search.timed_out => {
+            eprintln!("no program found in {} seconds", budget.time.as_secs());
+            process::exit(1);
+        }
+        None => {
+            eprintln!("no program of up to {} instructions", budget.max_size);
+            process::exit(1);
+        }
+    }
+}
+
+pub fn show_values(values: &[i16]) -> String {
+    let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+    format!("[{}]", values.join(" "))
+}
@@ -0,0 +1,33 @@
+//! `hrm opt`: applies the peephole optimizer.
+
+use super::{load_program, parse_args, print_usage, write_output};
+use getopts::Options;
+use hrm::optimizer;
+use hrm::printer;
+use std::process;
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    opts.optopt(
+        "o",
+        "output",
+        "write the optimized program to this file",
+        "NAME",
+    );
+    opts.optflag("h", "help", "print this help menu");
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") || matches.free.is_empty() {
+        print_usage(program, opts);
+        process::exit(64);
+    }
+
+    let script = &matches.free[0];
+    let (optimized, rewrites) = optimizer::optimize(&load_program(script));
+    for rewrite in rewrites.iter() {
+        eprintln!(
+            "{}:{}:{}: {}",
+            script, rewrite.location.line, rewrite.location.col, rewrite.value
+        );
+    }
+    write_output(matches.opt_str("o"), &printer::print(&optimized));
+}
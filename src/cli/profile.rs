@@ -0,0 +1,43 @@
+//! `hrm profile`: ranks instructions and loops by the steps they take.
+
+use super::{load_program, parse_args, parse_option, print_usage, read_file};
+use getopts::Options;
+use hrm::profile;
+use hrm::testcase;
+use std::process;
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    opts.optopt(
+        "",
+        "top",
+        "instructions and loops to list (default: 10)",
+        "N",
+    );
+    opts.optflag("h", "help", "print this help menu");
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") || matches.free.len() < 2 {
+        print_usage(program, opts);
+        process::exit(64);
+    }
+
+    let source = load_program(&matches.free[0]);
+    let mut cases = Vec::new();
+    for path in matches.free[1..].iter() {
+        match testcase::parse(&read_file(path), path) {
+            Ok(parsed) => cases.extend(parsed),
+            Err(e) => {
+                error!("{}: {:?}", path, e);
+                process::exit(1);
+            }
+        }
+    }
+    let top = parse_option(&matches, "top").unwrap_or(10);
+    match profile::profile(&source, &cases) {
+        Ok(profile) => print!("{}", profile.report(&source, top)),
+        Err(e) => {
+            error!("{:?}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! `hrm ranges`: interval analysis of the hand and tile values.
+
+use super::{load_program, parse_args, parse_floor, print_usage};
+use getopts::Options;
+use hrm::interpreter::{FLOOR_SIZE, MAX_VALUE};
+use hrm::interval::{self, Interval};
+use std::process;
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    opts.optopt(
+        "",
+        "inbox",
+        "range of the inbox values (default: -999..999)",
+        "LO..HI",
+    );
+    opts.optopt(
+        "",
+        "floor",
+        "comma separated tile=value pairs on the floor at the start",
+        "LIST",
+    );
+    opts.optflag("v", "verbose", "print the values before every instruction");
+    opts.optflag("h", "help", "print this help menu");
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") || matches.free.is_empty() {
+        print_usage(program, opts);
+        process::exit(64);
+    }
+
+    let inbox = matches.opt_str("inbox").map_or(Interval::FULL, |range| {
+        match range
+            .split_once("..")
+            .map(|(lo, hi)| (lo.trim().parse::<i16>(), hi.trim().parse::<i16>()))
+        {
+            Some((Ok(lo), Ok(hi))) if -MAX_VALUE <= lo && lo <= hi && hi <= MAX_VALUE => {
+                Interval::new(lo, hi)
+            }
+            _ => {
+                eprintln!("invalid inbox range: {}", range);
+                process::exit(64);
+            }
+        }
+    });
+    let floor = matches
+        .opt_str("floor")
+        .map_or(Vec::new(), |list| parse_floor(&list, FLOOR_SIZE));
+    let script = &matches.free[0];
+    let source = load_program(script);
+    let analysis = interval::analyze(&source, inbox, &floor);
+    if matches.opt_present("v") {
+        for (token, state) in source.iter().zip(analysis.states.iter()) {
+            let values = state
+                .as_ref()
+                .map_or("unreachable".to_string(), |state| state.to_string());
+            println!(
+                "{}:{} {}: {}",
+                token.location.line, token.location.col, token.value, values
+            );
+        }
+    }
+    for diagnostic in analysis.diagnostics.iter() {
+        println!("{}:{}", script, diagnostic);
+    }
+    if !analysis.diagnostics.is_empty() {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,21 @@
+//! `hrm repl`: interactive session on a stepping interpreter.
+
+use super::{parse_args, print_usage};
+use getopts::Options;
+use hrm::repl;
+use std::io;
+use std::process;
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    opts.optflag("h", "help", "print this help menu");
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") {
+        print_usage(program, opts);
+        process::exit(64);
+    }
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    repl::run(stdin.lock(), &mut stdout.lock()).expect("something went wrong in the session");
+}
@@ -0,0 +1,122 @@
+//! Running a program, the command used when no other one is named.
+
+use super::{load_program, parse_args, parse_option, print_usage, read_file};
+use getopts::Options;
+use hrm::interpreter::SimpleInterpreter;
+use hrm::state;
+use std::fs::File;
+use std::io::Write;
+use std::process;
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    opts.optopt("i", "input", "set input file name", "NAME");
+    opts.optopt("o", "output", "set output file name", "NAME");
+    opts.optopt(
+        "e",
+        "expected",
+        "stop at the first value differing from the outbox in this file",
+        "NAME",
+    );
+    opts.optopt(
+        "",
+        "load-state",
+        "resume the run saved in this state file",
+        "NAME",
+    );
+    opts.optopt(
+        "",
+        "save-state",
+        "save the state where the run stops to this file",
+        "NAME",
+    );
+    opts.optopt(
+        "",
+        "stop-after",
+        "pause the run once this many steps were taken",
+        "N",
+    );
+    opts.optflag("h", "help", "print this help menu");
+
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") {
+        print_usage(program, opts);
+        return;
+    }
+
+    let _output = matches.opt_str("o");
+    let input = matches.opt_str("i");
+    if input.is_some() && matches.opt_present("load-state") {
+        eprintln!("-i cannot be given with --load-state, which restores the inbox");
+        process::exit(64);
+    }
+    let script = if !matches.free.is_empty() {
+        matches.free[0].clone()
+    } else {
+        print_usage(program, opts);
+        process::exit(64);
+    };
+
+    let program = load_program(&script);
+    let mut interpreter = SimpleInterpreter::new();
+    if let Some(state_path) = matches.opt_str("load-state") {
+        match state::parse(&read_file(&state_path)) {
+            Ok(snapshot) if snapshot.cursor <= program.len() => interpreter.restore(&snapshot),
+            Ok(snapshot) => {
+                error!(
+                    "{}: cursor {} is past the end of {}",
+                    state_path, snapshot.cursor, script
+                );
+                process::exit(1);
+            }
+            Err(e) => {
+                error!("{}: {:?}", state_path, e);
+                process::exit(1);
+            }
+        }
+    }
+    if let Some(input_path) = input {
+        debug!("Input file is {:?}.", input_path);
+        interpreter.set_inbox(read_file(&input_path));
+        debug!("InBox is {:?}.", interpreter.show_inbox());
+    } else {
+        debug!("Input file is empty.");
+    }
+    if let Some(expected_path) = matches.opt_str("e") {
+        match read_file(&expected_path)
+            .split_whitespace()
+            .map(|value| value.parse::<i16>())
+            .collect::<Result<Vec<i16>, _>>()
+        {
+            Ok(expected) => interpreter.set_expected_outbox(&expected),
+            Err(e) => {
+                error!("{}: {}", expected_path, e);
+                process::exit(1);
+            }
+        }
+    }
+
+    let stop_after: Option<usize> = parse_option(&matches, "stop-after");
+    info!("START interpreter.eval");
+    let exit_status = interpreter.load(&program).and_then(|()| {
+        while stop_after.is_none_or(|steps| interpreter.steps() < steps) {
+            if !interpreter.step()? {
+                break;
+            }
+        }
+        Ok(())
+    });
+    info!("END interpreter.eval");
+
+    if let Err(e) = &exit_status {
+        error!("{:?}", e);
+    }
+    if let Some(state_path) = matches.opt_str("save-state") {
+        File::create(&state_path)
+            .and_then(|mut file| file.write_all(state::write(&interpreter.snapshot()).as_bytes()))
+            .expect("failed to write the state file");
+    }
+    if exit_status.is_err() {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,33 @@
+//! `hrm superopt`: searches for the shortest equivalent program.
+
+use super::{load_program, parse_args, print_usage, search_level, search_options, write_search};
+use getopts::Options;
+use hrm::superopt;
+use std::process;
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    search_options(&mut opts);
+    opts.optopt(
+        "o",
+        "output",
+        "write the program found to this file",
+        "NAME",
+    );
+    opts.optflag("h", "help", "print this help menu");
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") || matches.free.is_empty() {
+        print_usage(program, opts);
+        process::exit(64);
+    }
+
+    let (level, budget) = search_level(&matches);
+    let search = match superopt::superoptimize(&load_program(&matches.free[0]), &level, &budget) {
+        Ok(search) => search,
+        Err(e) => {
+            error!("{:?}", e);
+            process::exit(1);
+        }
+    };
+    write_search(search, &budget, matches.opt_str("o"));
+}
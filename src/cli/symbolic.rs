@@ -0,0 +1,138 @@
+//! `hrm symbolic`: explores the paths of a program symbolically.
+
+use super::{load_program, parse_args, parse_floor, parse_option, print_usage, show_values};
+use getopts::Options;
+use hrm::interpreter::FLOOR_SIZE;
+use hrm::lexer::TokenKind;
+use hrm::symbolic::{self, End, Limits};
+use std::process;
+
+fn show_path(path: &symbolic::Path) -> String {
+    let end = match &path.end {
+        End::Finished => "finished".to_string(),
+        End::Error(e) => format!("{:?} at {}:{}", e.value, e.location.line, e.location.col),
+        End::DepthExceeded => "cut".to_string(),
+        End::Abandoned => "abandoned".to_string(),
+    };
+    let inbox = match &path.witness {
+        Some(witness) => show_values(witness),
+        None => "unknown".to_string(),
+    };
+    let outbox: Vec<String> = path.outbox.iter().map(|value| value.to_string()).collect();
+    let mut text = format!("{}, inbox {}, outbox [{}]", end, inbox, outbox.join(", "));
+    if !path.constraints.is_empty() {
+        let constraints: Vec<String> = path.constraints.iter().map(|c| c.to_string()).collect();
+        text.push_str(&format!(" when {}", constraints.join(", ")));
+    }
+    text
+}
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    opts.optopt(
+        "",
+        "floor",
+        "comma separated tile=value pairs on the floor at the start",
+        "LIST",
+    );
+    opts.optopt(
+        "",
+        "depth",
+        "steps after which a path is cut (default: 100)",
+        "N",
+    );
+    opts.optopt(
+        "",
+        "paths",
+        "paths after which the exploration stops (default: 10000)",
+        "N",
+    );
+    opts.optflag("v", "verbose", "print every path");
+    opts.optflag("h", "help", "print this help menu");
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") || matches.free.is_empty() {
+        print_usage(program, opts);
+        process::exit(64);
+    }
+
+    let floor = matches
+        .opt_str("floor")
+        .map_or(Vec::new(), |list| parse_floor(&list, FLOOR_SIZE));
+    let mut limits = Limits::default();
+    if let Some(depth) = parse_option(&matches, "depth") {
+        limits.depth = depth;
+    }
+    if let Some(paths) = parse_option(&matches, "paths") {
+        limits.paths = paths;
+    }
+    let source = load_program(&matches.free[0]);
+    let exploration = match symbolic::explore(&source, &floor, &limits) {
+        Ok(exploration) => exploration,
+        Err(e) => {
+            error!("{:?}", e);
+            process::exit(1);
+        }
+    };
+
+    let count = |end: fn(&End) -> bool| exploration.paths.iter().filter(|p| end(&p.end)).count();
+    println!(
+        "{} paths: {} finished, {} failed, {} cut after {} steps, {} abandoned{}",
+        exploration.paths.len(),
+        count(|end| *end == End::Finished),
+        count(|end| matches!(end, End::Error(_))),
+        count(|end| *end == End::DepthExceeded),
+        limits.depth,
+        count(|end| *end == End::Abandoned),
+        if exploration.truncated {
+            format!(", stopped at the limit of {} paths", limits.paths)
+        } else {
+            String::new()
+        }
+    );
+    if matches.opt_present("v") {
+        for (index, path) in exploration.paths.iter().enumerate() {
+            println!("  path {}: {}", index + 1, show_path(path));
+        }
+    }
+    let errors = exploration.errors();
+    if !errors.is_empty() {
+        println!("errors:");
+        for path in errors {
+            println!("  {}", show_path(path));
+        }
+    }
+
+    // ops are the instructions of the program without the labels.
+    let instructions: Vec<_> = source
+        .iter()
+        .filter(|token| !matches!(token.value, TokenKind::JumpTarget(_)))
+        .collect();
+    let covered = exploration
+        .covered
+        .iter()
+        .filter(|&&covered| covered)
+        .count();
+    println!(
+        "coverage: {} of {} instructions",
+        covered,
+        instructions.len()
+    );
+    for (index, token) in instructions.iter().enumerate() {
+        let at = format!(
+            "{}:{} {}",
+            token.location.line, token.location.col, token.value
+        );
+        if !exploration.covered[index] {
+            println!("  never executed: {}", at);
+            continue;
+        }
+        if let TokenKind::JumpIfZero(_) | TokenKind::JumpIfNeg(_) = token.value {
+            if !exploration.jumped[index] {
+                println!("  never jumps: {}", at);
+            }
+            if !exploration.fell_through[index] {
+                println!("  never falls through: {}", at);
+            }
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! `hrm synth`: searches for a program from inbox/outbox examples.
+
+use super::{parse_args, print_usage, read_file, search_level, search_options, write_search};
+use getopts::Options;
+use hrm::superopt::Example;
+use hrm::synth;
+use hrm::testcase;
+use std::process;
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    search_options(&mut opts);
+    opts.optopt(
+        "o",
+        "output",
+        "write the program found to this file",
+        "NAME",
+    );
+    opts.optflag("h", "help", "print this help menu");
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") || matches.free.is_empty() {
+        print_usage(program, opts);
+        process::exit(64);
+    }
+
+    let path = &matches.free[0];
+    let cases = match testcase::parse(&read_file(path), "example") {
+        Ok(cases) => cases,
+        Err(e) => {
+            error!("{}: {:?}", path, e);
+            process::exit(1);
+        }
+    };
+    let (mut level, budget) = search_level(&matches);
+    // without --floor, the cases give the floor, which must be the same for all of them.
+    if !matches.opt_present("floor") {
+        if cases.iter().any(|case| case.floor != cases[0].floor) {
+            eprintln!("{}: the cases start with different floors", path);
+            process::exit(1);
+        }
+        level.floor = cases[0].floor.clone();
+    }
+    let examples: Vec<Example> = cases
+        .into_iter()
+        .map(|case| (case.inbox, case.outbox))
+        .collect();
+    write_search(
+        synth::synthesize(&examples, &level, &budget),
+        &budget,
+        matches.opt_str("o"),
+    );
+}
@@ -0,0 +1,109 @@
+//! `hrm test`: runs test case files, optionally collecting coverage.
+
+use super::{load_program, parse_args, print_usage, read_file};
+use getopts::Options;
+use hrm::coverage::Coverage;
+use hrm::testcase::{self, Outcome};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::process;
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    opts.optflag(
+        "",
+        "coverage",
+        "print the source with execution counts over all the cases",
+    );
+    opts.optopt(
+        "",
+        "html",
+        "write the source with execution counts as HTML",
+        "FILE",
+    );
+    opts.optflag("h", "help", "print this help menu");
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") || matches.free.len() < 2 {
+        print_usage(program, opts);
+        process::exit(64);
+    }
+
+    let solution = load_program(&matches.free[0]);
+    let mut coverage = Coverage::new(&solution);
+    let mut passed = 0;
+    let mut failed = 0;
+    let mut steps = Vec::new();
+    for path in matches.free[1..].iter() {
+        let name = Path::new(path)
+            .file_stem()
+            .map_or(path.clone(), |stem| stem.to_string_lossy().into_owned());
+        let cases = match testcase::parse(&read_file(path), &name) {
+            Ok(cases) => cases,
+            Err(e) => {
+                error!("{}: {:?}", path, e);
+                process::exit(1);
+            }
+        };
+        for case in cases.iter() {
+            let result = testcase::run(&solution, case);
+            coverage.add(&result.report);
+            print!("test {}::{} ... ", path, case.name);
+            if result.outcome == Outcome::Passed {
+                println!("ok ({} steps)", result.report.steps);
+                passed += 1;
+                steps.push(result.report.steps);
+                continue;
+            }
+            println!("FAILED");
+            failed += 1;
+            match result.outcome {
+                Outcome::WrongOutbox => {
+                    print!("{}", testcase::diff(&case.outbox, &result.report.outbox))
+                }
+                Outcome::OverBudget => println!(
+                    "  took more than {} steps",
+                    case.max_steps.unwrap_or_default()
+                ),
+                Outcome::StepLimit => println!(
+                    "  stopped after {} steps, the program may not terminate",
+                    testcase::STEP_LIMIT
+                ),
+                Outcome::Error(e) => {
+                    println!("  {:?} at {}:{}", e.value, e.location.line, e.location.col)
+                }
+                Outcome::Passed => {}
+            }
+        }
+    }
+
+    println!();
+    println!(
+        "test result: {}. {} passed; {} failed",
+        if failed == 0 { "ok" } else { "FAILED" },
+        passed,
+        failed
+    );
+    if let (Some(min), Some(max)) = (steps.iter().min(), steps.iter().max()) {
+        println!(
+            "steps of passed cases: min {}, max {}, mean {:.1}",
+            min,
+            max,
+            steps.iter().sum::<usize>() as f64 / steps.len() as f64
+        );
+    }
+    let source = read_file(&matches.free[0]);
+    if matches.opt_present("coverage") {
+        println!();
+        print!("{}", coverage.to_text(&solution, &source));
+    }
+    if let Some(path) = matches.opt_str("html") {
+        let html = coverage.to_html(&solution, &source, &matches.free[0]);
+        File::create(&path)
+            .and_then(|mut file| file.write_all(html.as_bytes()))
+            .expect("failed to write the coverage report");
+    }
+    if failed > 0 {
+        process::exit(1);
+    }
+}
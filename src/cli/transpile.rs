@@ -0,0 +1,34 @@
+//! `hrm transpile`: emits a Rust or C program.
+
+use super::{load_program, parse_args, print_usage, write_output};
+use getopts::Options;
+use hrm::transpile::{self, Target};
+use std::process;
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    opts.optopt("t", "target", "rust (default) or c", "TARGET");
+    opts.optopt("o", "output", "write the source to this file", "NAME");
+    opts.optflag("h", "help", "print this help menu");
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") || matches.free.is_empty() {
+        print_usage(program, opts);
+        process::exit(64);
+    }
+
+    let target = match matches.opt_str("t").as_deref() {
+        None | Some("rust") => Target::Rust,
+        Some("c") => Target::C,
+        Some(target) => {
+            eprintln!("unknown target: {}", target);
+            process::exit(64);
+        }
+    };
+    match transpile::transpile(&load_program(&matches.free[0]), target) {
+        Ok(source) => write_output(matches.opt_str("o"), &source),
+        Err(e) => {
+            error!("{:?}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! `hrm tui`: steps through a run in the terminal.
+
+use super::{load_program, parse_args, print_usage, read_file};
+use getopts::Options;
+use hrm::interpreter::SimpleInterpreter;
+use hrm::tui::Visualiser;
+use std::process;
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    opts.optopt("i", "input", "set input file name", "NAME");
+    opts.optflag("h", "help", "print this help menu");
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") || matches.free.is_empty() {
+        print_usage(program, opts);
+        process::exit(64);
+    }
+
+    let mut interpreter = SimpleInterpreter::new();
+    if let Some(input_path) = matches.opt_str("i") {
+        interpreter.set_inbox(read_file(&input_path));
+    }
+    let mut visualiser = match Visualiser::new(&load_program(&matches.free[0]), interpreter) {
+        Ok(visualiser) => visualiser,
+        Err(e) => {
+            error!("{:?}", e);
+            process::exit(1);
+        }
+    };
+    visualiser
+        .run()
+        .expect("something went wrong drawing the terminal");
+}
@@ -0,0 +1,30 @@
+//! `hrm wasm`: emits a WebAssembly text module.
+
+use super::{load_program, parse_args, print_usage, write_output};
+use getopts::Options;
+use hrm::wasm;
+use std::process;
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    opts.optopt(
+        "o",
+        "output",
+        "write the WebAssembly text to this file",
+        "NAME",
+    );
+    opts.optflag("h", "help", "print this help menu");
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") || matches.free.is_empty() {
+        print_usage(program, opts);
+        process::exit(64);
+    }
+
+    match wasm::to_wat(&load_program(&matches.free[0])) {
+        Ok(wat) => write_output(matches.opt_str("o"), &wat),
+        Err(e) => {
+            error!("{:?}", e);
+            process::exit(1);
+        }
+    }
+}
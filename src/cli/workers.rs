@@ -0,0 +1,145 @@
+//! `hrm workers`: runs programs side by side over a shared floor.
+
+use super::{
+    load_program, parse_args, parse_floor, parse_option, print_usage, read_file, show_values,
+};
+use getopts::Options;
+use hrm::interpreter::FLOOR_SIZE;
+use hrm::lexer::{self, Program};
+use hrm::workers::{self, Scheduler};
+use std::process;
+
+pub fn run(program: &str, args: &[String]) {
+    let mut opts = Options::new();
+    opts.optopt("i", "input", "set input file name", "NAME");
+    opts.optopt(
+        "",
+        "floor",
+        "comma separated tile=value pairs on the floor at the start",
+        "LIST",
+    );
+    opts.optopt(
+        "",
+        "schedule",
+        "round-robin or random (default: round-robin)",
+        "NAME",
+    );
+    opts.optopt(
+        "",
+        "quantum",
+        "instructions of a round-robin turn (default: 1)",
+        "N",
+    );
+    opts.optopt("", "seed", "seed of the random schedule (default: 0)", "N");
+    opts.optopt(
+        "",
+        "steps",
+        "steps of all the workers after which the run is stopped (default: 1000000)",
+        "N",
+    );
+    opts.optflag("h", "help", "print this help menu");
+    let matches = parse_args(&opts, args);
+    if matches.opt_present("h") || matches.free.is_empty() {
+        print_usage(program, opts);
+        process::exit(64);
+    }
+
+    let scripts = &matches.free;
+    let programs: Vec<Program> = scripts.iter().map(|script| load_program(script)).collect();
+    let inbox = match matches.opt_str("i").map(|path| {
+        read_file(&path)
+            .split_whitespace()
+            .map(|value| value.parse::<i16>())
+            .collect::<Result<Vec<i16>, _>>()
+    }) {
+        Some(Ok(inbox)) => inbox,
+        Some(Err(e)) => {
+            error!("{}", e);
+            process::exit(1);
+        }
+        None => Vec::new(),
+    };
+    let floor = matches
+        .opt_str("floor")
+        .map_or(Vec::new(), |list| parse_floor(&list, FLOOR_SIZE));
+    let scheduler = match matches.opt_str("schedule").as_deref() {
+        None | Some("round-robin") => Scheduler::RoundRobin {
+            quantum: parse_option(&matches, "quantum").unwrap_or(1),
+        },
+        Some("random") => Scheduler::Random {
+            seed: parse_option(&matches, "seed").unwrap_or(0),
+        },
+        Some(schedule) => {
+            eprintln!("unknown schedule: {}", schedule);
+            process::exit(64);
+        }
+    };
+    let config = workers::Config {
+        scheduler,
+        max_steps: parse_option(&matches, "steps").unwrap_or(workers::Config::default().max_steps),
+    };
+
+    let at = |worker: usize, location: lexer::Location| {
+        format!("{}:{}:{}", scripts[worker], location.line, location.col)
+    };
+    let run = match workers::run(&programs, &inbox, &floor, &config) {
+        Ok(run) => run,
+        Err(workers::SetupError::Program { worker, error }) => {
+            error!("{}: {:?}", at(worker, error.location), error.value);
+            process::exit(1);
+        }
+        Err(e) => {
+            error!("{:?}", e);
+            process::exit(1);
+        }
+    };
+    let tiles: Vec<String> = run
+        .floor
+        .iter()
+        .enumerate()
+        .filter_map(|(tile, value)| value.map(|value| format!("{}={}", tile, value)))
+        .collect();
+    println!("outbox: {}", show_values(&run.outbox));
+    println!("floor: {}", tiles.join(" "));
+    for (worker, steps) in run.steps.iter().enumerate() {
+        println!("worker {} ({}): {} steps", worker, scripts[worker], steps);
+    }
+    for conflict in run.conflicts.iter() {
+        println!(
+            "{}: step {}: worker {} overwrote tile {} written by worker {} without reading it",
+            at(conflict.worker, conflict.location),
+            conflict.step,
+            conflict.worker,
+            conflict.tile,
+            conflict.overwritten
+        );
+    }
+    match run.end {
+        workers::End::Finished => {}
+        workers::End::Error { worker, error } => {
+            println!(
+                "{}: worker {} failed: {:?}",
+                at(worker, error.location),
+                worker,
+                error.value
+            );
+            process::exit(1);
+        }
+        workers::End::Deadlock(waiting) => {
+            println!("deadlock:");
+            for waiting in waiting.iter() {
+                println!(
+                    "  {}: worker {} waits for tile {}",
+                    at(waiting.worker, waiting.location),
+                    waiting.worker,
+                    waiting.tile
+                );
+            }
+            process::exit(1);
+        }
+        workers::End::StepLimitExceeded => {
+            println!("stopped after {} steps", config.max_steps);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! Coverage of a program over many runs: how often each instruction was executed and each
+//! conditional jump went either way, shown in the margin of the source.
+
+use crate::interpreter::RunReport;
+use crate::lexer::{Program, TokenKind};
+
+/// Counts of a program added up over runs of `SimpleInterpreter`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Coverage {
+    pub runs: usize,
+    /// How many times each instruction was executed.
+    pub hits: Vec<usize>,
+    /// How many times each conditional jump was taken and not taken.
+    pub branches: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Status {
+    Covered,
+    /// A conditional jump which always went the same way.
+    Partial,
+    Never,
+}
+
+/// Counts shown beside a line of the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Margin {
+    hits: usize,
+    branch: Option<(usize, usize)>,
+}
+
+impl Margin {
+    fn status(&self) -> Status {
+        match self.branch {
+            _ if self.hits == 0 => Status::Never,
+            Some((taken, not_taken)) if taken == 0 || not_taken == 0 => Status::Partial,
+            _ => Status::Covered,
+        }
+    }
+    fn branch(&self) -> String {
+        self.branch.map_or(String::new(), |(taken, not_taken)| {
+            format!("{}/{}", taken, not_taken)
+        })
+    }
+}
+
+fn is_instruction(kind: &TokenKind) -> bool {
+    !matches!(kind, TokenKind::JumpTarget(_))
+}
+
+fn is_conditional(kind: &TokenKind) -> bool {
+    matches!(kind, TokenKind::JumpIfZero(_) | TokenKind::JumpIfNeg(_))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+impl Coverage {
+    pub fn new(program: &Program) -> Self {
+        Self {
+            runs: 0,
+            hits: vec![0; program.len()],
+            branches: vec![(0, 0); program.len()],
+        }
+    }
+
+    /// Adds the counts of a run of the program.
+    pub fn add(&mut self, report: &RunReport) {
+        self.runs += 1;
+        for (total, hits) in self.hits.iter_mut().zip(report.hits.iter()) {
+            *total += hits;
+        }
+        for (total, branch) in self.branches.iter_mut().zip(report.branches.iter()) {
+            total.0 += branch.0;
+            total.1 += branch.1;
+        }
+    }
+
+    /// Instructions executed at least once, and all the instructions.
+    pub fn executed(&self, program: &Program) -> (usize, usize) {
+        let instructions = program
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| is_instruction(&t.value));
+        let executed = instructions
+            .clone()
+            .filter(|(i, _)| self.hits[*i] > 0)
+            .count();
+        (executed, instructions.count())
+    }
+
+    /// Directions taken at least once by the conditional jumps, and all their directions.
+    pub fn directions(&self, program: &Program) -> (usize, usize) {
+        let jumps: Vec<_> = (0..program.len())
+            .filter(|&i| is_conditional(&program[i].value))
+            .collect();
+        let taken = jumps
+            .iter()
+            .map(|&i| (self.branches[i].0 > 0) as usize + (self.branches[i].1 > 0) as usize)
+            .sum();
+        (taken, jumps.len() * 2)
+    }
+
+    fn summary(&self, program: &Program) -> String {
+        let (executed, instructions) = self.executed(program);
+        let (taken, directions) = self.directions(program);
+        format!(
+            "{} of {} instructions executed, {} of {} jump directions taken, over {} run{}",
+            executed,
+            instructions,
+            taken,
+            directions,
+            self.runs,
+            if self.runs == 1 { "" } else { "s" }
+        )
+    }
+
+    /// Counts for each line of `source`, from the first instruction on the line.
+    fn margins(&self, program: &Program, source: &str) -> Vec<Option<Margin>> {
+        let mut margins = vec![None; source.lines().count()];
+        for (i, token) in program.iter().enumerate() {
+            if !is_instruction(&token.value) {
+                continue;
+            }
+            if let Some(margin @ None) = margins.get_mut(token.location.line - 1) {
+                *margin = Some(Margin {
+                    hits: self.hits[i],
+                    branch: Some(self.branches[i]).filter(|_| is_conditional(&token.value)),
+                });
+            }
+        }
+        margins
+    }
+
+    /// `source` with the hits of each line and the taken/not taken counts of conditional jumps
+    /// in the margin. Lines never executed, and jumps which always went the same way, are
+    /// marked with `!`.
+    pub fn to_text(&self, program: &Program, source: &str) -> String {
+        let mut out = format!("{:>8} {:>11}   |\n", "hits", "taken/not");
+        for (line, margin) in source.lines().zip(self.margins(program, source)) {
+            let (hits, branch, mark) = match margin {
+                Some(margin) => (
+                    margin.hits.to_string(),
+                    margin.branch(),
+                    if margin.status() == Status::Covered {
+                        ' '
+                    } else {
+                        '!'
+                    },
+                ),
+                None => (String::new(), String::new(), ' '),
+            };
+            let row = format!("{:>8} {:>11} {} | {}", hits, branch, mark, line);
+            out.push_str(row.trim_end());
+            out.push('\n');
+        }
+        out.push_str(&self.summary(program));
+        out.push('\n');
+        out
+    }
+
+    /// The listing of `to_text` as a standalone HTML page, with lines never executed and jumps
+    /// which always went the same way highlighted.
+    pub fn to_html(&self, program: &Program, source: &str, title: &str) -> String {
+        let mut rows = String::new();
+        for (line, margin) in source.lines().zip(self.margins(program, source)) {
+            let (class, hits, branch) = match margin {
+                Some(margin) => (
+                    match margin.status() {
+                        Status::Covered => " class=\"covered\"",
+                        Status::Partial => " class=\"partial\"",
+                        Status::Never => " class=\"never\"",
+                    },
+                    margin.hits.to_string(),
+                    margin.branch(),
+                ),
+                None => ("", String::new(), String::new()),
+            };
+            rows.push_str(&format!(
+                "<tr{}><td class=\"count\">{}</td><td class=\"count\">{}</td><td>{}</td></tr>\n",
+                class,
+                hits,
+                branch,
+                escape(line)
+            ));
+        }
+        format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{0}</title>
+<style>
+body {{ font-family: monospace; }}
+table {{ border-collapse: collapse; }}
+th, td {{ padding: 0 0.5em; white-space: pre; text-align: left; }}
+td.count {{ text-align: right; color: #666; }}
+tr.covered {{ background: #e6ffe6; }}
+tr.partial {{ background: #fff5cc; }}
+tr.never {{ background: #ffe0e0; }}
+</style>
+</head>
+<body>
+<h1>{0}</h1>
+<p>{1}</p>
+<table>
+<tr><th>hits</th><th>taken/not</th><th></th></tr>
+{2}</table>
+</body>
+</html>
+"#,
+            escape(title),
+            self.summary(program),
+            rows
+        )
+    }
+}
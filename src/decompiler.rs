@@ -0,0 +1,380 @@
+use crate::cfg::{dominators, immediate_dominator, BlockGraph, ControlFlowGraph, EdgeKind};
+use crate::interpreter::{InterpreterError, InterpreterErrorKind};
+use crate::lexer::{Program, TokenKind};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Node {
+    Stmt(String),
+    Label(usize),
+    Loop(Vec<Node>),
+    If(Condition, Vec<Node>, Vec<Node>),
+    Break,
+    Continue,
+    Return,
+    Goto(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Condition {
+    Zero,
+    Neg,
+}
+impl Condition {
+    fn text(self, negated: bool) -> &'static str {
+        match (self, negated) {
+            (Condition::Zero, false) => "hand == 0",
+            (Condition::Zero, true) => "hand != 0",
+            (Condition::Neg, false) => "hand < 0",
+            (Condition::Neg, true) => "hand >= 0",
+        }
+    }
+}
+
+fn statement(kind: &TokenKind) -> Option<String> {
+    let text = match kind {
+        TokenKind::InBox => "hand = inbox();".to_string(),
+        TokenKind::OutBox => "outbox(hand);".to_string(),
+        TokenKind::CopyFrom(index) => format!("hand = t{};", index),
+        TokenKind::CopyTo(index) => format!("t{} = hand;", index),
+        TokenKind::Add(index) => format!("hand = hand + t{};", index),
+        TokenKind::Sub(index) => format!("hand = hand - t{};", index),
+        TokenKind::BumpPlus(index) => format!("hand = ++t{};", index),
+        TokenKind::BumpMinus(index) => format!("hand = --t{};", index),
+        _ => return None,
+    };
+    Some(text)
+}
+
+/// Drops `continue` where the end of a loop body continues anyway.
+fn trim_continue(nodes: &mut Vec<Node>) {
+    match nodes.last_mut() {
+        Some(Node::Continue) => {
+            nodes.pop();
+        }
+        Some(Node::If(_, then, otherwise)) => {
+            trim_continue(then);
+            trim_continue(otherwise);
+        }
+        _ => {}
+    }
+}
+
+/// `nodes` without the labels no `goto` refers to.
+fn visible<'a>(nodes: &'a [Node], targets: &[bool]) -> Vec<&'a Node> {
+    nodes
+        .iter()
+        .filter(|node| !matches!(node, Node::Label(block) if !targets[*block]))
+        .collect()
+}
+
+/// Sets over blocks as bit vectors, `blocks.len() + 1` wide to include the exit.
+type BlockSet = Vec<bool>;
+
+struct LoopInfo {
+    header: usize,
+    body: BlockSet,
+    follow: Option<usize>,
+}
+
+struct Decompiler<'a> {
+    program: &'a Program,
+    graph: BlockGraph,
+    /// Natural loop headed by each block, if any.
+    loops: Vec<Option<(BlockSet, Option<usize>)>>,
+    post_dominators: Vec<BlockSet>,
+    emitted: Vec<bool>,
+    entered: Vec<bool>,
+    loop_stack: Vec<LoopInfo>,
+}
+
+impl<'a> Decompiler<'a> {
+    fn new(program: &'a Program) -> Self {
+        let graph = BlockGraph::new(program);
+        let n = graph.blocks.len() + 1;
+        let exit = graph.exit();
+        let mut edges: Vec<Vec<usize>> = vec![Vec::new(); n];
+        for (i, block) in graph.blocks.iter().enumerate() {
+            edges[i] = block.successors.iter().map(|edge| edge.to).collect();
+        }
+        let dom = dominators(&edges, 0);
+
+        let mut loops = vec![None; n];
+        for (header, slot) in loops.iter_mut().enumerate().take(exit) {
+            let latches: Vec<usize> = (0..exit)
+                .filter(|&p| {
+                    edges[p].contains(&header) && dom[p].get(header).copied().unwrap_or(false)
+                })
+                .collect();
+            if latches.is_empty() {
+                continue;
+            }
+            let mut body = vec![false; n];
+            body[header] = true;
+            let mut stack = latches;
+            while let Some(node) = stack.pop() {
+                if !body[node] {
+                    body[node] = true;
+                    stack.extend((0..exit).filter(|&p| edges[p].contains(&node)));
+                }
+            }
+            let follow = (0..exit)
+                .filter(|&b| body[b])
+                .flat_map(|b| edges[b].iter().copied())
+                .filter(|&to| !body[to])
+                .min();
+            *slot = Some((body, follow));
+        }
+
+        // an `inbox` on an empty inbox ends the program.
+        let mut reverse: Vec<Vec<usize>> = vec![Vec::new(); n];
+        for (from, tos) in edges.iter().enumerate() {
+            for &to in tos.iter() {
+                reverse[to].push(from);
+            }
+        }
+        for (i, block) in graph.blocks.iter().enumerate() {
+            if program[block.start..block.end]
+                .iter()
+                .any(|token| token.value == TokenKind::InBox)
+            {
+                reverse[exit].push(i);
+            }
+        }
+        let post_dominators = dominators(&reverse, exit);
+
+        Self {
+            program,
+            graph,
+            loops,
+            post_dominators,
+            emitted: vec![false; n],
+            entered: vec![false; n],
+            loop_stack: Vec::new(),
+        }
+    }
+
+    /// Decides how to continue at `target`: returns the block to emit next,
+    /// or `None` after emitting a jump out of the current region.
+    fn transfer(&self, target: usize, stop: Option<usize>, out: &mut Vec<Node>) -> Option<usize> {
+        if Some(target) == stop {
+            return None;
+        }
+        if let Some(innermost) = self.loop_stack.last() {
+            if target == innermost.header {
+                out.push(Node::Continue);
+                return None;
+            }
+            if Some(target) == innermost.follow {
+                out.push(Node::Break);
+                return None;
+            }
+        }
+        if target == self.graph.exit() {
+            out.push(Node::Return);
+            return None;
+        }
+        if self.emitted[target] {
+            out.push(Node::Goto(target));
+            return None;
+        }
+        Some(target)
+    }
+
+    fn branch(&mut self, target: usize, join: Option<usize>) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        if let Some(next) = self.transfer(target, join, &mut nodes) {
+            self.region(next, join, &mut nodes);
+        }
+        nodes
+    }
+
+    /// Emits blocks from `current` until control reaches `stop` or leaves the region.
+    fn region(&mut self, mut current: usize, stop: Option<usize>, out: &mut Vec<Node>) {
+        loop {
+            if let (Some((body, follow)), false) = (&self.loops[current], self.entered[current]) {
+                let info = LoopInfo {
+                    header: current,
+                    body: body.clone(),
+                    follow: *follow,
+                };
+                self.entered[current] = true;
+                self.loop_stack.push(info);
+                let mut nodes = Vec::new();
+                self.region(current, None, &mut nodes);
+                let info = self.loop_stack.pop().unwrap();
+                trim_continue(&mut nodes);
+                out.push(Node::Loop(nodes));
+                match info
+                    .follow
+                    .and_then(|follow| self.transfer(follow, stop, out))
+                {
+                    Some(next) => {
+                        current = next;
+                        continue;
+                    }
+                    None => return,
+                }
+            }
+
+            self.emitted[current] = true;
+            out.push(Node::Label(current));
+            let block = &self.graph.blocks[current];
+            for token in self.program[block.start..block.end].iter() {
+                if let Some(text) = statement(&token.value) {
+                    out.push(Node::Stmt(text));
+                }
+            }
+
+            let successors = block.successors.clone();
+            let conditional = successors
+                .iter()
+                .find(|edge| edge.kind == EdgeKind::JumpIfZero || edge.kind == EdgeKind::JumpIfNeg)
+                .copied();
+            let next = match conditional {
+                Some(taken) => {
+                    let fallthrough = successors
+                        .iter()
+                        .find(|edge| edge.kind == EdgeKind::Fallthrough)
+                        .map(|edge| edge.to)
+                        .unwrap_or_else(|| self.graph.exit());
+                    let join =
+                        immediate_dominator(&self.post_dominators, current).filter(|&join| {
+                            self.loop_stack
+                                .last()
+                                .is_none_or(|innermost| innermost.body[join])
+                        });
+                    let condition = if taken.kind == EdgeKind::JumpIfZero {
+                        Condition::Zero
+                    } else {
+                        Condition::Neg
+                    };
+                    let then = self.branch(taken.to, join);
+                    let otherwise = self.branch(fallthrough, join);
+                    out.push(Node::If(condition, then, otherwise));
+                    join.and_then(|join| self.transfer(join, stop, out))
+                }
+                None => match successors.first() {
+                    Some(edge) => self.transfer(edge.to, stop, out),
+                    None => None,
+                },
+            };
+            match next {
+                Some(next) => current = next,
+                None => return,
+            }
+        }
+    }
+
+    fn label_name(&self, block: usize) -> String {
+        match &self.graph.blocks[block].label {
+            Some(label) => label.clone(),
+            None => format!("b{}", block),
+        }
+    }
+
+    fn gotos(nodes: &[Node], targets: &mut Vec<bool>) {
+        for node in nodes.iter() {
+            match node {
+                Node::Goto(block) => targets[*block] = true,
+                Node::Loop(body) => Self::gotos(body, targets),
+                Node::If(_, then, otherwise) => {
+                    Self::gotos(then, targets);
+                    Self::gotos(otherwise, targets);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Prints an `if` opened with `opener`, chaining a lone `if` in the else branch.
+    #[allow(clippy::too_many_arguments)]
+    fn print_if(
+        &self,
+        condition: Condition,
+        then: &[Node],
+        otherwise: &[Node],
+        opener: &str,
+        depth: usize,
+        targets: &[bool],
+        out: &mut String,
+    ) {
+        let indent = "    ".repeat(depth);
+        let (text, then, otherwise) = if visible(then, targets).is_empty() {
+            (condition.text(true), otherwise, then)
+        } else {
+            (condition.text(false), then, otherwise)
+        };
+        out.push_str(&format!("{}{} {} {{\n", indent, opener, text));
+        self.print(then, depth + 1, targets, out);
+        match visible(otherwise, targets).as_slice() {
+            [] => out.push_str(&format!("{}}}\n", indent)),
+            [Node::If(condition, then, otherwise)] => self.print_if(
+                *condition,
+                then,
+                otherwise,
+                "} else if",
+                depth,
+                targets,
+                out,
+            ),
+            _ => {
+                out.push_str(&format!("{}}} else {{\n", indent));
+                self.print(otherwise, depth + 1, targets, out);
+                out.push_str(&format!("{}}}\n", indent));
+            }
+        }
+    }
+
+    fn print(&self, nodes: &[Node], depth: usize, targets: &[bool], out: &mut String) {
+        let indent = "    ".repeat(depth);
+        for node in nodes.iter() {
+            match node {
+                Node::Stmt(text) => out.push_str(&format!("{}{}\n", indent, text)),
+                Node::Label(block) => {
+                    if targets[*block] {
+                        out.push_str(&format!("{}:\n", self.label_name(*block)));
+                    }
+                }
+                Node::Loop(body) => {
+                    out.push_str(&format!("{}loop {{\n", indent));
+                    self.print(body, depth + 1, targets, out);
+                    out.push_str(&format!("{}}}\n", indent));
+                }
+                Node::If(condition, then, otherwise) => {
+                    self.print_if(*condition, then, otherwise, "if", depth, targets, out)
+                }
+                Node::Break => out.push_str(&format!("{}break;\n", indent)),
+                Node::Continue => out.push_str(&format!("{}continue;\n", indent)),
+                Node::Return => out.push_str(&format!("{}return;\n", indent)),
+                Node::Goto(block) => {
+                    out.push_str(&format!("{}goto {};\n", indent, self.label_name(*block)))
+                }
+            }
+        }
+    }
+}
+
+/// Recovers `loop` and `if`/`else` from the control flow of `program` and prints it as
+/// indented pseudocode. Flow which does not fit them is printed as labels and `goto`s.
+///
+/// The hand is `hand`, floor tile `i` is `ti`, and the program ends on `return`
+/// or when `inbox()` finds the inbox empty.
+pub fn decompile(program: &Program) -> Result<String, InterpreterError> {
+    if let Some(&index) = ControlFlowGraph::new(program).unresolved_jumps().first() {
+        return Err(InterpreterError {
+            value: InterpreterErrorKind::UnexistedJumpTarget,
+            location: program[index].location,
+        });
+    }
+    let mut decompiler = Decompiler::new(program);
+    let mut nodes = Vec::new();
+    let exit = decompiler.graph.exit();
+    if exit > 0 {
+        decompiler.region(0, Some(exit), &mut nodes);
+    }
+    let mut targets = vec![false; exit + 1];
+    Decompiler::gotos(&nodes, &mut targets);
+    let mut out = String::new();
+    decompiler.print(&nodes, 0, &targets, &mut out);
+    Ok(out)
+}
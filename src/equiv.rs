@@ -0,0 +1,102 @@
+//! Equivalence checking of two programs by running them on the same inboxes: every inbox of
+//! up to `superopt::EXHAUSTIVE_LENGTH` small values, and `RANDOM_INBOXES` random ones.
+
+use crate::bytecode::{Bytecode, BytecodeInterpreter};
+use crate::interpreter::{InterpreterError, InterpreterErrorKind};
+use crate::lexer::Program;
+use crate::superopt;
+
+/// Random inboxes run after the exhaustive ones.
+pub const RANDOM_INBOXES: usize = 1000;
+
+/// What a program does with an inbox.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Behaviour {
+    pub outbox: Vec<i16>,
+    /// The error ending the run, if any.
+    pub error: Option<InterpreterError>,
+}
+
+impl Behaviour {
+    /// Whether `self` and `other` put the same values into the outbox and fail the same way.
+    /// Runs stopped at the step limit are alike whatever they put into the outbox.
+    fn same(&self, other: &Behaviour) -> bool {
+        let kind = |behaviour: &Behaviour| behaviour.error.as_ref().map(|e| e.value);
+        let limit = Some(InterpreterErrorKind::StepLimitExceeded);
+        kind(self) == kind(other) && (kind(self) == limit || self.outbox == other.outbox)
+    }
+}
+
+/// An inbox on which two programs behave differently.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Difference {
+    pub inbox: Vec<i16>,
+    pub left: Behaviour,
+    pub right: Behaviour,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Equivalence {
+    /// Number of inboxes both programs ran on.
+    pub inboxes: usize,
+    /// The smallest inbox telling the programs apart: the shortest, then the one with the
+    /// smallest values.
+    pub difference: Option<Difference>,
+}
+
+fn behaviour(interpreter: &mut BytecodeInterpreter, code: &Bytecode, inbox: &[i16]) -> Behaviour {
+    interpreter.set_inbox_values(inbox);
+    let error = interpreter.eval(code).err();
+    Behaviour {
+        outbox: interpreter.report().outbox,
+        error,
+    }
+}
+
+/// Runs `left` and `right` on the same inboxes with the tiles of `floor` seeded, stopping
+/// each run after `max_steps` steps.
+///
+/// Fails when either program has a jump to an undefined label.
+pub fn compare(
+    left: &Program,
+    right: &Program,
+    floor: &[(usize, i16)],
+    max_steps: usize,
+) -> Result<Equivalence, InterpreterError> {
+    let left = Bytecode::compile(left)?;
+    let right = Bytecode::compile(right)?;
+    let mut interpreter = BytecodeInterpreter::new();
+    for &(tile, value) in floor.iter() {
+        interpreter.set_tile(tile, Some(value));
+    }
+    interpreter.set_step_limit(Some(max_steps));
+
+    let inboxes: Vec<Vec<i16>> = superopt::exhaustive_inboxes()
+        .into_iter()
+        .chain(superopt::random_inboxes(RANDOM_INBOXES))
+        .collect();
+    let difference = inboxes
+        .iter()
+        .filter_map(|inbox| {
+            let left = behaviour(&mut interpreter, &left, inbox);
+            let right = behaviour(&mut interpreter, &right, inbox);
+            if left.same(&right) {
+                None
+            } else {
+                Some(Difference {
+                    inbox: inbox.clone(),
+                    left,
+                    right,
+                })
+            }
+        })
+        .min_by_key(|difference| {
+            let inbox = &difference.inbox;
+            let magnitude: i32 = inbox.iter().map(|value| (*value as i32).abs()).sum();
+            (inbox.len(), magnitude, inbox.clone())
+        });
+    Ok(Equivalence {
+        inboxes: inboxes.len(),
+        difference,
+    })
+}
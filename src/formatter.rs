@@ -0,0 +1,113 @@
+use crate::lexer::{Annotation, Lexer, Location, TokenKind};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FormatErrorKind {
+    /// Text the lexer would skip, which formatting would silently delete.
+    UnrecognisedInput(String),
+}
+pub type FormatError = Annotation<FormatErrorKind>;
+
+const INDENT: &str = "    ";
+
+enum Item {
+    Blank,
+    Comment(String),
+    Instruction(TokenKind, Option<String>),
+}
+
+/// Splits `line` into its code and its `#` comment, if any.
+fn split_comment(line: &str) -> (&str, Option<String>) {
+    match line.find('#') {
+        Some(index) => {
+            let text = line[index + 1..].trim_end();
+            let comment = if text.is_empty() || text.starts_with(' ') {
+                format!("#{}", text)
+            } else {
+                format!("# {}", text)
+            };
+            (&line[..index], Some(comment))
+        }
+        None => (line, None),
+    }
+}
+
+fn parse(source: &str) -> Result<Vec<Item>, FormatError> {
+    let mut items = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let (code, comment) = split_comment(line);
+        let tokens = Lexer::lex(code);
+        let chunks = code.split_whitespace().count();
+        if tokens
+            .iter()
+            .map(|token| token.value.width())
+            .sum::<usize>()
+            != chunks
+        {
+            let col = code.chars().take_while(|c| c.is_whitespace()).count() + 1;
+            return Err(FormatError::new(
+                FormatErrorKind::UnrecognisedInput(code.trim().to_string()),
+                Location { line: i + 1, col },
+            ));
+        }
+        if tokens.is_empty() {
+            items.push(match comment {
+                Some(comment) => Item::Comment(comment),
+                None => Item::Blank,
+            });
+            continue;
+        }
+        // a trailing comment stays with the last instruction of its line.
+        let last = tokens.len() - 1;
+        for (j, token) in tokens.into_iter().enumerate() {
+            let comment = if j == last { comment.clone() } else { None };
+            items.push(Item::Instruction(token.value, comment));
+        }
+    }
+    Ok(items)
+}
+
+/// Re-emits `source` with one instruction per line in canonical spelling, a single space
+/// before the operand and comments kept in place. Instructions following a `jump_target`
+/// are indented under it, and runs of blank lines are collapsed to one.
+pub fn format(source: &str) -> Result<String, FormatError> {
+    let items = parse(source)?;
+
+    let mut indents = vec![""; items.len()];
+    let mut indent = "";
+    for (item, slot) in items.iter().zip(indents.iter_mut()) {
+        match item {
+            Item::Instruction(TokenKind::JumpTarget(_), _) => indent = INDENT,
+            Item::Instruction(_, _) => *slot = indent,
+            _ => {}
+        }
+    }
+    // a comment line takes the indentation of the instruction it precedes.
+    let mut next = indent;
+    for (item, slot) in items.iter().zip(indents.iter_mut()).rev() {
+        match item {
+            Item::Instruction(_, _) => next = *slot,
+            _ => *slot = next,
+        }
+    }
+
+    let mut out = String::new();
+    let mut blank = false;
+    for (item, indent) in items.iter().zip(indents) {
+        let line = match item {
+            Item::Blank => {
+                blank = true;
+                continue;
+            }
+            Item::Comment(comment) => format!("{}{}", indent, comment),
+            Item::Instruction(kind, None) => format!("{}{}", indent, kind),
+            Item::Instruction(kind, Some(comment)) => format!("{}{} {}", indent, kind, comment),
+        };
+        if blank && !out.is_empty() {
+            out.push('\n');
+        }
+        blank = false;
+        out.push_str(&line);
+        out.push('\n');
+    }
+    Ok(out)
+}
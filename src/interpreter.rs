@@ -1,17 +1,88 @@
-use crate::lexer::{Annotation, Program, Token, TokenKind};
+use crate::lexer::{Annotation, Location, Program, Token, TokenKind};
 use log::{debug, trace};
 use std::collections::{HashMap, VecDeque};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum InterpreterErrorKind {
     UnexistedJumpTarget,
     UndefinedInputBox,
     EmptyInBox,
     EmptyFloorValue,
     EmptyHandValue,
+    /// A value reached the outbox at `index` where `expected` was wanted,
+    /// `None` when no more values were expected.
+    WrongOutput {
+        index: usize,
+        expected: Option<i16>,
+        actual: i16,
+    },
+    /// The program ended before putting all the expected values into the outbox.
+    NotEnoughOutput {
+        expected: usize,
+        actual: usize,
+    },
+    /// The run was stopped after taking the most steps allowed.
+    StepLimitExceeded,
+    /// `add`, `sub` or a bump made a value out of `-MAX_VALUE..=MAX_VALUE`.
+    Overflow,
+    /// An instruction refers to a tile past the end of the floor.
+    FloorOutOfRange,
+}
+
+/// Number of tiles on the floor.
+pub const FLOOR_SIZE: usize = 6;
+
+/// Largest absolute value the office can hold.
+pub const MAX_VALUE: i16 = 999;
+
+/// `value` if it is within `-MAX_VALUE..=MAX_VALUE`.
+pub fn checked_value(value: i32) -> Option<i16> {
+    if value.abs() <= MAX_VALUE as i32 {
+        Some(value as i16)
+    } else {
+        None
+    }
 }
-type InterpreterError = Annotation<InterpreterErrorKind>;
 
+pub type InterpreterError = Annotation<InterpreterErrorKind>;
+
+/// `value`, or an `Overflow` error at `command`.
+fn checked(value: i32, command: &Token) -> Result<i16, InterpreterError> {
+    checked_value(value).ok_or(InterpreterError {
+        value: InterpreterErrorKind::Overflow,
+        location: command.location,
+    })
+}
+
+/// Statistics of a run.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RunReport {
+    pub steps: usize,
+    pub outbox: Vec<i16>,
+    /// How many times each instruction of the program was executed.
+    pub hits: Vec<usize>,
+    /// How many times each conditional jump was taken and not taken, `(0, 0)` for the other
+    /// instructions.
+    pub branches: Vec<(usize, usize)>,
+}
+
+/// State of a `SimpleInterpreter` in the middle of a run.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot {
+    pub hand: Option<i16>,
+    pub cells: Vec<Option<i16>>,
+    /// Values left in the inbox, `None` when no inbox was given.
+    pub inbox: Option<Vec<i16>>,
+    pub outbox: Vec<i16>,
+    /// Index of the next instruction.
+    pub cursor: usize,
+    pub steps: usize,
+}
+
+#[derive(Clone)]
 pub struct SimpleInterpreter {
     hand: Option<i16>,
     program_cursor: usize,
@@ -20,6 +91,17 @@ pub struct SimpleInterpreter {
     inbox: Option<VecDeque<i16>>,
     jump_table: HashMap<usize, usize>,
     step_counter: usize,
+    hits: Vec<usize>,
+    branches: Vec<(usize, usize)>,
+    outbox: Vec<i16>,
+    expected_outbox: Option<Vec<i16>>,
+    print_outbox: bool,
+}
+
+impl Default for SimpleInterpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SimpleInterpreter {
@@ -27,11 +109,16 @@ impl SimpleInterpreter {
         Self {
             hand: None,
             program_cursor: 0,
-            cells: vec![],
+            cells: vec![None; FLOOR_SIZE],
             program: Vec::new(),
             inbox: None,
             jump_table: HashMap::new(),
             step_counter: 0,
+            hits: Vec::new(),
+            branches: Vec::new(),
+            outbox: Vec::new(),
+            expected_outbox: None,
+            print_outbox: true,
         }
     }
     pub fn set_inbox(&mut self, inbox: String) {
@@ -52,7 +139,7 @@ impl SimpleInterpreter {
                 _ => {
                     if let Some(v) = n {
                         if minus_flag {
-                            stream.push_back(-1 * v as i16);
+                            stream.push_back(-(v as i16));
                         } else {
                             stream.push_back(v as i16);
                         }
@@ -66,12 +153,51 @@ impl SimpleInterpreter {
         }
         self.inbox = Some(stream);
     }
+    pub fn set_inbox_values(&mut self, values: &[i16]) {
+        self.inbox = Some(values.iter().copied().collect());
+    }
+    /// Whether values put into the outbox are also printed to stdout. Defaults to `true`.
+    pub fn set_print_outbox(&mut self, print_outbox: bool) {
+        self.print_outbox = print_outbox;
+    }
+    /// Makes the run fail at the first value put into the outbox which differs from
+    /// `expected`, and when the program ends before putting all of `expected` there.
+    pub fn set_expected_outbox(&mut self, expected: &[i16]) {
+        self.expected_outbox = Some(expected.to_vec());
+    }
     pub fn show_inbox(&self) -> &Option<VecDeque<i16>> {
         &self.inbox
     }
+    pub fn hand(&self) -> Option<i16> {
+        self.hand
+    }
+    pub fn floor(&self) -> &[Option<i16>] {
+        &self.cells
+    }
+    /// Puts `value` on a floor tile before the program runs.
+    pub fn set_tile(&mut self, index: usize, value: Option<i16>) {
+        self.cells[index] = value;
+    }
+    /// Steps taken so far; labels take none.
+    pub fn steps(&self) -> usize {
+        self.step_counter
+    }
+    pub fn outbox(&self) -> &[i16] {
+        &self.outbox
+    }
+    /// Index of the next instruction to execute.
+    pub fn cursor(&self) -> usize {
+        self.program_cursor
+    }
+    pub fn set_cursor(&mut self, cursor: usize) {
+        self.program_cursor = cursor;
+    }
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
     fn eval_inbox(&mut self, command: &Token) -> Result<(), InterpreterError> {
         self.hand = if let Some(ref mut input) = self.inbox {
-            if input.len() > 0 {
+            if !input.is_empty() {
                 Some(input.pop_front().unwrap())
             } else {
                 return Err(InterpreterError {
@@ -91,7 +217,23 @@ impl SimpleInterpreter {
     }
     fn eval_outbox(&mut self, command: &Token) -> Result<(), InterpreterError> {
         if let Some(value) = self.hand {
-            println!("{}", value);
+            if let Some(expected) = self.expected_outbox.as_ref() {
+                let index = self.outbox.len();
+                if expected.get(index) != Some(&value) {
+                    return Err(InterpreterError {
+                        value: InterpreterErrorKind::WrongOutput {
+                            index,
+                            expected: expected.get(index).copied(),
+                            actual: value,
+                        },
+                        location: command.location,
+                    });
+                }
+            }
+            if self.print_outbox {
+                println!("{}", value);
+            }
+            self.outbox.push(value);
             self.hand = None;
         } else {
             return Err(InterpreterError {
@@ -133,7 +275,7 @@ impl SimpleInterpreter {
     fn eval_add(&mut self, command: &Token, index: usize) -> Result<(), InterpreterError> {
         if let Some(floor_value) = self.cells[index] {
             if let Some(ref mut hand_value) = self.hand {
-                *hand_value += floor_value;
+                *hand_value = checked(*hand_value as i32 + floor_value as i32, command)?;
             } else {
                 return Err(InterpreterError {
                     value: InterpreterErrorKind::EmptyHandValue,
@@ -153,7 +295,7 @@ impl SimpleInterpreter {
     fn eval_sub(&mut self, command: &Token, index: usize) -> Result<(), InterpreterError> {
         if let Some(floor_value) = self.cells[index] {
             if let Some(ref mut hand_value) = self.hand {
-                *hand_value -= floor_value;
+                *hand_value = checked(*hand_value as i32 - floor_value as i32, command)?;
             } else {
                 return Err(InterpreterError {
                     value: InterpreterErrorKind::EmptyHandValue,
@@ -176,9 +318,10 @@ impl SimpleInterpreter {
         index: usize,
         delta: i16,
     ) -> Result<(), InterpreterError> {
-        if let Some(ref mut floor_value) = self.cells[index] {
-            *floor_value += delta;
-            self.hand = Some(*floor_value);
+        if let Some(floor_value) = self.cells[index] {
+            let value = checked(floor_value as i32 + delta as i32, command)?;
+            self.cells[index] = Some(value);
+            self.hand = Some(value);
         } else {
             return Err(InterpreterError {
                 value: InterpreterErrorKind::EmptyFloorValue,
@@ -212,6 +355,7 @@ impl SimpleInterpreter {
         if self.hand == Some(0) {
             if let Some(line) = self.jump_table.get(&label) {
                 self.program_cursor = *line;
+                self.branches[label].0 += 1;
             } else {
                 return Err(InterpreterError {
                     value: InterpreterErrorKind::UnexistedJumpTarget,
@@ -220,6 +364,7 @@ impl SimpleInterpreter {
             }
         } else {
             self.program_cursor += 1;
+            self.branches[label].1 += 1;
         }
         self.step_counter += 1;
         Ok(())
@@ -229,6 +374,7 @@ impl SimpleInterpreter {
             if value < 0 {
                 if let Some(line) = self.jump_table.get(&label) {
                     self.program_cursor = *line;
+                    self.branches[label].0 += 1;
                 } else {
                     return Err(InterpreterError {
                         value: InterpreterErrorKind::UnexistedJumpTarget,
@@ -237,20 +383,21 @@ impl SimpleInterpreter {
                 }
             } else {
                 self.program_cursor += 1;
+                self.branches[label].1 += 1;
             }
         } else {
             self.program_cursor += 1;
+            self.branches[label].1 += 1;
         }
         self.step_counter += 1;
         Ok(())
     }
     fn init(&mut self) -> Result<usize, InterpreterError> {
-        self.cells = vec![];
-        for _ in 0..6 {
-            self.cells.push(None);
-        }
+        self.hits.resize(self.program.len(), 0);
+        self.branches.resize(self.program.len(), (0, 0));
+        self.jump_table.clear();
         let mut jump_targets = HashMap::new();
-        let mut jump_table = HashMap::new();
+        let mut jumps = Vec::new();
         for i in 0..self.program.len() {
             match &self.program[i].value {
                 TokenKind::JumpTarget(label) => {
@@ -259,61 +406,144 @@ impl SimpleInterpreter {
                 TokenKind::Jump(label)
                 | TokenKind::JumpIfZero(label)
                 | TokenKind::JumpIfNeg(label) => {
-                    jump_table.insert(i, label);
+                    jumps.push((i, label));
                 }
                 _ => {}
             };
         }
-        for (index, label) in jump_table.iter() {
-            if jump_targets.contains_key(label) {
-                self.jump_table
-                    .insert(*index, *jump_targets.get(label).unwrap());
-            } else {
-                return Err(InterpreterError {
-                    value: InterpreterErrorKind::UnexistedJumpTarget,
-                    location: self.program[*index].location,
-                });
+        // the first undefined label in the program is reported.
+        for (index, label) in jumps.iter() {
+            match jump_targets.get(label) {
+                Some(&target) => {
+                    self.jump_table.insert(*index, target);
+                }
+                None => {
+                    return Err(InterpreterError {
+                        value: InterpreterErrorKind::UnexistedJumpTarget,
+                        location: self.program[*index].location,
+                    });
+                }
             }
         }
 
         Ok(0)
     }
 
-    pub fn eval(&mut self, program: &Program) -> Result<usize, InterpreterError> {
+    /// Replaces the program and resolves its labels.
+    /// The hand, floor, inbox and cursor are kept, so execution can continue with `step`.
+    pub fn load(&mut self, program: &Program) -> Result<(), InterpreterError> {
         self.program = (*program).clone();
-        if let Err(e) = self.init() {
-            return Err(e);
-        };
-        while self.program_cursor < self.program.len() {
-            let command = &self.program[self.program_cursor].clone();
-            trace!("step:{}\tcommand:{:?}", self.step_counter, command);
-            let res = match command.value {
-                TokenKind::InBox => self.eval_inbox(command),
-                TokenKind::OutBox => self.eval_outbox(command),
-                TokenKind::CopyFrom(index) => self.eval_copy_from(command, index),
-                TokenKind::CopyTo(index) => self.eval_copy_to(command, index),
-                TokenKind::Add(index) => self.eval_add(command, index),
-                TokenKind::Sub(index) => self.eval_sub(command, index),
-                TokenKind::BumpPlus(index) => self.eval_bump_plus(command, index),
-                TokenKind::BumpMinus(index) => self.eval_bump_minus(command, index),
-                TokenKind::Jump(_) => self.eval_jump(command, self.program_cursor),
-                TokenKind::JumpIfZero(_) => self.eval_jump_if_zero(command, self.program_cursor),
-                TokenKind::JumpIfNeg(_) => self.eval_jump_if_neg(command, self.program_cursor),
-                _ => {
-                    self.program_cursor += 1;
-                    Ok(())
-                }
+        self.init()?;
+        Ok(())
+    }
+
+    /// Executes the instruction at the cursor.
+    /// Returns `false` without changing anything once the program has finished,
+    /// either past its last instruction or at an `inbox` with an empty inbox.
+    pub fn step(&mut self) -> Result<bool, InterpreterError> {
+        if self.program_cursor >= self.program.len() {
+            let location = match self.program.last() {
+                Some(token) => token.location,
+                None => Location { line: 1, col: 1 },
             };
-            if res.is_err() {
-                let err = res.err().unwrap();
-                // if an EmptyInBox error is happened, worker will exit with status 0.
-                if err.value == InterpreterErrorKind::EmptyInBox {
-                    debug!("EmptyInBox and return.");
-                    return Ok(0);
-                }
-                return Err(err);
+            return self.finish(location);
+        }
+        let command = &self.program[self.program_cursor].clone();
+        trace!("step:{}\tcommand:{:?}", self.step_counter, command);
+        self.hits[self.program_cursor] += 1;
+        if let TokenKind::CopyFrom(index)
+        | TokenKind::CopyTo(index)
+        | TokenKind::Add(index)
+        | TokenKind::Sub(index)
+        | TokenKind::BumpPlus(index)
+        | TokenKind::BumpMinus(index) = command.value
+        {
+            if index >= FLOOR_SIZE {
+                return Err(InterpreterError {
+                    value: InterpreterErrorKind::FloorOutOfRange,
+                    location: command.location,
+                });
             }
         }
+        let res = match command.value {
+            TokenKind::InBox => self.eval_inbox(command),
+            TokenKind::OutBox => self.eval_outbox(command),
+            TokenKind::CopyFrom(index) => self.eval_copy_from(command, index),
+            TokenKind::CopyTo(index) => self.eval_copy_to(command, index),
+            TokenKind::Add(index) => self.eval_add(command, index),
+            TokenKind::Sub(index) => self.eval_sub(command, index),
+            TokenKind::BumpPlus(index) => self.eval_bump_plus(command, index),
+            TokenKind::BumpMinus(index) => self.eval_bump_minus(command, index),
+            TokenKind::Jump(_) => self.eval_jump(command, self.program_cursor),
+            TokenKind::JumpIfZero(_) => self.eval_jump_if_zero(command, self.program_cursor),
+            TokenKind::JumpIfNeg(_) => self.eval_jump_if_neg(command, self.program_cursor),
+            _ => {
+                self.program_cursor += 1;
+                Ok(())
+            }
+        };
+        if let Err(err) = res {
+            // if an EmptyInBox error is happened, worker will exit with status 0.
+            if err.value == InterpreterErrorKind::EmptyInBox {
+                debug!("EmptyInBox and return.");
+                return self.finish(err.location);
+            }
+            return Err(err);
+        }
+        Ok(true)
+    }
+
+    /// Checks that all the expected output was produced when the program ends at `location`.
+    fn finish(&self, location: Location) -> Result<bool, InterpreterError> {
+        match self.expected_outbox.as_ref() {
+            Some(expected) if self.outbox.len() < expected.len() => Err(InterpreterError {
+                value: InterpreterErrorKind::NotEnoughOutput {
+                    expected: expected.len(),
+                    actual: self.outbox.len(),
+                },
+                location,
+            }),
+            _ => Ok(false),
+        }
+    }
+
+    pub fn eval(&mut self, program: &Program) -> Result<usize, InterpreterError> {
+        self.load(program)?;
+        while self.step()? {}
         Ok(0)
     }
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            hand: self.hand,
+            cells: self.cells.clone(),
+            inbox: self
+                .inbox
+                .as_ref()
+                .map(|inbox| inbox.iter().copied().collect()),
+            outbox: self.outbox.clone(),
+            cursor: self.program_cursor,
+            steps: self.step_counter,
+        }
+    }
+    /// Puts the interpreter back into the state of `snapshot`, to continue with `load` and `step`.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.hand = snapshot.hand;
+        self.cells = snapshot.cells.clone();
+        self.cells.resize(FLOOR_SIZE, None);
+        self.inbox = snapshot
+            .inbox
+            .as_ref()
+            .map(|inbox| inbox.iter().copied().collect());
+        self.outbox = snapshot.outbox.clone();
+        self.program_cursor = snapshot.cursor;
+        self.step_counter = snapshot.steps;
+    }
+    pub fn report(&self) -> RunReport {
+        RunReport {
+            steps: self.step_counter,
+            outbox: self.outbox.clone(),
+            hits: self.hits.clone(),
+            branches: self.branches.clone(),
+        }
+    }
 }
@@ -0,0 +1,351 @@
+//! Value range analysis: an abstract interpreter keeping an interval of the values the hand and
+//! each floor tile may hold before every instruction, given the range of the inbox values.
+//!
+//! Instructions name their tile directly, so a tile past the end of the floor is found without
+//! any range: it is reported wherever the instruction is reachable.
+
+use crate::cfg::{ControlFlowGraph, EdgeKind};
+use crate::check::{self, Certainty, Content, Diagnostic, Finding};
+use crate::interpreter::{InterpreterErrorKind, FLOOR_SIZE, MAX_VALUE};
+use crate::lexer::{Program, TokenKind};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Updates of a state after which growing bounds jump to the limits, so loops settle quickly.
+const WIDEN_AFTER: usize = 3;
+
+/// The values `lo..=hi`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Interval {
+    pub lo: i16,
+    pub hi: i16,
+}
+
+impl Interval {
+    /// Every value a program can hold.
+    pub const FULL: Interval = Interval {
+        lo: -MAX_VALUE,
+        hi: MAX_VALUE,
+    };
+
+    pub fn new(lo: i16, hi: i16) -> Self {
+        Self { lo, hi }
+    }
+    pub fn contains(self, value: i16) -> bool {
+        self.lo <= value && value <= self.hi
+    }
+    fn hull(self, other: Self) -> Self {
+        Self::new(self.lo.min(other.lo), self.hi.max(other.hi))
+    }
+    fn meet(self, other: Self) -> Option<Self> {
+        let meet = Self::new(self.lo.max(other.lo), self.hi.min(other.hi));
+        if meet.lo <= meet.hi {
+            Some(meet)
+        } else {
+            None
+        }
+    }
+    fn widen(self, other: Self) -> Self {
+        Self::new(
+            if other.lo < self.lo {
+                -MAX_VALUE
+            } else {
+                self.lo
+            },
+            if other.hi > self.hi {
+                MAX_VALUE
+            } else {
+                self.hi
+            },
+        )
+    }
+    /// The values other than `value`, if they still make an interval.
+    fn without(self, value: i16) -> Option<Self> {
+        if self.lo == value && self.hi == value {
+            None
+        } else if self.lo == value {
+            Some(Self::new(value + 1, self.hi))
+        } else if self.hi == value {
+            Some(Self::new(self.lo, value - 1))
+        } else {
+            Some(self)
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.lo == self.hi {
+            write!(f, "{}", self.lo)
+        } else {
+            write!(f, "{}..{}", self.lo, self.hi)
+        }
+    }
+}
+
+/// Values of the hand and the floor tiles before an instruction is executed.
+/// `None` means the hand or tile never holds a value there.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct State {
+    pub hand: Option<Interval>,
+    pub tiles: Vec<Option<Interval>>,
+    /// Tiles known to hold the same value as the hand, so that a conditional jump narrows them
+    /// too.
+    copies: Vec<bool>,
+}
+
+fn join(slot: &mut Option<Interval>, other: Option<Interval>, widen: bool) {
+    *slot = match (*slot, other) {
+        (Some(current), Some(other)) if widen => Some(current.widen(other)),
+        (Some(current), Some(other)) => Some(current.hull(other)),
+        (current, other) => current.or(other),
+    };
+}
+
+impl State {
+    fn join(&mut self, other: &Self, widen: bool) -> bool {
+        let before = self.clone();
+        join(&mut self.hand, other.hand, widen);
+        for (tile, other) in self.tiles.iter_mut().zip(other.tiles.iter()) {
+            join(tile, *other, widen);
+        }
+        for (copy, other) in self.copies.iter_mut().zip(other.copies.iter()) {
+            *copy = *copy && *other;
+        }
+        *self != before
+    }
+
+    /// Narrows the hand, and the tiles holding the same value, to `hand`.
+    fn narrow(&mut self, hand: Option<Interval>) {
+        self.hand = hand;
+        for (tile, copy) in self.tiles.iter_mut().zip(self.copies.iter()) {
+            if *copy {
+                *tile = hand;
+            }
+        }
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.hand {
+            Some(hand) => write!(f, "hand {}", hand)?,
+            None => write!(f, "hand -")?,
+        }
+        for (index, tile) in self.tiles.iter().enumerate() {
+            if let Some(tile) = tile {
+                write!(f, ", [{}] {}", index, tile)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Result of the value range analysis.
+pub struct Analysis {
+    /// State before each instruction, `None` if it is never reached.
+    pub states: Vec<Option<State>>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// The values of `lo..=hi` a program can hold, recording an overflow for the others.
+fn checked(lo: i32, hi: i32, findings: &mut Vec<Finding>) -> Option<Interval> {
+    let max = MAX_VALUE as i32;
+    let (kept_lo, kept_hi) = (lo.max(-max), hi.min(max));
+    let certainty = if kept_lo > kept_hi {
+        Certainty::Definite
+    } else if lo < -max || hi > max {
+        Certainty::Possible
+    } else {
+        return Some(Interval::new(lo as i16, hi as i16));
+    };
+    findings.push(Finding {
+        certainty,
+        kind: InterpreterErrorKind::Overflow,
+        tile: None,
+    });
+    if certainty == Certainty::Definite {
+        None
+    } else {
+        Some(Interval::new(kept_lo as i16, kept_hi as i16))
+    }
+}
+
+fn operand(kind: &TokenKind) -> Option<usize> {
+    match *kind {
+        TokenKind::CopyFrom(index)
+        | TokenKind::CopyTo(index)
+        | TokenKind::Add(index)
+        | TokenKind::Sub(index)
+        | TokenKind::BumpPlus(index)
+        | TokenKind::BumpMinus(index) => Some(index),
+        _ => None,
+    }
+}
+
+/// Applies `kind` to `state`.
+/// Returns the state after a successful execution, or `None` if it always fails.
+/// Reading an empty hand or tile is left to `check`.
+fn transfer(
+    kind: &TokenKind,
+    state: &State,
+    inbox: Interval,
+    findings: &mut Vec<Finding>,
+) -> Option<State> {
+    if let Some(index) = operand(kind).filter(|index| *index >= FLOOR_SIZE) {
+        findings.push(Finding {
+            certainty: Certainty::Definite,
+            kind: InterpreterErrorKind::FloorOutOfRange,
+            tile: Some(index),
+        });
+        return None;
+    }
+    let mut next = state.clone();
+    let only = |index: usize| (0..FLOOR_SIZE).map(|tile| tile == index).collect();
+    match *kind {
+        TokenKind::InBox => {
+            next.hand = Some(inbox);
+            next.copies = vec![false; FLOOR_SIZE];
+        }
+        TokenKind::OutBox => {
+            state.hand?;
+            next.hand = None;
+            next.copies = vec![false; FLOOR_SIZE];
+        }
+        TokenKind::CopyFrom(index) => {
+            next.hand = Some(state.tiles[index]?);
+            next.copies = only(index);
+        }
+        TokenKind::CopyTo(index) => {
+            next.tiles[index] = Some(state.hand?);
+            next.copies[index] = true;
+        }
+        TokenKind::Add(index) | TokenKind::Sub(index) => {
+            let (hand, tile) = (state.hand?, state.tiles[index]?);
+            let (hand_lo, hand_hi) = (hand.lo as i32, hand.hi as i32);
+            let (tile_lo, tile_hi) = (tile.lo as i32, tile.hi as i32);
+            next.hand = Some(match kind {
+                TokenKind::Add(_) => checked(hand_lo + tile_lo, hand_hi + tile_hi, findings)?,
+                _ => checked(hand_lo - tile_hi, hand_hi - tile_lo, findings)?,
+            });
+            next.copies = vec![false; FLOOR_SIZE];
+        }
+        TokenKind::BumpPlus(index) | TokenKind::BumpMinus(index) => {
+            let tile = state.tiles[index]?;
+            let delta = if let TokenKind::BumpPlus(_) = kind {
+                1
+            } else {
+                -1
+            };
+            let value = checked(tile.lo as i32 + delta, tile.hi as i32 + delta, findings)?;
+            next.hand = Some(value);
+            next.tiles[index] = Some(value);
+            next.copies = only(index);
+        }
+        _ => {}
+    }
+    Some(next)
+}
+
+/// The state following `edge` out of a conditional jump, `None` if the edge is never taken.
+/// An empty hand never jumps.
+fn follow(kind: EdgeKind, jump: &TokenKind, state: &State, may_be_empty: bool) -> Option<State> {
+    let hand = match (kind, jump) {
+        (EdgeKind::JumpIfZero, _) => Some(state.hand?.meet(Interval::new(0, 0))?),
+        (EdgeKind::JumpIfNeg, _) => Some(state.hand?.meet(Interval::new(-MAX_VALUE, -1))?),
+        (EdgeKind::Fallthrough, TokenKind::JumpIfZero(_)) => {
+            match state.hand.and_then(|hand| hand.without(0)) {
+                None if !may_be_empty => return None,
+                hand => hand,
+            }
+        }
+        (EdgeKind::Fallthrough, TokenKind::JumpIfNeg(_)) => {
+            match state
+                .hand
+                .and_then(|hand| hand.meet(Interval::new(0, MAX_VALUE)))
+            {
+                None if !may_be_empty => return None,
+                hand => hand,
+            }
+        }
+        _ => return Some(state.clone()),
+    };
+    let mut next = state.clone();
+    next.narrow(hand);
+    Some(next)
+}
+
+/// Computes the values the hand and each floor tile may hold before every instruction, when
+/// each inbox value is within `inbox` and the tiles of `floor` are seeded.
+///
+/// Entries of `floor` past the end of the floor or out of `Interval::FULL` are ignored, as no
+/// level starts with them.
+pub fn analyze(program: &Program, inbox: Interval, floor: &[(usize, i16)]) -> Analysis {
+    let cfg = ControlFlowGraph::new(program);
+    let emptiness = check::analyze(program);
+    let mut states: Vec<Option<State>> = vec![None; program.len() + 1];
+    let mut tiles = vec![None; FLOOR_SIZE];
+    for &(tile, value) in floor.iter() {
+        if tile < FLOOR_SIZE && Interval::FULL.contains(value) {
+            tiles[tile] = Some(Interval::new(value, value));
+        }
+    }
+    states[0] = Some(State {
+        hand: None,
+        tiles,
+        copies: vec![false; FLOOR_SIZE],
+    });
+    let mut updates = vec![0; program.len() + 1];
+    let mut worklist = VecDeque::new();
+    worklist.push_back(0);
+    while let Some(node) = worklist.pop_front() {
+        if node == cfg.exit() {
+            continue;
+        }
+        let state = states[node].clone().unwrap();
+        let next = match transfer(&program[node].value, &state, inbox, &mut Vec::new()) {
+            Some(next) => next,
+            None => continue,
+        };
+        let may_be_empty = emptiness.states[node]
+            .as_ref()
+            .is_none_or(|state| state.hand != Content::Full);
+        for edge in cfg.successors(node) {
+            let out = match follow(edge.kind, &program[node].value, &next, may_be_empty) {
+                Some(out) => out,
+                None => continue,
+            };
+            let changed = match states[edge.to].as_mut() {
+                Some(current) => current.join(&out, updates[edge.to] >= WIDEN_AFTER),
+                None => {
+                    states[edge.to] = Some(out);
+                    true
+                }
+            };
+            if changed {
+                updates[edge.to] += 1;
+                worklist.push_back(edge.to);
+            }
+        }
+    }
+
+    let mut diagnostics = Vec::new();
+    for (token, state) in program.iter().zip(states.iter()) {
+        if let Some(state) = state {
+            let mut findings = Vec::new();
+            transfer(&token.value, state, inbox, &mut findings);
+            for finding in findings {
+                diagnostics.push(Diagnostic {
+                    value: finding,
+                    location: token.location,
+                });
+            }
+        }
+    }
+    diagnostics.sort_by_key(|d| (d.location.line, d.location.col));
+    states.truncate(program.len());
+    Analysis {
+        states,
+        diagnostics,
+    }
+}
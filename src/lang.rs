@@ -0,0 +1,585 @@
+//! A small structured language compiled down to a `Program`.
+//!
+//! ```text
+//! let zero @ 5;            // pin a variable to a floor tile
+//! loop {
+//!     x = inbox();
+//!     if x == 0 { break; }
+//!     while x > 0 {
+//!         outbox(x);
+//!         x = --x;
+//!     }
+//! }
+//! ```
+//!
+//! Variables live on floor tiles, allocated on their first assignment unless pinned by `let`.
+//! Expressions are `inbox()`, variables, `++x`, `--x` and `+`/`-` of them;
+//! conditions compare an expression with `0`.
+
+use crate::interpreter::FLOOR_SIZE;
+use crate::lexer::{Annotation, Location, Program, Token};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CompileErrorKind {
+    UnexpectedChar(char),
+    UnexpectedToken(String),
+    UnexpectedEnd,
+    UndefinedVariable(String),
+    TileOutOfRange(usize),
+    OutOfTiles,
+    BreakOutsideLoop,
+}
+pub type CompileError = Annotation<CompileErrorKind>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Lexeme {
+    Ident(String),
+    Number(usize),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 17] = [
+    "++", "--", "==", "!=", "<=", ">=", "(", ")", "{", "}", ";", "=", "+", "-", "<", ">", "@",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Annotation<Lexeme>>, CompileError> {
+    let mut lexemes = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let line = match line.find("//") {
+            Some(comment) => &line[..comment],
+            None => line,
+        };
+        let mut rest = line;
+        while let Some(c) = rest.chars().next() {
+            let location = Location {
+                line: i + 1,
+                col: line.len() - rest.len() + 1,
+            };
+            if c.is_whitespace() {
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+            let length = if c.is_ascii_alphabetic() || c == '_' {
+                let length = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                lexemes.push(Annotation::new(
+                    Lexeme::Ident(rest[..length].to_string()),
+                    location,
+                ));
+                length
+            } else if c.is_ascii_digit() {
+                let length = rest
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len());
+                let number = rest[..length]
+                    .parse()
+                    .map_err(|_| Annotation::new(CompileErrorKind::UnexpectedChar(c), location))?;
+                lexemes.push(Annotation::new(Lexeme::Number(number), location));
+                length
+            } else if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(*symbol)) {
+                lexemes.push(Annotation::new(Lexeme::Symbol(symbol), location));
+                symbol.len()
+            } else {
+                return Err(Annotation::new(
+                    CompileErrorKind::UnexpectedChar(c),
+                    location,
+                ));
+            };
+            rest = &rest[length..];
+        }
+    }
+    Ok(lexemes)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Expr {
+    InBox,
+    Var(Annotation<String>),
+    Increment(Annotation<String>),
+    Decrement(Annotation<String>),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+}
+impl Expr {
+    /// Whether evaluating the expression neither reads the inbox nor changes a tile.
+    fn is_pure(&self) -> bool {
+        match self {
+            Expr::Var(_) => true,
+            Expr::Add(left, right) | Expr::Sub(left, right) => left.is_pure() && right.is_pure(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Stmt {
+    Let(String, usize),
+    Assign(String, Expr),
+    OutBox(Expr),
+    Expr(Expr),
+    While(Expr, Comparison, Vec<Statement>),
+    Loop(Vec<Statement>),
+    If(Expr, Comparison, Vec<Statement>, Vec<Statement>),
+    Break,
+}
+type Statement = Annotation<Stmt>;
+
+struct Parser {
+    lexemes: Vec<Annotation<Lexeme>>,
+    cursor: usize,
+    end: Location,
+}
+
+impl Parser {
+    fn peek(&self, offset: usize) -> Option<&Lexeme> {
+        self.lexemes
+            .get(self.cursor + offset)
+            .map(|lexeme| &lexeme.value)
+    }
+    fn location(&self) -> Location {
+        self.lexemes
+            .get(self.cursor)
+            .map(|lexeme| lexeme.location)
+            .unwrap_or(self.end)
+    }
+    fn unexpected(&self) -> CompileError {
+        let kind = match self.peek(0) {
+            Some(Lexeme::Ident(name)) => CompileErrorKind::UnexpectedToken(name.clone()),
+            Some(Lexeme::Number(number)) => CompileErrorKind::UnexpectedToken(number.to_string()),
+            Some(Lexeme::Symbol(symbol)) => CompileErrorKind::UnexpectedToken(symbol.to_string()),
+            None => CompileErrorKind::UnexpectedEnd,
+        };
+        Annotation::new(kind, self.location())
+    }
+    fn accept(&mut self, symbol: &str) -> bool {
+        if matches!(self.peek(0), Some(Lexeme::Symbol(s)) if *s == symbol) {
+            self.cursor += 1;
+            true
+        } else {
+            false
+        }
+    }
+    fn accept_keyword(&mut self, keyword: &str) -> bool {
+        match self.peek(0) {
+            Some(Lexeme::Ident(name)) if name == keyword => {
+                self.cursor += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+    fn expect(&mut self, symbol: &str) -> Result<(), CompileError> {
+        if self.accept(symbol) {
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+    fn ident(&mut self) -> Result<Annotation<String>, CompileError> {
+        let location = self.location();
+        match self.peek(0) {
+            Some(Lexeme::Ident(name)) => {
+                let name = Annotation::new(name.clone(), location);
+                self.cursor += 1;
+                Ok(name)
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn block(&mut self) -> Result<Vec<Statement>, CompileError> {
+        self.expect("{")?;
+        let mut statements = Vec::new();
+        while !self.accept("}") {
+            statements.push(self.statement()?);
+        }
+        Ok(statements)
+    }
+    fn condition(&mut self) -> Result<(Expr, Comparison), CompileError> {
+        let expr = self.expr()?;
+        let comparison = match self.peek(0) {
+            Some(Lexeme::Symbol("==")) => Comparison::Eq,
+            Some(Lexeme::Symbol("!=")) => Comparison::Ne,
+            Some(Lexeme::Symbol("<")) => Comparison::Lt,
+            Some(Lexeme::Symbol("<=")) => Comparison::Le,
+            Some(Lexeme::Symbol(">")) => Comparison::Gt,
+            Some(Lexeme::Symbol(">=")) => Comparison::Ge,
+            _ => return Err(self.unexpected()),
+        };
+        self.cursor += 1;
+        match self.peek(0) {
+            Some(Lexeme::Number(0)) => self.cursor += 1,
+            _ => return Err(self.unexpected()),
+        }
+        Ok((expr, comparison))
+    }
+    fn if_statement(&mut self, location: Location) -> Result<Statement, CompileError> {
+        let (expr, comparison) = self.condition()?;
+        let then = self.block()?;
+        let otherwise = if self.accept_keyword("else") {
+            let location = self.location();
+            if self.accept_keyword("if") {
+                vec![self.if_statement(location)?]
+            } else {
+                self.block()?
+            }
+        } else {
+            Vec::new()
+        };
+        Ok(Annotation::new(
+            Stmt::If(expr, comparison, then, otherwise),
+            location,
+        ))
+    }
+    fn statement(&mut self) -> Result<Statement, CompileError> {
+        let location = self.location();
+        let stmt = if self.accept_keyword("let") {
+            let name = self.ident()?;
+            self.expect("@")?;
+            let tile = match self.peek(0) {
+                Some(Lexeme::Number(tile)) => *tile,
+                _ => return Err(self.unexpected()),
+            };
+            self.cursor += 1;
+            self.expect(";")?;
+            Stmt::Let(name.value, tile)
+        } else if self.accept_keyword("while") {
+            let (expr, comparison) = self.condition()?;
+            Stmt::While(expr, comparison, self.block()?)
+        } else if self.accept_keyword("loop") {
+            Stmt::Loop(self.block()?)
+        } else if self.accept_keyword("if") {
+            return self.if_statement(location);
+        } else if self.accept_keyword("break") {
+            self.expect(";")?;
+            Stmt::Break
+        } else if self.accept_keyword("outbox") {
+            self.expect("(")?;
+            let expr = self.expr()?;
+            self.expect(")")?;
+            self.expect(";")?;
+            Stmt::OutBox(expr)
+        } else if let (Some(Lexeme::Ident(_)), Some(Lexeme::Symbol("="))) =
+            (self.peek(0), self.peek(1))
+        {
+            let name = self.ident()?;
+            self.cursor += 1;
+            let expr = self.expr()?;
+            self.expect(";")?;
+            Stmt::Assign(name.value, expr)
+        } else {
+            let expr = self.expr()?;
+            self.expect(";")?;
+            Stmt::Expr(expr)
+        };
+        Ok(Annotation::new(stmt, location))
+    }
+    fn expr(&mut self) -> Result<Expr, CompileError> {
+        let mut expr = self.unary()?;
+        loop {
+            if self.accept("+") {
+                expr = Expr::Add(Box::new(expr), Box::new(self.unary()?));
+            } else if self.accept("-") {
+                expr = Expr::Sub(Box::new(expr), Box::new(self.unary()?));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+    fn unary(&mut self) -> Result<Expr, CompileError> {
+        if self.accept("++") {
+            Ok(Expr::Increment(self.ident()?))
+        } else if self.accept("--") {
+            Ok(Expr::Decrement(self.ident()?))
+        } else if self.accept("(") {
+            let expr = self.expr()?;
+            self.expect(")")?;
+            Ok(expr)
+        } else if self.accept_keyword("inbox") {
+            self.expect("(")?;
+            self.expect(")")?;
+            Ok(Expr::InBox)
+        } else {
+            Ok(Expr::Var(self.ident()?))
+        }
+    }
+}
+
+struct Codegen {
+    program: Program,
+    variables: HashMap<String, usize>,
+    used: Vec<bool>,
+    labels: usize,
+    loop_ends: Vec<String>,
+    location: Location,
+}
+
+impl Codegen {
+    fn emit(&mut self, token: fn(Location, usize) -> Token, index: usize) {
+        self.program.push(token(self.location, index));
+    }
+    fn emit_label(&mut self, token: fn(Location, String) -> Token, label: &str) {
+        self.program.push(token(self.location, label.to_string()));
+    }
+    fn new_label(&mut self, name: &str) -> String {
+        self.labels += 1;
+        format!("{}_{}", name, self.labels)
+    }
+    fn allocate(&mut self) -> Result<usize, CompileError> {
+        match self.used.iter().position(|used| !used) {
+            Some(tile) => {
+                self.used[tile] = true;
+                Ok(tile)
+            }
+            None => Err(Annotation::new(CompileErrorKind::OutOfTiles, self.location)),
+        }
+    }
+    fn release(&mut self, tile: usize) {
+        self.used[tile] = false;
+    }
+    fn variable(&self, name: &Annotation<String>) -> Result<usize, CompileError> {
+        match self.variables.get(&name.value) {
+            Some(&tile) => Ok(tile),
+            None => Err(Annotation::new(
+                CompileErrorKind::UndefinedVariable(name.value.clone()),
+                name.location,
+            )),
+        }
+    }
+
+    /// Emits code leaving the value of `expr` in the hand.
+    fn expr(&mut self, expr: &Expr) -> Result<(), CompileError> {
+        match expr {
+            Expr::InBox => self.program.push(Token::inbox(self.location)),
+            Expr::Var(name) => {
+                let tile = self.variable(name)?;
+                self.emit(Token::copy_from, tile);
+            }
+            Expr::Increment(name) => {
+                let tile = self.variable(name)?;
+                self.emit(Token::bump_plus, tile);
+            }
+            Expr::Decrement(name) => {
+                let tile = self.variable(name)?;
+                self.emit(Token::bump_minus, tile);
+            }
+            Expr::Add(left, right) | Expr::Sub(left, right) => {
+                let op: fn(Location, usize) -> Token = if let Expr::Add(_, _) = expr {
+                    Token::add
+                } else {
+                    Token::sub
+                };
+                if let Expr::Var(name) = right.as_ref() {
+                    self.expr(left)?;
+                    let tile = self.variable(name)?;
+                    self.emit(op, tile);
+                } else if left.is_pure() && right.is_pure() {
+                    self.expr(right)?;
+                    let temp = self.allocate()?;
+                    self.emit(Token::copy_to, temp);
+                    self.expr(left)?;
+                    self.emit(op, temp);
+                    self.release(temp);
+                } else {
+                    // keep the left to right order of side effects.
+                    self.expr(left)?;
+                    let left_temp = self.allocate()?;
+                    self.emit(Token::copy_to, left_temp);
+                    self.expr(right)?;
+                    let right_temp = self.allocate()?;
+                    self.emit(Token::copy_to, right_temp);
+                    self.emit(Token::copy_from, left_temp);
+                    self.emit(op, right_temp);
+                    self.release(right_temp);
+                    self.release(left_temp);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Emits code falling through when `expr` compares with zero as `comparison`,
+    /// and jumping to `otherwise` when it does not.
+    fn condition(
+        &mut self,
+        expr: &Expr,
+        comparison: Comparison,
+        otherwise: &str,
+    ) -> Result<(), CompileError> {
+        self.expr(expr)?;
+        match comparison {
+            Comparison::Ne => self.emit_label(Token::jump_if_zero, otherwise),
+            Comparison::Ge => self.emit_label(Token::jump_if_neg, otherwise),
+            Comparison::Gt => {
+                self.emit_label(Token::jump_if_zero, otherwise);
+                self.emit_label(Token::jump_if_neg, otherwise);
+            }
+            Comparison::Eq | Comparison::Lt | Comparison::Le => {
+                let then = self.new_label("then");
+                if comparison != Comparison::Lt {
+                    self.emit_label(Token::jump_if_zero, &then);
+                }
+                if comparison != Comparison::Eq {
+                    self.emit_label(Token::jump_if_neg, &then);
+                }
+                self.emit_label(Token::jump, otherwise);
+                self.emit_label(Token::jump_target, &then);
+            }
+        }
+        Ok(())
+    }
+
+    fn statements(&mut self, statements: &[Statement]) -> Result<(), CompileError> {
+        for statement in statements.iter() {
+            self.location = statement.location;
+            match &statement.value {
+                Stmt::Let(_, _) => {}
+                Stmt::Assign(name, expr) => {
+                    self.expr(expr)?;
+                    let tile = self.variables[name];
+                    self.emit(Token::copy_to, tile);
+                }
+                Stmt::OutBox(expr) => {
+                    self.expr(expr)?;
+                    self.program.push(Token::outbox(self.location));
+                }
+                Stmt::Expr(expr) => self.expr(expr)?,
+                Stmt::While(expr, comparison, body) => {
+                    let head = self.new_label("while");
+                    let end = self.new_label("end_while");
+                    self.emit_label(Token::jump_target, &head);
+                    self.condition(expr, *comparison, &end)?;
+                    self.loop_ends.push(end.clone());
+                    self.statements(body)?;
+                    self.loop_ends.pop();
+                    self.location = statement.location;
+                    self.emit_label(Token::jump, &head);
+                    self.emit_label(Token::jump_target, &end);
+                }
+                Stmt::Loop(body) => {
+                    let head = self.new_label("loop");
+                    let end = self.new_label("end_loop");
+                    self.emit_label(Token::jump_target, &head);
+                    self.loop_ends.push(end.clone());
+                    self.statements(body)?;
+                    self.loop_ends.pop();
+                    self.location = statement.location;
+                    self.emit_label(Token::jump, &head);
+                    self.emit_label(Token::jump_target, &end);
+                }
+                Stmt::If(expr, comparison, then, otherwise) => {
+                    let else_label = self.new_label("else");
+                    let end = self.new_label("end_if");
+                    self.condition(expr, *comparison, &else_label)?;
+                    self.statements(then)?;
+                    self.location = statement.location;
+                    self.emit_label(Token::jump, &end);
+                    self.emit_label(Token::jump_target, &else_label);
+                    self.statements(otherwise)?;
+                    self.location = statement.location;
+                    self.emit_label(Token::jump_target, &end);
+                }
+                Stmt::Break => match self.loop_ends.last().cloned() {
+                    Some(end) => self.emit_label(Token::jump, &end),
+                    None => {
+                        return Err(Annotation::new(
+                            CompileErrorKind::BreakOutsideLoop,
+                            statement.location,
+                        ))
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Pins the variables declared by `let` anywhere in `statements`.
+fn declare(codegen: &mut Codegen, statements: &[Statement]) -> Result<(), CompileError> {
+    for statement in statements.iter() {
+        match &statement.value {
+            Stmt::Let(name, tile) => {
+                if *tile >= FLOOR_SIZE {
+                    return Err(Annotation::new(
+                        CompileErrorKind::TileOutOfRange(*tile),
+                        statement.location,
+                    ));
+                }
+                codegen.used[*tile] = true;
+                codegen.variables.insert(name.clone(), *tile);
+            }
+            Stmt::While(_, _, body) | Stmt::Loop(body) => declare(codegen, body)?,
+            Stmt::If(_, _, then, otherwise) => {
+                declare(codegen, then)?;
+                declare(codegen, otherwise)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Gives a tile to each variable first assigned anywhere in `statements`, before any
+/// temporary is allocated, so that temporaries never share a tile with a variable.
+fn reserve(codegen: &mut Codegen, statements: &[Statement]) -> Result<(), CompileError> {
+    for statement in statements.iter() {
+        codegen.location = statement.location;
+        match &statement.value {
+            Stmt::Assign(name, _) if !codegen.variables.contains_key(name) => {
+                let tile = codegen.allocate()?;
+                codegen.variables.insert(name.clone(), tile);
+            }
+            Stmt::While(_, _, body) | Stmt::Loop(body) => reserve(codegen, body)?,
+            Stmt::If(_, _, then, otherwise) => {
+                reserve(codegen, then)?;
+                reserve(codegen, otherwise)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Compiles a structured program into instructions for `SimpleInterpreter`.
+/// Each instruction is located at the statement it was generated from.
+pub fn compile(source: &str) -> Result<Program, CompileError> {
+    let lexemes = tokenize(source)?;
+    let mut parser = Parser {
+        lexemes,
+        cursor: 0,
+        end: Location {
+            line: source.lines().count().max(1),
+            col: source.lines().last().map_or(0, |line| line.len()) + 1,
+        },
+    };
+    let mut statements = Vec::new();
+    while parser.peek(0).is_some() {
+        statements.push(parser.statement()?);
+    }
+
+    let mut codegen = Codegen {
+        program: Vec::new(),
+        variables: HashMap::new(),
+        used: vec![false; FLOOR_SIZE],
+        labels: 0,
+        loop_ends: Vec::new(),
+        location: Location { line: 1, col: 1 },
+    };
+    declare(&mut codegen, &statements)?;
+    reserve(&mut codegen, &statements)?;
+    codegen.location = Location { line: 1, col: 1 };
+    codegen.statements(&statements)?;
+    Ok(codegen.program)
+}
@@ -1,21 +1,26 @@
+use std::fmt;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Location {
     pub line: usize,
     pub col: usize,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Annotation<T> {
     pub value: T,
     pub location: Location,
 }
 impl<T> Annotation<T> {
-    fn new(value: T, location: Location) -> Self {
+    pub fn new(value: T, location: Location) -> Self {
         Self { value, location }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TokenKind {
     InBox,
     OutBox,
@@ -30,6 +35,33 @@ pub enum TokenKind {
     JumpIfNeg(String),
     JumpTarget(String),
 }
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenKind::InBox => write!(f, "inbox"),
+            TokenKind::OutBox => write!(f, "outbox"),
+            TokenKind::CopyFrom(index) => write!(f, "copyfrom {}", index),
+            TokenKind::CopyTo(index) => write!(f, "copyto {}", index),
+            TokenKind::Add(index) => write!(f, "add {}", index),
+            TokenKind::Sub(index) => write!(f, "sub {}", index),
+            TokenKind::BumpPlus(index) => write!(f, "bump_plus {}", index),
+            TokenKind::BumpMinus(index) => write!(f, "bump_minus {}", index),
+            TokenKind::Jump(label) => write!(f, "jump {}", label),
+            TokenKind::JumpIfZero(label) => write!(f, "jump_if_zero {}", label),
+            TokenKind::JumpIfNeg(label) => write!(f, "jump_if_neg {}", label),
+            TokenKind::JumpTarget(label) => write!(f, "jump_target {}", label),
+        }
+    }
+}
+impl TokenKind {
+    /// Number of whitespace separated chunks the lexer reads for this instruction.
+    pub fn width(&self) -> usize {
+        match self {
+            TokenKind::InBox | TokenKind::OutBox => 1,
+            _ => 2,
+        }
+    }
+}
 pub type Token = Annotation<TokenKind>;
 impl Token {
     pub fn inbox(location: Location) -> Self {
@@ -74,50 +106,88 @@ pub type Program = Vec<Token>;
 macro_rules! require_arg {
     ($input: ident, $arg_type: ty, $index: expr) => {
         if $index < $input.len() {
-            if let Ok(v) = $input[$index].value.parse::<$arg_type>() {
-                Some(v)
-            } else {
-                None
-            }
+            $input[$index].value.parse::<$arg_type>().ok()
         } else {
             None
         }
     };
 }
 
+/// Spelling of `chunk` as a mnemonic used in this repository.
+/// Mnemonics are case insensitive and accept the names used by the game.
+pub fn canonical_mnemonic(chunk: &str) -> Option<&'static str> {
+    let mnemonic = match chunk.to_ascii_lowercase().as_str() {
+        "inbox" => "inbox",
+        "outbox" => "outbox",
+        "copyfrom" => "copyfrom",
+        "copyto" => "copyto",
+        "add" => "add",
+        "sub" => "sub",
+        "bump_plus" | "bumpup" => "bump_plus",
+        "bump_minus" | "bumpdn" => "bump_minus",
+        "jump" => "jump",
+        "jump_if_zero" | "jumpz" => "jump_if_zero",
+        "jump_if_neg" | "jumpn" => "jump_if_neg",
+        "jump_target" => "jump_target",
+        _ => return None,
+    };
+    Some(mnemonic)
+}
+
+/// Replaces `#` comments with spaces, keeping the location of every other character.
+fn blank_comments(input: &str) -> String {
+    let mut in_comment = false;
+    input
+        .chars()
+        .map(|c| {
+            match c {
+                '#' => in_comment = true,
+                '\n' => in_comment = false,
+                _ => {}
+            }
+            if in_comment {
+                ' '
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
 pub struct Lexer;
 
 impl Lexer {
     pub fn lex(input: &str) -> Program {
+        let input = blank_comments(input);
+        let input = input.as_str();
         let mut line = 1;
         let mut col = 1;
         let mut tokens = Vec::new();
         let mut chunks = Vec::new();
+        // byte offset of the current chunk; columns count characters.
         let mut start = 0;
-        let mut end = 0;
-        for c in input.chars() {
+        for (end, c) in input.char_indices() {
             let loc = Location { line, col };
             if c.is_ascii_whitespace() {
                 if start < end {
                     chunks.push(Annotation::new(&input[start..end], loc));
                 }
-                col += end - start + 1;
+                col += input[start..end].chars().count() + 1;
                 start = end + 1;
                 if c == '\n' {
                     line += 1;
                     col = 1;
                 }
             }
-            end += 1;
         }
         // lexer allows program which terminated with '\n' or not '\n'.
-        if start < end {
-            chunks.push(Annotation::new(&input[start..end], Location { line, col }));
+        if start < input.len() {
+            chunks.push(Annotation::new(&input[start..], Location { line, col }));
         }
 
         let mut i = 0;
         while i < chunks.len() {
-            if let Some(command) = match chunks[i].value {
+            if let Some(command) = match canonical_mnemonic(chunks[i].value).unwrap_or("") {
                 "inbox" => {
                     let token = Token::inbox(chunks[i].location);
                     i += 1;
@@ -227,7 +297,6 @@ impl Lexer {
             } else {
                 i += 1;
             }
-            line += 1;
         }
         tokens
     }
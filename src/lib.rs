@@ -1,2 +1,26 @@
+pub mod asm;
+pub mod bytecode;
+pub mod cfg;
+pub mod check;
+pub mod coverage;
+pub mod decompiler;
+pub mod equiv;
+pub mod formatter;
 pub mod interpreter;
+pub mod interval;
+pub mod lang;
 pub mod lexer;
+pub mod lsp;
+pub mod optimizer;
+pub mod printer;
+pub mod profile;
+pub mod repl;
+pub mod state;
+pub mod superopt;
+pub mod symbolic;
+pub mod synth;
+pub mod testcase;
+pub mod transpile;
+pub mod tui;
+pub mod wasm;
+pub mod workers;
@@ -0,0 +1,417 @@
+//! Editor features for `hrm-lsp`, computed on the source text of one document.
+//!
+//! Positions follow the Language Server Protocol: zero based lines, and columns counted
+//! in UTF-16 code units.
+
+use crate::check::{self, Certainty};
+use crate::interpreter::{InterpreterErrorKind, FLOOR_SIZE};
+use crate::lexer::{canonical_mnemonic, Lexer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+impl Range {
+    fn contains(&self, position: Position) -> bool {
+        self.start <= position && position <= self.end
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Problem {
+    pub range: Range,
+    pub severity: Severity,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompletionKind {
+    Mnemonic,
+    Label,
+    Tile,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+    pub detail: String,
+}
+
+const MNEMONICS: [&str; 12] = [
+    "inbox",
+    "outbox",
+    "copyfrom",
+    "copyto",
+    "add",
+    "sub",
+    "bump_plus",
+    "bump_minus",
+    "jump",
+    "jump_if_zero",
+    "jump_if_neg",
+    "jump_target",
+];
+
+fn describe(mnemonic: &str) -> &'static str {
+    match mnemonic {
+        "inbox" => "Takes the next value of the inbox into the hand. The program ends when the inbox is empty.",
+        "outbox" => "Puts the value in the hand into the outbox, leaving the hand empty.",
+        "copyfrom" => "Copies the value on a floor tile into the hand.",
+        "copyto" => "Copies the value in the hand onto a floor tile.",
+        "add" => "Adds the value on a floor tile to the value in the hand.",
+        "sub" => "Subtracts the value on a floor tile from the value in the hand.",
+        "bump_plus" => "Adds 1 to the value on a floor tile and copies the result into the hand.",
+        "bump_minus" => "Subtracts 1 from the value on a floor tile and copies the result into the hand.",
+        "jump" => "Continues at a label.",
+        "jump_if_zero" => "Continues at a label if the hand holds 0.",
+        "jump_if_neg" => "Continues at a label if the hand holds a negative value.",
+        "jump_target" => "Defines a label to jump to. When a label is defined more than once, jumps go to the last definition.",
+        _ => "",
+    }
+}
+
+fn takes_label(mnemonic: &str) -> bool {
+    matches!(
+        mnemonic,
+        "jump" | "jump_if_zero" | "jump_if_neg" | "jump_target"
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Role {
+    Mnemonic(&'static str),
+    Tile(usize),
+    Label {
+        definition: bool,
+    },
+    /// Not an instruction; the lexer ignores it.
+    Unknown,
+    /// Follows an unknown word, which makes the lexer skip it.
+    Skipped,
+}
+
+#[derive(Debug, Clone)]
+struct Word {
+    text: String,
+    range: Range,
+    /// One based line and column as located by the lexer.
+    lexer_location: (usize, usize),
+    role: Role,
+}
+
+/// A parsed document.
+pub struct Document {
+    words: Vec<Word>,
+    problems: Vec<Problem>,
+}
+
+fn scan(source: &str) -> Vec<Word> {
+    let mut words = Vec::new();
+    for (line, text) in source.lines().enumerate() {
+        let mut character = 0;
+        let mut start: Option<(usize, usize, usize)> = None;
+        for (col, (byte, c)) in text.char_indices().enumerate() {
+            if c.is_ascii_whitespace() || c == '#' {
+                if let Some((start_byte, start_col, start_character)) = start.take() {
+                    words.push(Word {
+                        text: text[start_byte..byte].to_string(),
+                        range: Range {
+                            start: Position {
+                                line,
+                                character: start_character,
+                            },
+                            end: Position { line, character },
+                        },
+                        lexer_location: (line + 1, start_col + 1),
+                        role: Role::Unknown,
+                    });
+                }
+                if c == '#' {
+                    break;
+                }
+            } else if start.is_none() {
+                start = Some((byte, col, character));
+            }
+            character += c.len_utf16();
+        }
+        if let Some((start_byte, start_col, start_character)) = start {
+            words.push(Word {
+                text: text[start_byte..].to_string(),
+                range: Range {
+                    start: Position {
+                        line,
+                        character: start_character,
+                    },
+                    end: Position { line, character },
+                },
+                lexer_location: (line + 1, start_col + 1),
+                role: Role::Unknown,
+            });
+        }
+    }
+    words
+}
+
+impl Document {
+    pub fn new(source: &str) -> Self {
+        let mut words = scan(source);
+        let mut problems = Vec::new();
+        let error = |range: Range, message: String| Problem {
+            range,
+            severity: Severity::Error,
+            message,
+        };
+
+        // assign roles the way `Lexer::lex` reads the words.
+        let mut i = 0;
+        while i < words.len() {
+            let mnemonic = match canonical_mnemonic(&words[i].text) {
+                Some(mnemonic) => mnemonic,
+                None => {
+                    words[i].role = Role::Unknown;
+                    let mut message = format!("unknown instruction `{}`", words[i].text);
+                    if let Some(next) = words.get_mut(i + 1) {
+                        next.role = Role::Skipped;
+                        message.push_str(&format!(", `{}` after it is skipped too", next.text));
+                    }
+                    problems.push(error(words[i].range, message));
+                    i += 2;
+                    continue;
+                }
+            };
+            words[i].role = Role::Mnemonic(mnemonic);
+            if mnemonic == "inbox" || mnemonic == "outbox" {
+                i += 1;
+                continue;
+            }
+            let range = words[i].range;
+            let operand = match words.get_mut(i + 1) {
+                Some(operand) => operand,
+                None => {
+                    problems.push(error(range, format!("`{}` needs an operand", mnemonic)));
+                    i += 1;
+                    continue;
+                }
+            };
+            if takes_label(mnemonic) {
+                operand.role = Role::Label {
+                    definition: mnemonic == "jump_target",
+                };
+                i += 2;
+                continue;
+            }
+            match operand.text.parse::<usize>() {
+                Ok(tile) => {
+                    operand.role = Role::Tile(tile);
+                    if tile >= FLOOR_SIZE {
+                        problems.push(error(
+                            operand.range,
+                            format!(
+                                "floor tile {} does not exist, the floor has {} tiles",
+                                tile, FLOOR_SIZE
+                            ),
+                        ));
+                    }
+                    i += 2;
+                }
+                Err(_) => {
+                    problems.push(error(
+                        range,
+                        format!("`{}` needs a floor tile number", mnemonic),
+                    ));
+                    i += 1;
+                }
+            }
+        }
+
+        let mut document = Self { words, problems };
+        for word in document.words.iter() {
+            match word.role {
+                Role::Label { definition: false }
+                    if document.definition_of(&word.text).is_none() =>
+                {
+                    document.problems.push(error(
+                        word.range,
+                        format!("label `{}` is not defined", word.text),
+                    ));
+                }
+                Role::Label { definition: true } => {
+                    let last = document.definition_of(&word.text).unwrap();
+                    if last.range != word.range {
+                        document.problems.push(Problem {
+                            range: word.range,
+                            severity: Severity::Warning,
+                            message: format!(
+                                "label `{}` is defined again on line {}, jumps go there",
+                                word.text,
+                                last.range.start.line + 1
+                            ),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for diagnostic in check::analyze(&Lexer::lex(source)).diagnostics {
+            if diagnostic.value.kind == InterpreterErrorKind::UnexistedJumpTarget {
+                continue;
+            }
+            let location = (diagnostic.location.line, diagnostic.location.col);
+            let range = match document.words.iter().find(|w| w.lexer_location == location) {
+                Some(word) => word.range,
+                None => continue,
+            };
+            document.problems.push(Problem {
+                range,
+                severity: match diagnostic.value.certainty {
+                    Certainty::Definite => Severity::Error,
+                    Certainty::Possible => Severity::Warning,
+                },
+                message: diagnostic.value.to_string(),
+            });
+        }
+        document
+            .problems
+            .sort_by_key(|problem| (problem.range.start, problem.range.end));
+        document
+    }
+
+    pub fn problems(&self) -> &[Problem] {
+        &self.problems
+    }
+
+    fn word_at(&self, position: Position) -> Option<&Word> {
+        self.words.iter().find(|word| word.range.contains(position))
+    }
+
+    fn label_at(&self, position: Position) -> Option<&Word> {
+        self.word_at(position)
+            .filter(|word| matches!(word.role, Role::Label { .. }))
+    }
+
+    /// The definition jumps to `label` go to.
+    fn definition_of(&self, label: &str) -> Option<&Word> {
+        self.words
+            .iter()
+            .rev()
+            .find(|word| word.role == Role::Label { definition: true } && word.text == label)
+    }
+
+    /// Location of the `jump_target` of the label at `position`.
+    pub fn definition(&self, position: Position) -> Option<Range> {
+        let label = self.label_at(position)?;
+        self.definition_of(&label.text).map(|word| word.range)
+    }
+
+    /// Every occurrence of the label at `position`.
+    pub fn references(&self, position: Position, include_declaration: bool) -> Vec<Range> {
+        let label = match self.label_at(position) {
+            Some(label) => label,
+            None => return Vec::new(),
+        };
+        self.words
+            .iter()
+            .filter(|word| word.text == label.text)
+            .filter(|word| match word.role {
+                Role::Label { definition } => include_declaration || !definition,
+                _ => false,
+            })
+            .map(|word| word.range)
+            .collect()
+    }
+
+    /// Ranges to replace with `name` to rename the label at `position`.
+    pub fn rename(&self, position: Position, name: &str) -> Result<Vec<Range>, String> {
+        if name.is_empty() || name.contains(|c: char| c.is_ascii_whitespace() || c == '#') {
+            return Err(format!("`{}` is not a valid label", name));
+        }
+        if self.label_at(position).is_none() {
+            return Err("no label to rename here".to_string());
+        }
+        Ok(self.references(position, true))
+    }
+
+    /// Markdown describing the word at `position`.
+    pub fn hover(&self, position: Position) -> Option<(Range, String)> {
+        let word = self.word_at(position)?;
+        let text = match word.role {
+            Role::Mnemonic(mnemonic) => format!("`{}`\n\n{}", mnemonic, describe(mnemonic)),
+            Role::Tile(tile) => format!("floor tile {}", tile),
+            Role::Label { .. } => match self.definition_of(&word.text) {
+                Some(definition) => format!(
+                    "label `{}`, defined on line {}",
+                    word.text,
+                    definition.range.start.line + 1
+                ),
+                None => format!("label `{}`, not defined", word.text),
+            },
+            Role::Unknown | Role::Skipped => return None,
+        };
+        Some((word.range, text))
+    }
+
+    /// Mnemonics, or labels and floor tiles when `position` is an operand.
+    pub fn completions(&self, position: Position) -> Vec<Completion> {
+        let before: Vec<&Word> = self
+            .words
+            .iter()
+            .filter(|word| word.range.start < position)
+            .collect();
+        let previous = match before.last() {
+            Some(word) if word.range.contains(position) => before.iter().rev().nth(1),
+            _ => before.last(),
+        };
+        match previous.map(|word| word.role) {
+            Some(Role::Mnemonic("jump_target")) => Vec::new(),
+            Some(Role::Mnemonic(mnemonic)) if takes_label(mnemonic) => {
+                let mut labels: Vec<&str> = self
+                    .words
+                    .iter()
+                    .filter(|word| word.role == Role::Label { definition: true })
+                    .map(|word| word.text.as_str())
+                    .collect();
+                labels.sort_unstable();
+                labels.dedup();
+                labels
+                    .into_iter()
+                    .map(|label| Completion {
+                        label: label.to_string(),
+                        kind: CompletionKind::Label,
+                        detail: "label".to_string(),
+                    })
+                    .collect()
+            }
+            Some(Role::Mnemonic(mnemonic)) if mnemonic != "inbox" && mnemonic != "outbox" => (0
+                ..FLOOR_SIZE)
+                .map(|tile| Completion {
+                    label: tile.to_string(),
+                    kind: CompletionKind::Tile,
+                    detail: "floor tile".to_string(),
+                })
+                .collect(),
+            _ => MNEMONICS
+                .iter()
+                .map(|mnemonic| Completion {
+                    label: mnemonic.to_string(),
+                    kind: CompletionKind::Mnemonic,
+                    detail: describe(mnemonic).to_string(),
+                })
+                .collect(),
+        }
+    }
+}
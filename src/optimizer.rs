@@ -0,0 +1,197 @@
+use crate::cfg::{resolve_labels, ControlFlowGraph};
+use crate::lexer::{Annotation, Program, TokenKind};
+use std::collections::HashSet;
+use std::fmt;
+
+/// Semantics-preserving rewrites applied by `optimize`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    /// `copyfrom X` right after `copyto X` is removed.
+    RedundantCopyFrom,
+    /// A jump to the instruction right after it is removed.
+    JumpToNext,
+    /// `jump a` where `a` starts with `jump b` becomes `jump b`.
+    JumpChain,
+    /// Instructions which can never be executed are removed.
+    Unreachable,
+    /// `jump_target` nobody jumps to is removed.
+    UnusedLabel,
+}
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Rule::RedundantCopyFrom => "redundant-copyfrom",
+            Rule::JumpToNext => "jump-to-next",
+            Rule::JumpChain => "jump-chain",
+            Rule::Unreachable => "unreachable",
+            Rule::UnusedLabel => "unused-label",
+        };
+        write!(f, "{}", name)
+    }
+}
+pub type Rewrite = Annotation<Rule>;
+
+fn jump_label(kind: &TokenKind) -> Option<&String> {
+    match kind {
+        TokenKind::Jump(label) | TokenKind::JumpIfZero(label) | TokenKind::JumpIfNeg(label) => {
+            Some(label)
+        }
+        _ => None,
+    }
+}
+
+/// Index of the first instruction which is not a `jump_target` at or after `index`.
+fn skip_labels(program: &Program, mut index: usize) -> usize {
+    while index < program.len() {
+        if let TokenKind::JumpTarget(_) = program[index].value {
+            index += 1;
+        } else {
+            break;
+        }
+    }
+    index
+}
+
+fn redundant_copy_from(program: &mut Program, rewrites: &mut Vec<Rewrite>) -> bool {
+    for i in 1..program.len() {
+        if let (TokenKind::CopyTo(to), TokenKind::CopyFrom(from)) =
+            (&program[i - 1].value, &program[i].value)
+        {
+            if to == from {
+                let token = program.remove(i);
+                rewrites.push(Rewrite {
+                    value: Rule::RedundantCopyFrom,
+                    location: token.location,
+                });
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn jump_to_next(program: &mut Program, rewrites: &mut Vec<Rewrite>) -> bool {
+    let targets = resolve_labels(program);
+    for i in 0..program.len() {
+        if let Some(label) = jump_label(&program[i].value) {
+            let target = targets[label.as_str()];
+            if target > i && skip_labels(program, i + 1) > target {
+                let token = program.remove(i);
+                rewrites.push(Rewrite {
+                    value: Rule::JumpToNext,
+                    location: token.location,
+                });
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn jump_chain(program: &mut Program, rewrites: &mut Vec<Rewrite>) -> bool {
+    let targets = resolve_labels(program);
+    // follows `jump` instructions from `label`, giving up on cycles.
+    let follow = |label: &str| -> Option<String> {
+        let mut visited = HashSet::new();
+        let mut current = label.to_string();
+        loop {
+            if !visited.insert(current.clone()) {
+                return None;
+            }
+            let index = skip_labels(program, targets[current.as_str()]);
+            match program.get(index).map(|token| &token.value) {
+                Some(TokenKind::Jump(next)) => current = next.clone(),
+                _ => return Some(current),
+            }
+        }
+    };
+    let chained = program.iter().enumerate().find_map(|(i, token)| {
+        let label = jump_label(&token.value)?;
+        let last = follow(label)?;
+        if last != *label {
+            Some((i, last))
+        } else {
+            None
+        }
+    });
+    if let Some((i, last)) = chained {
+        program[i].value = match &program[i].value {
+            TokenKind::Jump(_) => TokenKind::Jump(last),
+            TokenKind::JumpIfZero(_) => TokenKind::JumpIfZero(last),
+            _ => TokenKind::JumpIfNeg(last),
+        };
+        rewrites.push(Rewrite {
+            value: Rule::JumpChain,
+            location: program[i].location,
+        });
+        return true;
+    }
+    false
+}
+
+fn unreachable(program: &mut Program, rewrites: &mut Vec<Rewrite>) -> bool {
+    let reachable = ControlFlowGraph::new(program).reachable();
+    let mut changed = false;
+    let mut index = 0;
+    program.retain(|token| {
+        let keep = reachable[index] || matches!(token.value, TokenKind::JumpTarget(_));
+        if !keep {
+            rewrites.push(Rewrite {
+                value: Rule::Unreachable,
+                location: token.location,
+            });
+            changed = true;
+        }
+        index += 1;
+        keep
+    });
+    changed
+}
+
+fn unused_label(program: &mut Program, rewrites: &mut Vec<Rewrite>) -> bool {
+    let targets = resolve_labels(program);
+    let used: HashSet<usize> = program
+        .iter()
+        .filter_map(|token| jump_label(&token.value))
+        .map(|label| targets[label.as_str()])
+        .collect();
+    let mut changed = false;
+    let mut index = 0;
+    program.retain(|token| {
+        let keep = !matches!(token.value, TokenKind::JumpTarget(_)) || used.contains(&index);
+        if !keep {
+            rewrites.push(Rewrite {
+                value: Rule::UnusedLabel,
+                location: token.location,
+            });
+            changed = true;
+        }
+        index += 1;
+        keep
+    });
+    changed
+}
+
+/// Applies peephole rewrites to `program` until none of them fires.
+///
+/// Programs jumping to undefined labels are returned unchanged,
+/// since `SimpleInterpreter` rejects them before running.
+pub fn optimize(program: &Program) -> (Program, Vec<Rewrite>) {
+    let mut program = program.clone();
+    let mut rewrites = Vec::new();
+    if !ControlFlowGraph::new(&program)
+        .unresolved_jumps()
+        .is_empty()
+    {
+        return (program, rewrites);
+    }
+    let passes: [fn(&mut Program, &mut Vec<Rewrite>) -> bool; 5] = [
+        redundant_copy_from,
+        jump_to_next,
+        jump_chain,
+        unreachable,
+        unused_label,
+    ];
+    while passes.iter().any(|pass| pass(&mut program, &mut rewrites)) {}
+    (program, rewrites)
+}
@@ -0,0 +1,17 @@
+use crate::lexer::{Program, TokenKind};
+
+/// Renders `program` as source text which `Lexer::lex` reads back into the same instructions.
+/// Instructions following a `jump_target` are indented under it.
+pub fn print(program: &Program) -> String {
+    let mut out = String::new();
+    let mut indent = "";
+    for token in program.iter() {
+        if let TokenKind::JumpTarget(_) = token.value {
+            out.push_str(&format!("{}\n", token.value));
+            indent = "    ";
+        } else {
+            out.push_str(&format!("{}{}\n", indent, token.value));
+        }
+    }
+    out
+}
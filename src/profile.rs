@@ -0,0 +1,136 @@
+//! Profiling of a program over a set of inboxes: the steps taken by each instruction and by
+//! each natural loop of the control-flow graph.
+
+use crate::cfg::{ControlFlowGraph, NaturalLoop};
+use crate::interpreter::{InterpreterError, SimpleInterpreter};
+use crate::lexer::{Program, TokenKind};
+use crate::testcase::{TestCase, STEP_LIMIT};
+
+/// Steps of a program added up over runs.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Profile {
+    pub runs: usize,
+    /// Steps taken by each token; labels take none.
+    pub steps: Vec<usize>,
+    pub loops: Vec<NaturalLoop>,
+}
+
+impl Profile {
+    pub fn total(&self) -> usize {
+        self.steps.iter().sum()
+    }
+
+    /// Steps taken by the instructions of `natural`, nested loops included.
+    pub fn loop_steps(&self, natural: &NaturalLoop) -> usize {
+        natural
+            .body
+            .iter()
+            .filter_map(|&node| self.steps.get(node))
+            .sum()
+    }
+
+    /// Ranked listing of the `top` instructions and loops taking the most steps, with their
+    /// share of all the steps and their steps per run.
+    pub fn report(&self, program: &Program, top: usize) -> String {
+        let total = self.total();
+        let share = |steps: usize| 100.0 * steps as f64 / total.max(1) as f64;
+        let per_run = |steps: usize| steps as f64 / self.runs.max(1) as f64;
+        let mut out = format!(
+            "{:.1} steps per run over {} run{}\n",
+            per_run(total),
+            self.runs,
+            if self.runs == 1 { "" } else { "s" }
+        );
+
+        let mut instructions: Vec<usize> =
+            (0..program.len()).filter(|&i| self.steps[i] > 0).collect();
+        instructions.sort_by_key(|&i| (std::cmp::Reverse(self.steps[i]), i));
+        out.push_str("\nhottest instructions:\n");
+        out.push_str(&format!(
+            "{:>7} {:>10}  {:<9} instruction\n",
+            "share", "steps/run", "location"
+        ));
+        for &i in instructions.iter().take(top) {
+            let token = &program[i];
+            out.push_str(&format!(
+                "{:>6.1}% {:>10.1}  {:<9} {}\n",
+                share(self.steps[i]),
+                per_run(self.steps[i]),
+                format!("{}:{}", token.location.line, token.location.col),
+                token.value
+            ));
+        }
+
+        let mut loops: Vec<&NaturalLoop> = self.loops.iter().collect();
+        loops.sort_by_key(|natural| (std::cmp::Reverse(self.loop_steps(natural)), natural.header));
+        out.push_str("\nhottest loops (nested loops count in the loops around them):\n");
+        if loops.is_empty() {
+            out.push_str("  none\n");
+            return out;
+        }
+        out.push_str(&format!(
+            "{:>7} {:>10}  {:<9} header\n",
+            "share", "steps/run", "lines"
+        ));
+        for natural in loops.iter().take(top) {
+            let lines: Vec<usize> = natural
+                .body
+                .iter()
+                .filter_map(|&node| program.get(node))
+                .map(|token| token.location.line)
+                .collect();
+            let (first, last) = (lines.iter().min(), lines.iter().max());
+            let steps = self.loop_steps(natural);
+            out.push_str(&format!(
+                "{:>6.1}% {:>10.1}  {:<9} {}\n",
+                share(steps),
+                per_run(steps),
+                format!("{}-{}", first.unwrap_or(&0), last.unwrap_or(&0)),
+                program
+                    .get(natural.header)
+                    .map_or("exit".to_string(), |token| token.value.to_string())
+            ));
+        }
+        out
+    }
+}
+
+/// Runs `program` on the inbox and floor of each case, up to `testcase::STEP_LIMIT` steps,
+/// whatever it puts into the outbox.
+///
+/// Fails when the program has a jump to an undefined label.
+pub fn profile(program: &Program, cases: &[TestCase]) -> Result<Profile, InterpreterError> {
+    let mut steps = vec![0; program.len()];
+    for case in cases.iter() {
+        let mut interpreter = SimpleInterpreter::new();
+        interpreter.set_print_outbox(false);
+        interpreter.set_inbox_values(&case.inbox);
+        for &(tile, value) in case.floor.iter() {
+            interpreter.set_tile(tile, Some(value));
+        }
+        interpreter.load(program)?;
+        let mut stopped = false;
+        while let Ok(true) = interpreter.step() {
+            if interpreter.steps() >= STEP_LIMIT {
+                stopped = true;
+                break;
+            }
+        }
+        let mut hits = interpreter.report().hits;
+        // the instruction ending the run, an `inbox` with an empty inbox or an error, is
+        // counted as a hit but takes no step.
+        if !stopped && interpreter.cursor() < program.len() {
+            hits[interpreter.cursor()] -= 1;
+        }
+        for (i, token) in program.iter().enumerate() {
+            if !matches!(token.value, TokenKind::JumpTarget(_)) {
+                steps[i] += hits[i];
+            }
+        }
+    }
+    Ok(Profile {
+        runs: cases.len(),
+        steps,
+        loops: ControlFlowGraph::new(program).natural_loops(),
+    })
+}
@@ -0,0 +1,219 @@
+//! Interactive session executing instructions as they are typed.
+//!
+//! Every entered instruction is appended to the program and run immediately, so a `jump`
+//! to a label entered earlier repeats the instructions in between. Lines starting with `:`
+//! are commands, see `HELP`.
+
+use crate::interpreter::{SimpleInterpreter, Snapshot};
+use crate::lexer::{Lexer, Program};
+use crate::printer;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+/// Steps run for one entered line before giving up on it, to escape infinite loops.
+pub const STEP_LIMIT: usize = 10_000;
+
+const HELP: &str = ":inbox [values...]  show the inbox, or replace it with values
+:hand               show the value in the hand
+:floor              show the floor tiles
+:outbox             show the values put into the outbox
+:program            show the instructions entered so far
+:undo               revert the last instruction or command
+:reset              start again with an empty program, floor and inbox
+:save file.hrm      write the instructions entered so far to a file
+:help               show this help
+:quit               leave the session";
+
+fn show(value: Option<i16>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "empty".to_string(),
+    }
+}
+
+pub struct Session {
+    interpreter: SimpleInterpreter,
+    program: Program,
+    /// States before each change, for `:undo`.
+    history: Vec<(Snapshot, Program)>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session {
+    pub fn new() -> Self {
+        let mut interpreter = SimpleInterpreter::new();
+        interpreter.set_print_outbox(false);
+        Self {
+            interpreter,
+            program: Vec::new(),
+            history: Vec::new(),
+        }
+    }
+
+    fn save_state(&mut self) {
+        self.history
+            .push((self.interpreter.snapshot(), self.program.clone()));
+    }
+
+    fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some((snapshot, program)) => {
+                self.interpreter.restore(&snapshot);
+                self.program = program;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Handles one line of input. Returns `false` when the session ends.
+    pub fn handle(&mut self, line: &str, out: &mut impl Write) -> io::Result<bool> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(true);
+        }
+        if line.starts_with(':') {
+            return self.command(line, out);
+        }
+        self.instructions(line, out)?;
+        Ok(true)
+    }
+
+    fn command(&mut self, line: &str, out: &mut impl Write) -> io::Result<bool> {
+        let args: Vec<&str> = line.split_whitespace().collect();
+        match args[0] {
+            ":quit" | ":q" => return Ok(false),
+            ":help" => writeln!(out, "{}", HELP)?,
+            ":hand" => writeln!(out, "hand: {}", show(self.interpreter.hand()))?,
+            ":floor" => {
+                for (index, value) in self.interpreter.floor().iter().enumerate() {
+                    writeln!(out, "{}: {}", index, show(*value))?;
+                }
+            }
+            ":outbox" => {
+                let values: Vec<String> = self
+                    .interpreter
+                    .outbox()
+                    .iter()
+                    .map(|value| value.to_string())
+                    .collect();
+                writeln!(out, "outbox: {}", values.join(" "))?;
+            }
+            ":inbox" if args.len() == 1 => match self.interpreter.show_inbox() {
+                Some(inbox) => {
+                    let values: Vec<String> = inbox.iter().map(|value| value.to_string()).collect();
+                    writeln!(out, "inbox: {}", values.join(" "))?;
+                }
+                None => writeln!(out, "inbox: not set")?,
+            },
+            ":inbox" => match args[1..]
+                .iter()
+                .map(|value| value.parse::<i16>())
+                .collect::<Result<Vec<i16>, _>>()
+            {
+                Ok(values) => {
+                    self.save_state();
+                    self.interpreter.set_inbox_values(&values);
+                }
+                Err(_) => writeln!(out, "error: inbox values must be numbers")?,
+            },
+            ":program" => write!(out, "{}", printer::print(&self.program))?,
+            ":undo" => {
+                if !self.undo() {
+                    writeln!(out, "nothing to undo")?;
+                }
+            }
+            ":reset" => {
+                self.save_state();
+                let history = std::mem::take(&mut self.history);
+                *self = Self::new();
+                self.history = history;
+            }
+            ":save" if args.len() == 2 => {
+                if let Err(e) = fs::write(args[1], printer::print(&self.program)) {
+                    writeln!(out, "error: {}", e)?;
+                }
+            }
+            ":save" => writeln!(out, "usage: :save file.hrm")?,
+            command => writeln!(out, "unknown command {}, see :help", command)?,
+        }
+        Ok(true)
+    }
+
+    fn instructions(&mut self, line: &str, out: &mut impl Write) -> io::Result<()> {
+        let mut tokens = Lexer::lex(line);
+        let chunks = line
+            .split('#')
+            .next()
+            .unwrap_or_default()
+            .split_whitespace()
+            .count();
+        if tokens
+            .iter()
+            .map(|token| token.value.width())
+            .sum::<usize>()
+            != chunks
+        {
+            writeln!(out, "error: not an instruction: {}", line)?;
+            return Ok(());
+        }
+        let entry = self.program.len() + 1;
+        for token in tokens.iter_mut() {
+            token.location.line = entry;
+        }
+
+        self.save_state();
+        let start = self.program.len();
+        let outbox = self.interpreter.outbox().len();
+        self.program.extend(tokens);
+        let result = self.interpreter.load(&self.program).and_then(|_| {
+            self.interpreter.set_cursor(start);
+            let mut steps = 0;
+            while self.interpreter.cursor() < self.program.len() {
+                if !self.interpreter.step()? {
+                    return Ok(Some("program ended: the inbox is empty".to_string()));
+                }
+                steps += 1;
+                if steps >= STEP_LIMIT {
+                    return Ok(Some(format!("stopped after {} steps", steps)));
+                }
+            }
+            Ok(None)
+        });
+        match result {
+            Ok(note) => {
+                for value in self.interpreter.outbox()[outbox..].iter() {
+                    writeln!(out, "outbox: {}", value)?;
+                }
+                if let Some(note) = note {
+                    writeln!(out, "{}", note)?;
+                }
+            }
+            Err(e) => {
+                self.undo();
+                writeln!(out, "error: {:?}, the line is discarded", e.value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads lines from `input` until `:quit` or the end of input, prompting on `out`.
+pub fn run(input: impl BufRead, out: &mut impl Write) -> io::Result<()> {
+    let mut session = Session::new();
+    write!(out, "> ")?;
+    out.flush()?;
+    for line in input.lines() {
+        if !session.handle(&line?, out)? {
+            return Ok(());
+        }
+        write!(out, "> ")?;
+        out.flush()?;
+    }
+    writeln!(out)
+}
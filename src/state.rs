@@ -0,0 +1,151 @@
+//! Interpreter snapshots kept in state files, to pause a run and resume it later.
+//!
+//! ```text
+//! cursor: 7            # index of the next instruction
+//! steps: 12
+//! hand: 4              # left out when the hand is empty
+//! floor: 0=4 3=-1      # tile=value pairs of the tiles holding a value
+//! inbox: 2 -5          # left out when no inbox was given
+//! outbox: 1 8
+//! ```
+
+use crate::interpreter::{Snapshot, FLOOR_SIZE};
+use crate::lexer::{Annotation, Location};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum StateFileErrorKind {
+    UnknownKey(String),
+    DuplicateKey(String),
+    InvalidNumber(String),
+    InvalidTile(String),
+    MissingKey(String),
+}
+pub type StateFileError = Annotation<StateFileErrorKind>;
+
+fn numbers<T: std::str::FromStr>(text: &str, location: Location) -> Result<Vec<T>, StateFileError> {
+    text.split_whitespace()
+        .map(|chunk| {
+            chunk.parse::<T>().map_err(|_| {
+                StateFileError::new(
+                    StateFileErrorKind::InvalidNumber(chunk.to_string()),
+                    location,
+                )
+            })
+        })
+        .collect()
+}
+
+fn number<T: std::str::FromStr>(text: &str, location: Location) -> Result<T, StateFileError> {
+    let mut values = numbers(text, location)?;
+    match values.pop() {
+        Some(value) if values.is_empty() => Ok(value),
+        _ => Err(StateFileError::new(
+            StateFileErrorKind::InvalidNumber(text.trim().to_string()),
+            location,
+        )),
+    }
+}
+
+fn floor(text: &str, location: Location) -> Result<Vec<Option<i16>>, StateFileError> {
+    let mut cells = vec![None; FLOOR_SIZE];
+    for chunk in text.split_whitespace() {
+        let invalid =
+            || StateFileError::new(StateFileErrorKind::InvalidTile(chunk.to_string()), location);
+        let (tile, value) = chunk.split_once('=').ok_or_else(invalid)?;
+        match (tile.parse::<usize>(), value.parse::<i16>()) {
+            (Ok(tile), Ok(value)) if tile < FLOOR_SIZE => cells[tile] = Some(value),
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(cells)
+}
+
+fn join(values: &[i16]) -> String {
+    values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Writes `snapshot` in the state file format.
+pub fn write(snapshot: &Snapshot) -> String {
+    let mut out = format!("cursor: {}\nsteps: {}\n", snapshot.cursor, snapshot.steps);
+    if let Some(hand) = snapshot.hand {
+        out.push_str(&format!("hand: {}\n", hand));
+    }
+    let tiles: Vec<String> = snapshot
+        .cells
+        .iter()
+        .enumerate()
+        .filter_map(|(tile, value)| value.map(|value| format!("{}={}", tile, value)))
+        .collect();
+    out.push_str(&format!("floor: {}\n", tiles.join(" ")));
+    if let Some(inbox) = snapshot.inbox.as_ref() {
+        out.push_str(&format!("inbox: {}\n", join(inbox)));
+    }
+    out.push_str(&format!("outbox: {}\n", join(&snapshot.outbox)));
+    out
+}
+
+/// Reads a state file. `cursor` and `steps` are required.
+pub fn parse(source: &str) -> Result<Snapshot, StateFileError> {
+    let mut snapshot = Snapshot {
+        cells: vec![None; FLOOR_SIZE],
+        ..Snapshot::default()
+    };
+    let mut keys: Vec<String> = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let location = Location {
+            line: i + 1,
+            col: 1,
+        };
+        let text = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => line,
+        }
+        .trim();
+        if text.is_empty() {
+            continue;
+        }
+        let (key, value) = match text.split_once(':') {
+            Some((key, value)) => (key.trim(), value),
+            None => {
+                return Err(StateFileError::new(
+                    StateFileErrorKind::UnknownKey(text.to_string()),
+                    location,
+                ))
+            }
+        };
+        if keys.iter().any(|seen| seen == key) {
+            return Err(StateFileError::new(
+                StateFileErrorKind::DuplicateKey(key.to_string()),
+                location,
+            ));
+        }
+        keys.push(key.to_string());
+        match key {
+            "cursor" => snapshot.cursor = number(value, location)?,
+            "steps" => snapshot.steps = number(value, location)?,
+            "hand" => snapshot.hand = Some(number(value, location)?),
+            "floor" => snapshot.cells = floor(value, location)?,
+            "inbox" => snapshot.inbox = Some(numbers(value, location)?),
+            "outbox" => snapshot.outbox = numbers(value, location)?,
+            _ => {
+                return Err(StateFileError::new(
+                    StateFileErrorKind::UnknownKey(key.to_string()),
+                    location,
+                ))
+            }
+        }
+    }
+    for required in ["cursor", "steps"] {
+        if !keys.iter().any(|key| key == required) {
+            return Err(StateFileError::new(
+                StateFileErrorKind::MissingKey(required.to_string()),
+                Location { line: 1, col: 1 },
+            ));
+        }
+    }
+    Ok(snapshot)
+}
@@ -0,0 +1,450 @@
+//! Superoptimiser searching for the shortest program which behaves like a reference program.
+//!
+//! Candidates are enumerated by increasing size from the instructions and floor of a level,
+//! skipping programs which have a shorter equivalent or only rename the empty tiles of another.
+//! A candidate first runs on random inboxes, then on every inbox of up to `EXHAUSTIVE_LENGTH`
+//! values from `EXHAUSTIVE_VALUES`. An inbox telling a candidate apart from the reference is
+//! added to the random ones, so that similar candidates are rejected early.
+
+use crate::bytecode::{Bytecode, BytecodeInterpreter, Op};
+use crate::interpreter::{InterpreterError, FLOOR_SIZE};
+use crate::lexer::{Location, Program, Token};
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+
+/// Random inboxes every candidate runs on.
+const RANDOM_INBOXES: usize = 32;
+const RANDOM_LENGTH: usize = 8;
+const RANDOM_VALUES: RangeInclusive<i16> = -30..=30;
+
+/// Inboxes checked exhaustively: every sequence of these values up to this length.
+pub const EXHAUSTIVE_LENGTH: usize = 3;
+pub const EXHAUSTIVE_VALUES: RangeInclusive<i16> = -3..=3;
+
+/// Mnemonics of all instructions, as returned by `canonical_mnemonic`.
+pub const INSTRUCTIONS: [&str; 11] = [
+    "inbox",
+    "outbox",
+    "copyfrom",
+    "copyto",
+    "add",
+    "sub",
+    "bump_plus",
+    "bump_minus",
+    "jump",
+    "jump_if_zero",
+    "jump_if_neg",
+];
+
+/// What a level offers to build a program from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Level {
+    /// Mnemonics of the allowed instructions, as returned by `canonical_mnemonic`.
+    pub instructions: Vec<&'static str>,
+    /// Number of floor tiles.
+    pub tiles: usize,
+    /// Values on the floor at the start, as `(tile, value)` pairs.
+    pub floor: Vec<(usize, i16)>,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self {
+            instructions: INSTRUCTIONS.to_vec(),
+            tiles: FLOOR_SIZE,
+            floor: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Budget {
+    /// Most instructions of a candidate, not counting `jump_target`s.
+    pub max_size: usize,
+    /// Time after which the search gives up.
+    pub time: Duration,
+    /// Steps after which a run is stopped, to escape infinite loops.
+    pub max_steps: usize,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Self {
+            max_size: 6,
+            time: Duration::from_secs(60),
+            max_steps: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Search {
+    /// The first program found, which `search` makes the shortest.
+    pub program: Option<Program>,
+    /// Number of candidates run.
+    pub candidates: usize,
+    /// Whether the time ran out before the search was over.
+    pub timed_out: bool,
+}
+
+/// An inbox and the outbox expected for it.
+pub type Example = (Vec<i16>, Vec<i16>);
+
+/// Shape of candidates: fixed instructions, and `None` for the holes to fill.
+pub type Sketch = Vec<Option<Op>>;
+
+/// `count` deterministic inboxes of random length and values.
+pub fn random_inboxes(count: usize) -> Vec<Vec<i16>> {
+    let mut seed: u32 = 7874;
+    let mut next = |bound: u32| {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        (seed >> 16) % bound
+    };
+    let span = (RANDOM_VALUES.end() - RANDOM_VALUES.start() + 1) as u32;
+    (0..count)
+        .map(|_| {
+            let length = next(RANDOM_LENGTH as u32 + 1) as usize;
+            (0..length)
+                .map(|_| RANDOM_VALUES.start() + next(span) as i16)
+                .collect()
+        })
+        .collect()
+}
+
+/// Every inbox of up to `EXHAUSTIVE_LENGTH` values from `EXHAUSTIVE_VALUES`, shortest first.
+pub fn exhaustive_inboxes() -> Vec<Vec<i16>> {
+    let mut inboxes = vec![Vec::new()];
+    let mut start = 0;
+    for _ in 0..EXHAUSTIVE_LENGTH {
+        let end = inboxes.len();
+        for index in start..end {
+            for value in EXHAUSTIVE_VALUES {
+                let mut inbox = inboxes[index].clone();
+                inbox.push(value);
+                inboxes.push(inbox);
+            }
+        }
+        start = end;
+    }
+    inboxes
+}
+
+/// Runs `code` on `inbox`, returning the outbox if it finishes without error.
+fn outbox(
+    interpreter: &mut BytecodeInterpreter,
+    code: &Bytecode,
+    inbox: &[i16],
+) -> Result<Vec<i16>, InterpreterError> {
+    interpreter.set_inbox_values(inbox);
+    interpreter.eval(code)?;
+    Ok(interpreter.report().outbox)
+}
+
+fn interpreter(level: &Level, budget: &Budget) -> BytecodeInterpreter {
+    let mut interpreter = BytecodeInterpreter::new();
+    for &(tile, value) in level.floor.iter() {
+        interpreter.set_tile(tile, Some(value));
+    }
+    interpreter.set_step_limit(Some(budget.max_steps));
+    interpreter
+}
+
+/// Turns `ops` back into a program, naming the jump targets `a`, `b`, ...
+pub fn to_program(ops: &[Op]) -> Program {
+    let mut targets: Vec<usize> = ops
+        .iter()
+        .filter_map(|op| match op {
+            Op::Jump(target) | Op::JumpIfZero(target) | Op::JumpIfNeg(target) => Some(*target),
+            _ => None,
+        })
+        .collect();
+    targets.sort_unstable();
+    targets.dedup();
+    let label = |target: usize| {
+        let index = targets.binary_search(&target).unwrap();
+        match index {
+            0..=25 => ((b'a' + index as u8) as char).to_string(),
+            _ => format!("l{}", index),
+        }
+    };
+
+    let mut program = Vec::new();
+    for index in 0..=ops.len() {
+        let location = Location {
+            line: program.len() + 1,
+            col: 1,
+        };
+        if targets.contains(&index) {
+            program.push(Token::jump_target(location, label(index)));
+        }
+        let location = Location {
+            line: program.len() + 1,
+            col: 1,
+        };
+        let token = match ops.get(index) {
+            Some(Op::InBox) => Token::inbox(location),
+            Some(Op::OutBox) => Token::outbox(location),
+            Some(Op::CopyFrom(tile)) => Token::copy_from(location, *tile),
+            Some(Op::CopyTo(tile)) => Token::copy_to(location, *tile),
+            Some(Op::Add(tile)) => Token::add(location, *tile),
+            Some(Op::Sub(tile)) => Token::sub(location, *tile),
+            Some(Op::BumpPlus(tile)) => Token::bump_plus(location, *tile),
+            Some(Op::BumpMinus(tile)) => Token::bump_minus(location, *tile),
+            Some(Op::Jump(target)) => Token::jump(location, label(*target)),
+            Some(Op::JumpIfZero(target)) => Token::jump_if_zero(location, label(*target)),
+            Some(Op::JumpIfNeg(target)) => Token::jump_if_neg(location, label(*target)),
+            None => continue,
+        };
+        program.push(token);
+    }
+    program
+}
+
+/// Enumerates the candidates of a sketch with a depth-first search over its holes.
+struct Enumerator<'a> {
+    level: &'a Level,
+    sketch: &'a [Option<Op>],
+    /// Tiles which start empty, in the order candidates must first use them.
+    empty_tiles: Vec<usize>,
+    ops: Vec<Op>,
+    size: usize,
+}
+
+impl<'a> Enumerator<'a> {
+    fn new(level: &'a Level, sketch: &'a [Option<Op>]) -> Self {
+        let size = sketch.len();
+        let empty_tiles = (0..level.tiles)
+            .filter(|tile| !level.floor.iter().any(|(seeded, _)| seeded == tile))
+            .collect();
+        Self {
+            level,
+            sketch,
+            empty_tiles,
+            ops: Vec::with_capacity(size),
+            size,
+        }
+    }
+
+    fn allows(&self, mnemonic: &str) -> bool {
+        self.level.instructions.contains(&mnemonic)
+    }
+
+    /// Instructions which may come at the end of the current prefix.
+    fn choices(&self, used_tiles: usize) -> Vec<Op> {
+        let index = self.ops.len();
+        let mut choices = Vec::new();
+        if self.allows("inbox") {
+            choices.push(Op::InBox);
+        }
+        // the hand is empty before the first instruction.
+        if index > 0 && self.allows("outbox") {
+            choices.push(Op::OutBox);
+        }
+        // empty tiles are interchangeable, so they are first used in order.
+        let tiles = (0..self.level.tiles).filter(|tile| {
+            match self.empty_tiles.iter().position(|empty| empty == tile) {
+                Some(order) => order <= used_tiles,
+                None => true,
+            }
+        });
+        for tile in tiles {
+            let empty = self.empty_tiles.contains(&tile);
+            let ops: [(&str, Op, bool); 6] = [
+                ("copyfrom", Op::CopyFrom(tile), !empty),
+                ("copyto", Op::CopyTo(tile), true),
+                ("add", Op::Add(tile), !empty),
+                ("sub", Op::Sub(tile), !empty),
+                ("bump_plus", Op::BumpPlus(tile), !empty),
+                ("bump_minus", Op::BumpMinus(tile), !empty),
+            ];
+            for (mnemonic, op, first) in ops {
+                let reads_hand = matches!(op, Op::CopyTo(_) | Op::Add(_) | Op::Sub(_));
+                if self.allows(mnemonic) && (index > 0 || (first && !reads_hand)) {
+                    choices.push(op);
+                }
+            }
+        }
+        // jumping to itself loops forever and jumping to the next instruction does nothing.
+        for target in (0..=self.size).filter(|&target| target != index && target != index + 1) {
+            let jumps: [(&str, Op); 3] = [
+                ("jump", Op::Jump(target)),
+                ("jump_if_zero", Op::JumpIfZero(target)),
+                ("jump_if_neg", Op::JumpIfNeg(target)),
+            ];
+            for (mnemonic, op) in jumps {
+                if self.allows(mnemonic) {
+                    choices.push(op);
+                }
+            }
+        }
+        choices
+    }
+
+    /// Whether a complete candidate has an obviously shorter equivalent.
+    fn redundant(&self) -> bool {
+        let mut targets = vec![false; self.size + 1];
+        for op in self.ops.iter() {
+            if let Op::Jump(target) | Op::JumpIfZero(target) | Op::JumpIfNeg(target) = op {
+                targets[*target] = true;
+            }
+        }
+        self.ops.windows(2).enumerate().any(|(index, pair)| {
+            !targets[index + 1]
+                && match pair {
+                    // unreachable code.
+                    [Op::Jump(_), _] => true,
+                    // the hand already holds the value.
+                    [Op::CopyTo(to), Op::CopyFrom(from)] => to == from,
+                    // the value is dropped.
+                    [Op::CopyFrom(_), Op::CopyFrom(_) | Op::InBox] => true,
+                    _ => false,
+                }
+        })
+    }
+
+    /// Calls `accept` with every candidate until it returns `false`.
+    /// Returns `false` when stopped by `accept`.
+    fn each(&mut self, used_tiles: usize, accept: &mut impl FnMut(&[Op]) -> bool) -> bool {
+        if self.ops.len() == self.size {
+            return self.redundant() || accept(&self.ops);
+        }
+        let choices = match self.sketch[self.ops.len()] {
+            Some(op) => vec![op],
+            None => self.choices(used_tiles),
+        };
+        for op in choices {
+            let used_tiles = match op {
+                Op::CopyFrom(tile)
+                | Op::CopyTo(tile)
+                | Op::Add(tile)
+                | Op::Sub(tile)
+                | Op::BumpPlus(tile)
+                | Op::BumpMinus(tile)
+                    if self.empty_tiles.get(used_tiles) == Some(&tile) =>
+                {
+                    used_tiles + 1
+                }
+                _ => used_tiles,
+            };
+            self.ops.push(op);
+            let more = self.each(used_tiles, accept);
+            self.ops.pop();
+            if !more {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Searches for the shortest program of `level` producing the outbox of every example in
+/// `examples`, and every inbox for which `verify` finds no counterexample.
+///
+/// Counterexamples found by `verify` are added to the front of `examples`.
+pub fn search(
+    level: &Level,
+    budget: &Budget,
+    examples: &mut Vec<Example>,
+    verify: impl FnMut(&Bytecode, &mut BytecodeInterpreter) -> Option<Example>,
+) -> Search {
+    let sketches = (1..=budget.max_size).map(|size| vec![None; size]);
+    search_sketches(level, budget, sketches, examples, verify)
+}
+
+/// Like `search`, but tries the candidates of each of `sketches` in turn and returns the first
+/// matching one. Sketches longer than `budget.max_size` are skipped.
+pub fn search_sketches(
+    level: &Level,
+    budget: &Budget,
+    sketches: impl IntoIterator<Item = Sketch>,
+    examples: &mut Vec<Example>,
+    mut verify: impl FnMut(&Bytecode, &mut BytecodeInterpreter) -> Option<Example>,
+) -> Search {
+    let start = Instant::now();
+    let mut interpreter = interpreter(level, budget);
+    let mut result = Search {
+        program: None,
+        candidates: 0,
+        timed_out: false,
+    };
+    for sketch in sketches {
+        if sketch.len() > budget.max_size {
+            continue;
+        }
+        let mut found = None;
+        Enumerator::new(level, &sketch).each(0, &mut |ops| {
+            if result.candidates % 256 == 0 && start.elapsed() > budget.time {
+                result.timed_out = true;
+                return false;
+            }
+            result.candidates += 1;
+            let code = Bytecode {
+                ops: ops.to_vec(),
+                locations: (1..=ops.len())
+                    .map(|line| Location { line, col: 1 })
+                    .collect(),
+            };
+            let passes = examples.iter().all(|(inbox, expected)| {
+                outbox(&mut interpreter, &code, inbox).is_ok_and(|actual| &actual == expected)
+            });
+            if !passes {
+                return true;
+            }
+            match verify(&code, &mut interpreter) {
+                Some(counterexample) => {
+                    examples.insert(0, counterexample);
+                    true
+                }
+                None => {
+                    found = Some(to_program(ops));
+                    false
+                }
+            }
+        });
+        if found.is_some() || result.timed_out {
+            result.program = found;
+            break;
+        }
+    }
+    result
+}
+
+/// Searches for the shortest program of `level` with the same outbox as `reference` on the
+/// random and exhaustive inboxes which `reference` runs on without error.
+///
+/// Fails with the error of `reference` when it does not compile or fails on every inbox.
+pub fn superoptimize(
+    reference: &Program,
+    level: &Level,
+    budget: &Budget,
+) -> Result<Search, InterpreterError> {
+    let code = Bytecode::compile(reference)?;
+    let mut interpreter = interpreter(level, budget);
+    let mut first_error = None;
+    let mut expect = |inboxes: Vec<Vec<i16>>| -> Vec<Example> {
+        inboxes
+            .into_iter()
+            .filter_map(|inbox| match outbox(&mut interpreter, &code, &inbox) {
+                Ok(outbox) => Some((inbox, outbox)),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                    None
+                }
+            })
+            .collect()
+    };
+    let mut examples = expect(random_inboxes(RANDOM_INBOXES));
+    let domain = expect(exhaustive_inboxes());
+    if examples.is_empty() && domain.is_empty() {
+        return Err(first_error.unwrap());
+    }
+
+    Ok(search(level, budget, &mut examples, |code, interpreter| {
+        domain
+            .iter()
+            .find(|(inbox, expected)| {
+                outbox(interpreter, code, inbox).map_or(true, |actual| &actual != expected)
+            })
+            .cloned()
+    }))
+}
@@ -0,0 +1,682 @@
+//! Bounded symbolic execution.
+//!
+//! The values taken from the inbox are symbols `x0`, `x1`, ..., and the hand and the floor hold
+//! linear expressions of them. A conditional jump on the hand forks the path with the condition
+//! added to the constraints of each side, and so does an `add`, `sub` or bump which may overflow.
+//! Only feasible paths are followed, as decided by a small solver for linear constraints over
+//! inbox values in `-MAX_VALUE..=MAX_VALUE`. The solver gives up on hard problems, in which case
+//! a path is kept without a witness.
+
+use crate::bytecode::{Bytecode, Op};
+use crate::interpreter::{InterpreterError, InterpreterErrorKind, FLOOR_SIZE, MAX_VALUE};
+use crate::lexer::Program;
+use std::fmt;
+
+/// Search nodes after which the solver gives up on a set of constraints.
+const SOLVER_NODES: usize = 10_000;
+
+/// `constant + coefficients[0] * x0 + coefficients[1] * x1 + ...`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Linear {
+    pub constant: i64,
+    /// Without trailing zeros, so that equal expressions compare equal.
+    pub coefficients: Vec<i64>,
+}
+
+impl Linear {
+    pub fn constant(value: i64) -> Self {
+        Self {
+            constant: value,
+            coefficients: Vec::new(),
+        }
+    }
+
+    pub fn symbol(index: usize) -> Self {
+        let mut coefficients = vec![0; index + 1];
+        coefficients[index] = 1;
+        Self {
+            constant: 0,
+            coefficients,
+        }
+    }
+
+    /// `self + sign * other`, or `None` when a coefficient does not fit.
+    fn combine(&self, other: &Linear, sign: i64) -> Option<Linear> {
+        let length = self.coefficients.len().max(other.coefficients.len());
+        let coefficient = |linear: &Linear, index: usize| -> i64 {
+            linear.coefficients.get(index).copied().unwrap_or(0)
+        };
+        let mut coefficients = Vec::with_capacity(length);
+        for index in 0..length {
+            coefficients.push(
+                coefficient(self, index)
+                    .checked_add(sign.checked_mul(coefficient(other, index))?)?,
+            );
+        }
+        while coefficients.last() == Some(&0) {
+            coefficients.pop();
+        }
+        Some(Linear {
+            constant: self
+                .constant
+                .checked_add(sign.checked_mul(other.constant)?)?,
+            coefficients,
+        })
+    }
+
+    fn offset(&self, delta: i64) -> Option<Linear> {
+        self.combine(&Linear::constant(delta), 1)
+    }
+
+    /// Value of the expression for the inbox values `values`.
+    pub fn eval(&self, values: &[i16]) -> i128 {
+        self.coefficients
+            .iter()
+            .zip(values.iter())
+            .map(|(&coefficient, &value)| coefficient as i128 * value as i128)
+            .sum::<i128>()
+            + self.constant as i128
+    }
+
+    /// Smallest and largest values for symbols within `bounds`.
+    fn range(&self, bounds: &[(i64, i64)]) -> (i128, i128) {
+        let (mut min, mut max) = (self.constant as i128, self.constant as i128);
+        for (&coefficient, &(lo, hi)) in self.coefficients.iter().zip(bounds.iter()) {
+            let (a, b) = (
+                coefficient as i128 * lo as i128,
+                coefficient as i128 * hi as i128,
+            );
+            min += a.min(b);
+            max += a.max(b);
+        }
+        (min, max)
+    }
+}
+
+impl fmt::Display for Linear {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut terms: Vec<(i64, Option<usize>)> = self
+            .coefficients
+            .iter()
+            .enumerate()
+            .filter(|(_, &coefficient)| coefficient != 0)
+            .map(|(index, &coefficient)| (coefficient, Some(index)))
+            .collect();
+        if self.constant != 0 || terms.is_empty() {
+            terms.push((self.constant, None));
+        }
+        for (position, (value, symbol)) in terms.into_iter().enumerate() {
+            let sign = match (position == 0, value < 0) {
+                (true, true) => "-",
+                (true, false) => "",
+                (false, true) => " - ",
+                (false, false) => " + ",
+            };
+            match (value.unsigned_abs(), symbol) {
+                (1, Some(index)) => write!(f, "{}x{}", sign, index)?,
+                (magnitude, Some(index)) => write!(f, "{}{}*x{}", sign, magnitude, index)?,
+                (magnitude, None) => write!(f, "{}{}", sign, magnitude)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Relation {
+    Zero,
+    NonZero,
+    Negative,
+    NonNegative,
+}
+
+/// `expression` compared with zero.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Constraint {
+    pub expression: Linear,
+    pub relation: Relation,
+}
+
+impl Constraint {
+    pub fn holds(&self, values: &[i16]) -> bool {
+        let value = self.expression.eval(values);
+        match self.relation {
+            Relation::Zero => value == 0,
+            Relation::NonZero => value != 0,
+            Relation::Negative => value < 0,
+            Relation::NonNegative => value >= 0,
+        }
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let relation = match self.relation {
+            Relation::Zero => "==",
+            Relation::NonZero => "!=",
+            Relation::Negative => "<",
+            Relation::NonNegative => ">=",
+        };
+        write!(f, "{} {} 0", self.expression, relation)
+    }
+}
+
+enum Solution {
+    Sat(Vec<i16>),
+    Unsat,
+    Unknown,
+}
+
+fn floor_div(a: i128, b: i128) -> i128 {
+    let quotient = a / b;
+    if a % b != 0 && (a < 0) != (b < 0) {
+        quotient - 1
+    } else {
+        quotient
+    }
+}
+
+fn ceil_div(a: i128, b: i128) -> i128 {
+    -floor_div(-a, b)
+}
+
+/// Narrows `bounds` to the values which may satisfy `constraints`.
+/// Returns `false` when no values can.
+fn propagate(constraints: &[Constraint], bounds: &mut [(i64, i64)]) -> bool {
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for constraint in constraints.iter() {
+            let expression = &constraint.expression;
+            let (min, max) = expression.range(bounds);
+            let feasible = match constraint.relation {
+                Relation::Zero => min <= 0 && max >= 0,
+                Relation::NonZero => !(min == 0 && max == 0),
+                Relation::Negative => min < 0,
+                Relation::NonNegative => max >= 0,
+            };
+            if !feasible {
+                return false;
+            }
+            let unfixed: Vec<usize> = (0..expression.coefficients.len())
+                .filter(|&index| {
+                    expression.coefficients[index] != 0 && bounds[index].0 < bounds[index].1
+                })
+                .collect();
+            if constraint.relation == Relation::NonZero {
+                // with one unknown left, a value at an end of its bounds may be excluded.
+                if let [index] = unfixed[..] {
+                    let a = expression.coefficients[index] as i128;
+                    let (lo, hi) = bounds[index];
+                    let rest = min - (a * lo as i128).min(a * hi as i128);
+                    if rest % a == 0 {
+                        let excluded = -rest / a;
+                        if excluded == lo as i128 {
+                            bounds[index].0 += 1;
+                            changed = true;
+                        } else if excluded == hi as i128 {
+                            bounds[index].1 -= 1;
+                            changed = true;
+                        }
+                    }
+                }
+                continue;
+            }
+            for &index in unfixed.iter() {
+                let a = expression.coefficients[index] as i128;
+                let (lo, hi) = (bounds[index].0 as i128, bounds[index].1 as i128);
+                let (term_min, term_max) = ((a * lo).min(a * hi), (a * lo).max(a * hi));
+                let (rest_min, rest_max) = (min - term_min, max - term_max);
+                // the term `a * x` must lie within `low..=high`.
+                let (low, high) = match constraint.relation {
+                    Relation::Zero => (-rest_max, -rest_min),
+                    Relation::Negative => (i128::MIN / 2, -1 - rest_min),
+                    _ => (-rest_max, i128::MAX / 2),
+                };
+                let (new_lo, new_hi) = if a > 0 {
+                    (ceil_div(low, a).max(lo), floor_div(high, a).min(hi))
+                } else {
+                    (ceil_div(high, a).max(lo), floor_div(low, a).min(hi))
+                };
+                if new_lo > new_hi {
+                    return false;
+                }
+                if (new_lo, new_hi) != (lo, hi) {
+                    bounds[index] = (new_lo as i64, new_hi as i64);
+                    changed = true;
+                }
+            }
+        }
+    }
+    true
+}
+
+/// Searches for values within `bounds` satisfying `constraints`, trying values near zero first.
+fn search(constraints: &[Constraint], bounds: &mut [(i64, i64)], nodes: &mut usize) -> Solution {
+    if !propagate(constraints, bounds) {
+        return Solution::Unsat;
+    }
+    *nodes += 1;
+    if *nodes > SOLVER_NODES {
+        return Solution::Unknown;
+    }
+    let open = (0..bounds.len())
+        .filter(|&index| bounds[index].0 < bounds[index].1)
+        .min_by_key(|&index| bounds[index].1 - bounds[index].0);
+    let index = match open {
+        Some(index) => index,
+        None => return Solution::Sat(bounds.iter().map(|&(lo, _)| lo as i16).collect()),
+    };
+    let (lo, hi) = bounds[index];
+    let value = 0.clamp(lo, hi);
+    let mut unknown = false;
+    for (lo, hi) in [(value, value), (lo, value - 1), (value + 1, hi)] {
+        if lo > hi {
+            continue;
+        }
+        let mut narrowed = bounds.to_vec();
+        narrowed[index] = (lo, hi);
+        match search(constraints, &mut narrowed, nodes) {
+            Solution::Sat(values) => return Solution::Sat(values),
+            Solution::Unknown => unknown = true,
+            Solution::Unsat => {}
+        }
+    }
+    if unknown {
+        Solution::Unknown
+    } else {
+        Solution::Unsat
+    }
+}
+
+/// Inbox values for `symbols` symbols satisfying `constraints`.
+fn solve(constraints: &[Constraint], symbols: usize) -> Solution {
+    let limit = MAX_VALUE as i64;
+    let mut bounds = vec![(-limit, limit); symbols];
+    search(constraints, &mut bounds, &mut 0)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum End {
+    /// The inbox ran out, or the program ran past its last instruction.
+    Finished,
+    Error(InterpreterError),
+    /// The path was cut after `Limits::depth` steps.
+    DepthExceeded,
+    /// The expressions grew too large to follow.
+    Abandoned,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Path {
+    pub end: End,
+    /// Conditions on the inbox values for the program to take this path.
+    pub constraints: Vec<Constraint>,
+    /// Number of values taken from the inbox.
+    pub symbols: usize,
+    pub outbox: Vec<Linear>,
+    /// Conditional jumps on the way, as the index of the op and whether it jumped.
+    pub branches: Vec<(usize, bool)>,
+    /// An inbox taking this path, unless the solver gave up.
+    pub witness: Option<Vec<i16>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Limits {
+    /// Steps after which a path is cut.
+    pub depth: usize,
+    /// Paths after which the exploration stops.
+    pub paths: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            depth: 100,
+            paths: 10_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Exploration {
+    /// The program lowered to ops, which the indices below refer to.
+    pub code: Bytecode,
+    pub paths: Vec<Path>,
+    /// Whether some path executes each op.
+    pub covered: Vec<bool>,
+    /// Whether some path jumps at each conditional jump.
+    pub jumped: Vec<bool>,
+    /// Whether some path falls through each conditional jump.
+    pub fell_through: Vec<bool>,
+    /// Whether `Limits::paths` stopped the exploration before every path was followed.
+    pub truncated: bool,
+}
+
+impl Exploration {
+    /// The path with the shortest witness for each error, by location, then by kind.
+    pub fn errors(&self) -> Vec<&Path> {
+        let mut errors: Vec<&Path> = Vec::new();
+        for path in self.paths.iter() {
+            if let End::Finished | End::DepthExceeded | End::Abandoned = path.end {
+                continue;
+            }
+            let size = |path: &Path| path.witness.as_ref().map_or(usize::MAX, Vec::len);
+            match errors.iter_mut().find(|other| other.end == path.end) {
+                Some(other) if size(path) < size(other) => *other = path,
+                Some(_) => {}
+                None => errors.push(path),
+            }
+        }
+        let key = |path: &&Path| match &path.end {
+            End::Error(e) => (e.location.line, e.location.col, format!("{:?}", e.value)),
+            _ => (0, 0, String::new()),
+        };
+        errors.sort_by_key(key);
+        errors
+    }
+}
+
+#[derive(Clone)]
+struct State {
+    cursor: usize,
+    steps: usize,
+    hand: Option<Linear>,
+    floor: Vec<Option<Linear>>,
+    constraints: Vec<Constraint>,
+    symbols: usize,
+    outbox: Vec<Linear>,
+    branches: Vec<(usize, bool)>,
+    /// Inbox values satisfying `constraints`, `None` once the solver gave up.
+    model: Option<Vec<i16>>,
+}
+
+impl State {
+    /// Adds `constraint`, returning `false` when the path becomes infeasible.
+    fn assume(&mut self, constraint: Constraint) -> bool {
+        let satisfied = self
+            .model
+            .as_ref()
+            .is_some_and(|model| constraint.holds(model));
+        self.constraints.push(constraint);
+        if satisfied {
+            return true;
+        }
+        match solve(&self.constraints, self.symbols) {
+            Solution::Sat(model) => {
+                self.model = Some(model);
+                true
+            }
+            Solution::Unsat => false,
+            Solution::Unknown => {
+                self.model = None;
+                true
+            }
+        }
+    }
+
+    fn end(self, end: End) -> Path {
+        Path {
+            end,
+            constraints: self.constraints,
+            symbols: self.symbols,
+            outbox: self.outbox,
+            branches: self.branches,
+            witness: self.model,
+        }
+    }
+}
+
+/// What follows a step of a state.
+enum Next {
+    Continue(State),
+    End(State, End),
+}
+
+/// Forks `state` on whether `value` leaves `-MAX_VALUE..=MAX_VALUE`: the feasible states where
+/// it does, and the state where it does not if that is feasible.
+/// Returns `None` when the bounds do not fit in the expressions.
+fn fork_overflow(state: State, value: &Linear) -> Option<(Vec<State>, Option<State>)> {
+    let limit = MAX_VALUE as i64;
+    let (min, max) = value.range(&vec![(-limit, limit); state.symbols]);
+    // `value < -MAX_VALUE` and `value > MAX_VALUE`, on the sides which may overflow.
+    let mut sides = Vec::new();
+    if min < -(limit as i128) {
+        sides.push((
+            value.offset(limit)?,
+            Relation::Negative,
+            Relation::NonNegative,
+        ));
+    }
+    if max > limit as i128 {
+        sides.push((
+            value.offset(-limit - 1)?,
+            Relation::NonNegative,
+            Relation::Negative,
+        ));
+    }
+    let mut overflowed = Vec::new();
+    let mut within = Some(state.clone());
+    for (expression, outside, inside) in sides {
+        let mut fork = state.clone();
+        if fork.assume(Constraint {
+            expression: expression.clone(),
+            relation: outside,
+        }) {
+            overflowed.push(fork);
+        }
+        within = within.and_then(|mut within| {
+            let feasible = within.assume(Constraint {
+                expression,
+                relation: inside,
+            });
+            if feasible {
+                Some(within)
+            } else {
+                None
+            }
+        });
+    }
+    Some((overflowed, within))
+}
+
+fn step(code: &Bytecode, mut state: State, limits: &Limits) -> Vec<Next> {
+    if state.steps >= limits.depth {
+        return vec![Next::End(state, End::DepthExceeded)];
+    }
+    if state.cursor >= code.ops.len() {
+        return vec![Next::End(state, End::Finished)];
+    }
+    let cursor = state.cursor;
+    let location = code.locations[cursor];
+    let fail = |state: State, kind: InterpreterErrorKind| {
+        vec![Next::End(
+            state,
+            End::Error(InterpreterError::new(kind, location)),
+        )]
+    };
+    state.steps += 1;
+    let op = code.ops[cursor];
+    if let Op::CopyFrom(index)
+    | Op::CopyTo(index)
+    | Op::Add(index)
+    | Op::Sub(index)
+    | Op::BumpPlus(index)
+    | Op::BumpMinus(index) = op
+    {
+        if index >= FLOOR_SIZE {
+            return fail(state, InterpreterErrorKind::FloorOutOfRange);
+        }
+    }
+    match op {
+        Op::InBox => {
+            let empty = state.clone();
+            state.hand = Some(Linear::symbol(state.symbols));
+            state.symbols += 1;
+            if let Some(model) = state.model.as_mut() {
+                model.push(0);
+            }
+            state.cursor += 1;
+            vec![Next::End(empty, End::Finished), Next::Continue(state)]
+        }
+        Op::OutBox => match state.hand.take() {
+            Some(value) => {
+                state.outbox.push(value);
+                state.cursor += 1;
+                vec![Next::Continue(state)]
+            }
+            None => fail(state, InterpreterErrorKind::EmptyHandValue),
+        },
+        Op::CopyFrom(index) => match state.floor[index].clone() {
+            Some(value) => {
+                state.hand = Some(value);
+                state.cursor += 1;
+                vec![Next::Continue(state)]
+            }
+            None => fail(state, InterpreterErrorKind::EmptyFloorValue),
+        },
+        Op::CopyTo(index) => match state.hand.clone() {
+            Some(value) => {
+                state.floor[index] = Some(value);
+                state.cursor += 1;
+                vec![Next::Continue(state)]
+            }
+            None => fail(state, InterpreterErrorKind::EmptyHandValue),
+        },
+        Op::Add(index) | Op::Sub(index) | Op::BumpPlus(index) | Op::BumpMinus(index) => {
+            let floor_value = match state.floor[index].clone() {
+                Some(value) => value,
+                None => return fail(state, InterpreterErrorKind::EmptyFloorValue),
+            };
+            let value = match op {
+                Op::Add(_) | Op::Sub(_) => {
+                    let hand = match state.hand.clone() {
+                        Some(hand) => hand,
+                        None => return fail(state, InterpreterErrorKind::EmptyHandValue),
+                    };
+                    hand.combine(&floor_value, if let Op::Add(_) = op { 1 } else { -1 })
+                }
+                _ => floor_value.offset(if let Op::BumpPlus(_) = op { 1 } else { -1 }),
+            };
+            let value = match value {
+                Some(value) => value,
+                None => return vec![Next::End(state, End::Abandoned)],
+            };
+            let (overflowed, within) = match fork_overflow(state.clone(), &value) {
+                Some(forks) => forks,
+                None => return vec![Next::End(state, End::Abandoned)],
+            };
+            let mut next: Vec<Next> = overflowed
+                .into_iter()
+                .map(|state| {
+                    let error = InterpreterError::new(InterpreterErrorKind::Overflow, location);
+                    Next::End(state, End::Error(error))
+                })
+                .collect();
+            if let Some(mut state) = within {
+                if let Op::BumpPlus(_) | Op::BumpMinus(_) = op {
+                    state.floor[index] = Some(value.clone());
+                }
+                state.hand = Some(value);
+                state.cursor += 1;
+                next.push(Next::Continue(state));
+            }
+            next
+        }
+        Op::Jump(target) => {
+            state.cursor = target;
+            vec![Next::Continue(state)]
+        }
+        Op::JumpIfZero(target) | Op::JumpIfNeg(target) => {
+            let hand = match state.hand.clone() {
+                Some(hand) => hand,
+                None => {
+                    state.branches.push((cursor, false));
+                    state.cursor += 1;
+                    return vec![Next::Continue(state)];
+                }
+            };
+            let (jump, fall) = if let Op::JumpIfZero(_) = op {
+                (Relation::Zero, Relation::NonZero)
+            } else {
+                (Relation::Negative, Relation::NonNegative)
+            };
+            let mut next = Vec::new();
+            for (relation, jumped) in [(jump, true), (fall, false)] {
+                let mut fork = state.clone();
+                if fork.assume(Constraint {
+                    expression: hand.clone(),
+                    relation,
+                }) {
+                    fork.branches.push((cursor, jumped));
+                    fork.cursor = if jumped { target } else { cursor + 1 };
+                    next.push(Next::Continue(fork));
+                }
+            }
+            next
+        }
+    }
+}
+
+/// Follows every feasible path of `program` within `limits`, with the tiles of `floor` seeded.
+///
+/// Fails when the program has a jump to an undefined label.
+pub fn explore(
+    program: &Program,
+    floor: &[(usize, i16)],
+    limits: &Limits,
+) -> Result<Exploration, InterpreterError> {
+    let code = Bytecode::compile(program)?;
+    let mut initial = State {
+        cursor: 0,
+        steps: 0,
+        hand: None,
+        floor: vec![None; FLOOR_SIZE],
+        constraints: Vec::new(),
+        symbols: 0,
+        outbox: Vec::new(),
+        branches: Vec::new(),
+        model: Some(Vec::new()),
+    };
+    for &(tile, value) in floor.iter() {
+        initial.floor[tile] = Some(Linear::constant(value as i64));
+    }
+
+    let mut exploration = Exploration {
+        paths: Vec::new(),
+        covered: vec![false; code.ops.len()],
+        jumped: vec![false; code.ops.len()],
+        fell_through: vec![false; code.ops.len()],
+        truncated: false,
+        code,
+    };
+    let mut stack = vec![initial];
+    while let Some(state) = stack.pop() {
+        if exploration.paths.len() >= limits.paths {
+            exploration.truncated = true;
+            break;
+        }
+        if state.cursor < exploration.code.ops.len() && state.steps < limits.depth {
+            exploration.covered[state.cursor] = true;
+        }
+        let branches = state.branches.len();
+        // pushed in reverse, so that the first successor is followed first.
+        for next in step(&exploration.code, state, limits).into_iter().rev() {
+            match next {
+                Next::Continue(state) => {
+                    if state.branches.len() > branches {
+                        let (op, jumped) = state.branches[branches];
+                        if jumped {
+                            exploration.jumped[op] = true;
+                        } else {
+                            exploration.fell_through[op] = true;
+                        }
+                    }
+                    stack.push(state)
+                }
+                Next::End(state, end) => exploration.paths.push(state.end(end)),
+            }
+        }
+    }
+    Ok(exploration)
+}
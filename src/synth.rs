@@ -0,0 +1,45 @@
+//! Synthesis of programs from examples of inboxes and the outboxes expected for them.
+//!
+//! Most solutions read the inbox in a loop, so candidates shaped like
+//!
+//! ```text
+//!     <setup>
+//! a:
+//!     inbox
+//!     <body>
+//!     jump a
+//! ```
+//!
+//! are tried first for every size within the budget, and only then candidates of any shape.
+
+use crate::bytecode::Op;
+use crate::superopt::{self, Budget, Example, Level, Search, Sketch};
+
+/// Sketches of `size` instructions reading the inbox in a loop, with the shortest setup first.
+pub fn loop_sketches(size: usize) -> Vec<Sketch> {
+    (0..size.saturating_sub(1))
+        .map(|setup| {
+            let mut sketch = vec![None; size];
+            sketch[setup] = Some(Op::InBox);
+            sketch[size - 1] = Some(Op::Jump(setup));
+            sketch
+        })
+        .collect()
+}
+
+/// Searches for a program of `level` producing the outbox of every example.
+pub fn synthesize(examples: &[Example], level: &Level, budget: &Budget) -> Search {
+    let loops = if level.instructions.contains(&"inbox") && level.instructions.contains(&"jump") {
+        (2..=budget.max_size).flat_map(loop_sketches).collect()
+    } else {
+        Vec::new()
+    };
+    let any = (1..=budget.max_size).map(|size| vec![None; size]);
+    superopt::search_sketches(
+        level,
+        budget,
+        loops.into_iter().chain(any),
+        &mut examples.to_vec(),
+        |_, _| None,
+    )
+}
@@ -0,0 +1,223 @@
+//! Test cases for solutions, kept in `.hrmtest` files.
+//!
+//! ```text
+//! # cases start with `case <name>`; a file may also hold a single unnamed case.
+//! case countdown
+//! inbox: 3 -2
+//! floor: 5=0           # tile=value pairs put on the floor before running
+//! outbox: 3 2 1 0 -2 -1 0
+//! steps: 60            # optional step budget
+//! ```
+
+use crate::interpreter::{InterpreterError, RunReport, SimpleInterpreter, FLOOR_SIZE};
+use crate::lexer::{Annotation, Location, Program};
+
+/// Steps after which a case without a budget is stopped, to escape infinite loops.
+pub const STEP_LIMIT: usize = 1_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TestFileErrorKind {
+    UnknownKey(String),
+    DuplicateKey(String),
+    InvalidNumber(String),
+    InvalidTile(String),
+    MissingOutbox(String),
+}
+pub type TestFileError = Annotation<TestFileErrorKind>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct TestCase {
+    pub name: String,
+    pub inbox: Vec<i16>,
+    /// Initial floor as `(tile, value)` pairs.
+    pub floor: Vec<(usize, i16)>,
+    pub outbox: Vec<i16>,
+    /// Most steps the solution may take.
+    pub max_steps: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Passed,
+    WrongOutbox,
+    /// Took more steps than the budget of the case.
+    OverBudget,
+    /// Ran `STEP_LIMIT` steps without finishing, in a case without a budget.
+    StepLimit,
+    Error(InterpreterError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TestResult {
+    pub outcome: Outcome,
+    pub report: RunReport,
+}
+
+fn numbers<T: std::str::FromStr>(text: &str, location: Location) -> Result<Vec<T>, TestFileError> {
+    text.split_whitespace()
+        .map(|chunk| {
+            chunk.parse::<T>().map_err(|_| {
+                TestFileError::new(
+                    TestFileErrorKind::InvalidNumber(chunk.to_string()),
+                    location,
+                )
+            })
+        })
+        .collect()
+}
+
+fn tiles(text: &str, location: Location) -> Result<Vec<(usize, i16)>, TestFileError> {
+    text.split_whitespace()
+        .map(|chunk| {
+            let invalid =
+                || TestFileError::new(TestFileErrorKind::InvalidTile(chunk.to_string()), location);
+            let (tile, value) = chunk.split_once('=').ok_or_else(invalid)?;
+            match (tile.parse::<usize>(), value.parse::<i16>()) {
+                (Ok(tile), Ok(value)) if tile < FLOOR_SIZE => Ok((tile, value)),
+                _ => Err(invalid()),
+            }
+        })
+        .collect()
+}
+
+/// Reads the cases of a test file. Cases without a name are called `default_name`.
+pub fn parse(source: &str, default_name: &str) -> Result<Vec<TestCase>, TestFileError> {
+    // each case with the keys it has seen and where it starts.
+    let mut cases: Vec<(TestCase, Vec<String>, Location)> = Vec::new();
+    let start = |name: &str, line: usize| {
+        (
+            TestCase {
+                name: name.to_string(),
+                ..TestCase::default()
+            },
+            Vec::new(),
+            Location { line, col: 1 },
+        )
+    };
+    for (i, line) in source.lines().enumerate() {
+        let location = Location {
+            line: i + 1,
+            col: 1,
+        };
+        let text = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => line,
+        }
+        .trim();
+        if text.is_empty() {
+            continue;
+        }
+        if let Some(name) = text.strip_prefix("case ") {
+            cases.push(start(name.trim(), i + 1));
+            continue;
+        }
+        if cases.is_empty() {
+            cases.push(start(default_name, i + 1));
+        }
+        let (case, keys, _) = cases.last_mut().unwrap();
+        let (key, value) = match text.split_once(':') {
+            Some((key, value)) => (key.trim(), value),
+            None => {
+                return Err(TestFileError::new(
+                    TestFileErrorKind::UnknownKey(text.to_string()),
+                    location,
+                ))
+            }
+        };
+        if keys.iter().any(|seen| seen == key) {
+            return Err(TestFileError::new(
+                TestFileErrorKind::DuplicateKey(key.to_string()),
+                location,
+            ));
+        }
+        keys.push(key.to_string());
+        match key {
+            "inbox" => case.inbox = numbers(value, location)?,
+            "floor" => case.floor = tiles(value, location)?,
+            "outbox" => case.outbox = numbers(value, location)?,
+            "steps" => match numbers::<usize>(value, location)?.as_slice() {
+                [steps] => case.max_steps = Some(*steps),
+                _ => {
+                    return Err(TestFileError::new(
+                        TestFileErrorKind::InvalidNumber(value.trim().to_string()),
+                        location,
+                    ))
+                }
+            },
+            _ => {
+                return Err(TestFileError::new(
+                    TestFileErrorKind::UnknownKey(key.to_string()),
+                    location,
+                ))
+            }
+        }
+    }
+    cases
+        .into_iter()
+        .map(|(case, keys, location)| {
+            if keys.iter().any(|key| key == "outbox") {
+                Ok(case)
+            } else {
+                Err(TestFileError::new(
+                    TestFileErrorKind::MissingOutbox(case.name),
+                    location,
+                ))
+            }
+        })
+        .collect()
+}
+
+/// Runs `program` on the inbox and floor of `case` and compares its outbox.
+pub fn run(program: &Program, case: &TestCase) -> TestResult {
+    let mut interpreter = SimpleInterpreter::new();
+    interpreter.set_print_outbox(false);
+    interpreter.set_inbox_values(&case.inbox);
+    for &(tile, value) in case.floor.iter() {
+        interpreter.set_tile(tile, Some(value));
+    }
+    let limit = case.max_steps.unwrap_or(STEP_LIMIT);
+    let mut outcome = match interpreter.load(program) {
+        Ok(()) => Outcome::Passed,
+        Err(e) => Outcome::Error(e),
+    };
+    while outcome == Outcome::Passed {
+        match interpreter.step() {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => outcome = Outcome::Error(e),
+        }
+        if interpreter.steps() > limit {
+            outcome = match case.max_steps {
+                Some(_) => Outcome::OverBudget,
+                None => Outcome::StepLimit,
+            };
+        }
+    }
+    if outcome == Outcome::Passed && interpreter.outbox() != case.outbox.as_slice() {
+        outcome = Outcome::WrongOutbox;
+    }
+    TestResult {
+        outcome,
+        report: interpreter.report(),
+    }
+}
+
+/// Expected and actual outbox side by side, marking the rows which differ with `!`.
+pub fn diff(expected: &[i16], actual: &[i16]) -> String {
+    let show = |value: Option<&i16>| match value {
+        Some(value) => value.to_string(),
+        None => "-".to_string(),
+    };
+    let mut out = format!("  {:>5}  {:>8}  {:>8}\n", "index", "expected", "actual");
+    for index in 0..expected.len().max(actual.len()) {
+        let (expected, actual) = (expected.get(index), actual.get(index));
+        out.push_str(&format!(
+            "{} {:>5}  {:>8}  {:>8}\n",
+            if expected == actual { ' ' } else { '!' },
+            index,
+            show(expected),
+            show(actual)
+        ));
+    }
+    out
+}
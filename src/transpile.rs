@@ -0,0 +1,279 @@
+use crate::cfg::{BlockGraph, ControlFlowGraph, EdgeKind};
+use crate::interpreter::{InterpreterError, InterpreterErrorKind, FLOOR_SIZE, MAX_VALUE};
+use crate::lexer::{Program, Token, TokenKind};
+
+/// Target language of `transpile`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Target {
+    Rust,
+    C,
+}
+
+/// Where control goes after a block: the jump at its end, if any, and the fallthrough.
+struct Transition {
+    jump: Option<(EdgeKind, usize)>,
+    fallthrough: Option<usize>,
+}
+
+fn transition(graph: &BlockGraph, block: usize) -> Transition {
+    let mut transition = Transition {
+        jump: None,
+        fallthrough: None,
+    };
+    for edge in graph.blocks[block].successors.iter() {
+        match edge.kind {
+            EdgeKind::Fallthrough => transition.fallthrough = Some(edge.to),
+            kind => transition.jump = Some((kind, edge.to)),
+        }
+    }
+    transition
+}
+
+fn rust_instruction(token: &Token, out: &mut String) {
+    let (line, col) = (token.location.line, token.location.col);
+    let fail = |kind: &str| format!("fail(\"{}\", {}, {})", kind, line, col);
+    let hand = fail("EmptyHandValue");
+    let floor = fail("EmptyFloorValue");
+    let overflow = format!("if value.abs() > {} {{ {}; }}", MAX_VALUE, fail("Overflow"));
+    let code = match token.value {
+        TokenKind::InBox => {
+            "hand = Some(match inbox.next() {\n    Some(value) => value,\n    None => return,\n});"
+                .to_string()
+        }
+        TokenKind::OutBox => format!(
+            "match hand.take() {{\n    Some(value) => println!(\"{{}}\", value),\n    None => {},\n}}",
+            hand
+        ),
+        TokenKind::CopyFrom(index)
+        | TokenKind::CopyTo(index)
+        | TokenKind::Add(index)
+        | TokenKind::Sub(index)
+        | TokenKind::BumpPlus(index)
+        | TokenKind::BumpMinus(index)
+            if index >= FLOOR_SIZE =>
+        {
+            format!("{};", fail("FloorOutOfRange"))
+        }
+        TokenKind::CopyFrom(index) => format!(
+            "hand = Some(floor[{}].unwrap_or_else(|| {}));",
+            index, floor
+        ),
+        TokenKind::CopyTo(index) => format!(
+            "floor[{}] = Some(hand.unwrap_or_else(|| {}));",
+            index, hand
+        ),
+        TokenKind::Add(index) | TokenKind::Sub(index) => format!(
+            "let value = floor[{}].unwrap_or_else(|| {}) as i32;\nlet value = hand.unwrap_or_else(|| {}) as i32 {} value;\n{}\nhand = Some(value as i16);",
+            index,
+            floor,
+            hand,
+            if let TokenKind::Add(_) = token.value { "+" } else { "-" },
+            overflow
+        ),
+        TokenKind::BumpPlus(index) | TokenKind::BumpMinus(index) => format!(
+            "let value = floor[{}].unwrap_or_else(|| {}) as i32 {} 1;\n{}\nfloor[{}] = Some(value as i16);\nhand = Some(value as i16);",
+            index,
+            floor,
+            if let TokenKind::BumpPlus(_) = token.value { "+" } else { "-" },
+            overflow,
+            index
+        ),
+        _ => return,
+    };
+    out.push_str(&format!(
+        "                // {}:{} {}\n",
+        line, col, token.value
+    ));
+    out.push_str("                {\n");
+    for line in code.lines() {
+        out.push_str(&format!("                    {}\n", line));
+    }
+    out.push_str("                }\n");
+}
+
+fn to_rust(program: &Program, graph: &BlockGraph) -> String {
+    let mut out = String::new();
+    out.push_str(
+        "// Generated by hrm from a Human Resource Machine program.
+#![allow(dead_code, unused_assignments, unused_mut, unreachable_code, unused_variables)]
+use std::io::Read;
+use std::process;
+
+fn fail(kind: &str, line: usize, col: usize) -> ! {
+    eprintln!(\"error: {} at {}:{}\", kind, line, col);
+    process::exit(1);
+}
+
+fn main() {
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input).unwrap();
+    let mut inbox = input.split_whitespace().map_while(|s| s.parse::<i16>().ok());
+    let mut hand: Option<i16> = None;
+",
+    );
+    out.push_str(&format!(
+        "    let mut floor: [Option<i16>; {}] = [None; {}];\n",
+        FLOOR_SIZE, FLOOR_SIZE
+    ));
+    out.push_str("    let mut block = 0;\n    loop {\n        match block {\n");
+    for (i, block) in graph.blocks.iter().enumerate() {
+        out.push_str(&format!("            {} => {{\n", i));
+        for token in program[block.start..block.end].iter() {
+            rust_instruction(token, &mut out);
+        }
+        let transition = transition(graph, i);
+        let next = match (transition.jump, transition.fallthrough) {
+            (Some((EdgeKind::JumpIfZero, to)), Some(fallthrough)) => {
+                format!("if hand == Some(0) {{ {} }} else {{ {} }}", to, fallthrough)
+            }
+            (Some((EdgeKind::JumpIfNeg, to)), Some(fallthrough)) => format!(
+                "match hand {{ Some(value) if value < 0 => {}, _ => {} }}",
+                to, fallthrough
+            ),
+            (Some((_, to)), _) | (None, Some(to)) => to.to_string(),
+            (None, None) => graph.exit().to_string(),
+        };
+        out.push_str(&format!("                block = {};\n", next));
+        out.push_str("            }\n");
+    }
+    out.push_str("            _ => return,\n        }\n    }\n}\n");
+    out
+}
+
+fn c_instruction(token: &Token, out: &mut String) {
+    let (line, col) = (token.location.line, token.location.col);
+    let fail = |kind: &str| format!("fail(\"{}\", {}, {});", kind, line, col);
+    let hand = format!("if (!hand_full) {}", fail("EmptyHandValue"));
+    let floor = |index: usize| format!("if (!floor_full[{}]) {}", index, fail("EmptyFloorValue"));
+    let overflow = format!(
+        "if (value > {0} || value < -{0}) {1}",
+        MAX_VALUE,
+        fail("Overflow")
+    );
+    let code = match token.value {
+        TokenKind::InBox => "if (scanf(\"%hd\", &hand) != 1) return 0;\nhand_full = 1;".to_string(),
+        TokenKind::OutBox => format!("{}\nprintf(\"%d\\n\", hand);\nhand_full = 0;", hand),
+        TokenKind::CopyFrom(index)
+        | TokenKind::CopyTo(index)
+        | TokenKind::Add(index)
+        | TokenKind::Sub(index)
+        | TokenKind::BumpPlus(index)
+        | TokenKind::BumpMinus(index)
+            if index >= FLOOR_SIZE =>
+        {
+            fail("FloorOutOfRange")
+        }
+        TokenKind::CopyFrom(index) => {
+            format!("{}\nhand = floor[{}];\nhand_full = 1;", floor(index), index)
+        }
+        TokenKind::CopyTo(index) => format!(
+            "{}\nfloor[{}] = hand;\nfloor_full[{}] = 1;",
+            hand, index, index
+        ),
+        TokenKind::Add(index) | TokenKind::Sub(index) => format!(
+            "{}\n{}\nvalue = hand {} floor[{}];\n{}\nhand = value;",
+            floor(index),
+            hand,
+            if let TokenKind::Add(_) = token.value {
+                "+"
+            } else {
+                "-"
+            },
+            index,
+            overflow
+        ),
+        TokenKind::BumpPlus(index) | TokenKind::BumpMinus(index) => format!(
+            "{}\nvalue = floor[{}] {} 1;\n{}\nfloor[{}] = value;\nhand = value;\nhand_full = 1;",
+            floor(index),
+            index,
+            if let TokenKind::BumpPlus(_) = token.value {
+                "+"
+            } else {
+                "-"
+            },
+            overflow,
+            index
+        ),
+        _ => return,
+    };
+    out.push_str(&format!("    /* {}:{} {} */\n", line, col, token.value));
+    for line in code.lines() {
+        out.push_str(&format!("    {}\n", line));
+    }
+}
+
+fn to_c(program: &Program, graph: &BlockGraph) -> String {
+    let label = |block: usize| {
+        if block == graph.exit() {
+            "end".to_string()
+        } else {
+            format!("b{}", block)
+        }
+    };
+    let mut out = String::new();
+    out.push_str(
+        "/* Generated by hrm from a Human Resource Machine program. */
+#include <stdio.h>
+#include <stdlib.h>
+
+static void fail(const char *kind, int line, int col) {
+    fprintf(stderr, \"error: %s at %d:%d\\n\", kind, line, col);
+    exit(1);
+}
+
+int main(void) {
+    short hand = 0;
+    int hand_full = 0;
+    int value = 0;
+",
+    );
+    out.push_str(&format!(
+        "    short floor[{}] = {{0}};\n    int floor_full[{}] = {{0}};\n",
+        FLOOR_SIZE, FLOOR_SIZE
+    ));
+    for (i, block) in graph.blocks.iter().enumerate() {
+        out.push_str(&format!("{}:\n", label(i)));
+        for token in program[block.start..block.end].iter() {
+            c_instruction(token, &mut out);
+        }
+        let transition = transition(graph, i);
+        match transition.jump {
+            Some((EdgeKind::JumpIfZero, to)) => out.push_str(&format!(
+                "    if (hand_full && hand == 0) goto {};\n",
+                label(to)
+            )),
+            Some((EdgeKind::JumpIfNeg, to)) => out.push_str(&format!(
+                "    if (hand_full && hand < 0) goto {};\n",
+                label(to)
+            )),
+            Some((_, to)) => out.push_str(&format!("    goto {};\n", label(to))),
+            None => {}
+        }
+        if let Some(to) = transition.fallthrough {
+            if to != i + 1 {
+                out.push_str(&format!("    goto {};\n", label(to)));
+            }
+        }
+    }
+    out.push_str(&format!("{}:\n    return 0;\n}}\n", label(graph.exit())));
+    out
+}
+
+/// Emits a standalone program which reads the inbox from stdin and writes the outbox to stdout.
+///
+/// Like `SimpleInterpreter`, it stops when the inbox runs out, and reports an empty hand or
+/// floor tile, a value past ±999 or a floor tile out of range as
+/// `error: <InterpreterErrorKind> at <line>:<col>` on stderr with exit status 1.
+pub fn transpile(program: &Program, target: Target) -> Result<String, InterpreterError> {
+    if let Some(&index) = ControlFlowGraph::new(program).unresolved_jumps().first() {
+        return Err(InterpreterError {
+            value: InterpreterErrorKind::UnexistedJumpTarget,
+            location: program[index].location,
+        });
+    }
+    let graph = BlockGraph::new(program);
+    Ok(match target {
+        Target::Rust => to_rust(program, &graph),
+        Target::C => to_c(program, &graph),
+    })
+}
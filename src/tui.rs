@@ -0,0 +1,253 @@
+//! Terminal animation of the office: the conveyors, the floor, the worker's hand and the
+//! program with the current instruction highlighted.
+
+use crate::interpreter::{InterpreterError, SimpleInterpreter, FLOOR_SIZE};
+use crate::lexer::{Program, TokenKind};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Steps per second when playing.
+const SPEEDS: [u64; 7] = [1, 2, 4, 8, 16, 32, 64];
+
+/// Floor tiles in one row of the grid.
+const FLOOR_COLUMNS: usize = 3;
+
+const KEYS: &str = "space play/pause  s step  +/- speed  r restart  q quit";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Status {
+    Running,
+    Finished,
+    Failed(InterpreterError),
+}
+
+/// A line of the screen, and whether it is highlighted.
+pub type Line = (String, bool);
+
+pub struct Visualiser {
+    initial: SimpleInterpreter,
+    interpreter: SimpleInterpreter,
+    status: Status,
+    playing: bool,
+    speed: usize,
+}
+
+fn value(value: Option<i16>) -> String {
+    match value {
+        Some(value) => format!("{:^6}", value),
+        None => " ".repeat(6),
+    }
+}
+
+fn conveyor<'a>(values: impl Iterator<Item = &'a i16>) -> String {
+    values
+        .map(|value| format!("[{:>4}]", value))
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+impl Visualiser {
+    /// Prepares to run `program` on `interpreter`, which holds the inbox.
+    pub fn new(
+        program: &Program,
+        mut interpreter: SimpleInterpreter,
+    ) -> Result<Self, InterpreterError> {
+        interpreter.set_print_outbox(false);
+        interpreter.load(program)?;
+        Ok(Self {
+            initial: interpreter.clone(),
+            interpreter,
+            status: Status::Running,
+            playing: false,
+            speed: 2,
+        })
+    }
+
+    pub fn status(&self) -> &Status {
+        &self.status
+    }
+
+    pub fn step(&mut self) {
+        if self.status != Status::Running {
+            return;
+        }
+        match self.interpreter.step() {
+            Ok(true) => {}
+            Ok(false) => self.status = Status::Finished,
+            Err(e) => self.status = Status::Failed(e),
+        }
+        if self.status != Status::Running {
+            self.playing = false;
+        }
+    }
+
+    pub fn restart(&mut self) {
+        self.interpreter = self.initial.clone();
+        self.status = Status::Running;
+        self.playing = false;
+    }
+
+    fn status_text(&self) -> String {
+        let state = match &self.status {
+            Status::Running if self.playing => "playing".to_string(),
+            Status::Running => "paused".to_string(),
+            Status::Finished => "finished".to_string(),
+            Status::Failed(e) => format!(
+                "error: {:?} at {}:{}",
+                e.value, e.location.line, e.location.col
+            ),
+        };
+        format!(
+            "step {}  speed {}/s  {}",
+            self.interpreter.steps(),
+            SPEEDS[self.speed],
+            state
+        )
+    }
+
+    /// Lines of the screen for a terminal of `height` rows.
+    pub fn frame(&self, height: usize) -> Vec<Line> {
+        let mut lines: Vec<Line> = Vec::new();
+        let mut push = |text: String| lines.push((text, false));
+        push(self.status_text());
+        push(String::new());
+        let inbox = match self.interpreter.show_inbox() {
+            Some(inbox) => conveyor(inbox.iter()),
+            None => String::new(),
+        };
+        push(format!("inbox  < {}", inbox));
+        push(format!(
+            "outbox > {}",
+            conveyor(self.interpreter.outbox().iter().rev())
+        ));
+        push(String::new());
+        push(format!("hand   [{}]", value(self.interpreter.hand())));
+        push(String::new());
+
+        let floor = self.interpreter.floor();
+        let border = format!("+{}", "------+".repeat(FLOOR_COLUMNS));
+        push(border.clone());
+        for row in 0..FLOOR_SIZE.div_ceil(FLOOR_COLUMNS) {
+            let tiles: Vec<usize> = (row * FLOOR_COLUMNS..(row + 1) * FLOOR_COLUMNS)
+                .filter(|&index| index < FLOOR_SIZE)
+                .collect();
+            let numbers: String = tiles.iter().map(|index| format!("{:<6}|", index)).collect();
+            let values: String = tiles
+                .iter()
+                .map(|&index| format!("{}|", value(floor[index])))
+                .collect();
+            push(format!("|{}", numbers));
+            push(format!("|{}", values));
+            push(border.clone());
+        }
+        push(String::new());
+
+        // the program, scrolled to keep the current instruction in view.
+        let program = self.interpreter.program();
+        let rows = height.saturating_sub(lines.len() + 2).max(1);
+        let cursor = self.interpreter.cursor();
+        let first = cursor
+            .saturating_sub(rows / 2)
+            .min(program.len().saturating_sub(rows));
+        let mut indent = "";
+        for (index, token) in program.iter().enumerate() {
+            let text = if let TokenKind::JumpTarget(_) = token.value {
+                indent = "    ";
+                format!("{}", token.value)
+            } else {
+                format!("{}{}", indent, token.value)
+            };
+            if index < first || index >= first + rows {
+                continue;
+            }
+            let marker = if index == cursor { ">" } else { " " };
+            lines.push((
+                format!("{} {:>3} {}", marker, token.location.line, text),
+                index == cursor,
+            ));
+        }
+        lines.push((String::new(), false));
+        lines.push((KEYS.to_string(), false));
+        lines
+    }
+
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = terminal::size()?;
+        queue!(out, Clear(ClearType::All))?;
+        for (row, (text, highlighted)) in self.frame(height as usize).into_iter().enumerate() {
+            if row >= height as usize {
+                break;
+            }
+            let text: String = text.chars().take(width as usize).collect();
+            queue!(out, MoveTo(0, row as u16))?;
+            if highlighted {
+                queue!(
+                    out,
+                    SetAttribute(Attribute::Reverse),
+                    Print(text),
+                    SetAttribute(Attribute::Reset)
+                )?;
+            } else {
+                queue!(out, Print(text))?;
+            }
+        }
+        out.flush()
+    }
+
+    fn event_loop(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let mut last_step = Instant::now();
+        loop {
+            self.draw(out)?;
+            let interval = Duration::from_millis(1000 / SPEEDS[self.speed]);
+            let timeout = if self.playing {
+                interval.saturating_sub(last_step.elapsed())
+            } else {
+                Duration::from_millis(250)
+            };
+            if event::poll(timeout)? {
+                let key = match event::read()? {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                    _ => continue,
+                };
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char(' ') => {
+                        self.playing = !self.playing && self.status == Status::Running;
+                        last_step = Instant::now();
+                    }
+                    KeyCode::Char('s') | KeyCode::Right => {
+                        self.playing = false;
+                        self.step();
+                    }
+                    KeyCode::Char('+') | KeyCode::Char('=') | KeyCode::Up => {
+                        self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+                    }
+                    KeyCode::Char('-') | KeyCode::Down => {
+                        self.speed = self.speed.saturating_sub(1);
+                    }
+                    KeyCode::Char('r') => self.restart(),
+                    _ => {}
+                }
+            } else if self.playing {
+                self.step();
+                last_step = Instant::now();
+            }
+        }
+    }
+
+    /// Takes over the terminal until the user quits.
+    pub fn run(&mut self) -> io::Result<()> {
+        let mut out = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(out, EnterAlternateScreen, Hide)?;
+        let result = self.event_loop(&mut out);
+        execute!(out, Show, LeaveAlternateScreen)?;
+        terminal::disable_raw_mode()?;
+        result
+    }
+}
@@ -0,0 +1,209 @@
+use crate::cfg::{BlockGraph, ControlFlowGraph, EdgeKind};
+use crate::interpreter::{InterpreterError, InterpreterErrorKind, FLOOR_SIZE, MAX_VALUE};
+use crate::lexer::{Location, Program, Token, TokenKind};
+
+/// Value returned by the imported `inbox` function when the inbox is empty.
+pub const EMPTY_INBOX: i32 = i32::MIN;
+
+/// Status returned by the exported `run` function for each error.
+/// `run` returns `0` when the program finishes normally. Modules never check the outbox
+/// against an expected one or limit steps, so they return no `WrongOutput`, `NotEnoughOutput`
+/// or `StepLimitExceeded`.
+pub fn error_code(kind: InterpreterErrorKind) -> i32 {
+    match kind {
+        InterpreterErrorKind::UnexistedJumpTarget => 1,
+        InterpreterErrorKind::UndefinedInputBox => 2,
+        InterpreterErrorKind::EmptyInBox => 3,
+        InterpreterErrorKind::EmptyFloorValue => 4,
+        InterpreterErrorKind::EmptyHandValue => 5,
+        InterpreterErrorKind::WrongOutput { .. } => 6,
+        InterpreterErrorKind::NotEnoughOutput { .. } => 7,
+        InterpreterErrorKind::StepLimitExceeded => 8,
+        InterpreterErrorKind::Overflow => 9,
+        InterpreterErrorKind::FloorOutOfRange => 10,
+    }
+}
+
+/// Inverse of `error_code` for the errors a module can return.
+pub fn error_kind(code: i32) -> Option<InterpreterErrorKind> {
+    match code {
+        1 => Some(InterpreterErrorKind::UnexistedJumpTarget),
+        2 => Some(InterpreterErrorKind::UndefinedInputBox),
+        3 => Some(InterpreterErrorKind::EmptyInBox),
+        4 => Some(InterpreterErrorKind::EmptyFloorValue),
+        5 => Some(InterpreterErrorKind::EmptyHandValue),
+        9 => Some(InterpreterErrorKind::Overflow),
+        10 => Some(InterpreterErrorKind::FloorOutOfRange),
+        _ => None,
+    }
+}
+
+fn fail(kind: InterpreterErrorKind, location: Location) -> String {
+    format!(
+        "i32.const {}\nglobal.set $error_line\ni32.const {}\nglobal.set $error_col\ni32.const {}\nreturn",
+        location.line,
+        location.col,
+        error_code(kind)
+    )
+}
+
+fn instruction(token: &Token, out: &mut String) {
+    let hand = format!(
+        "local.get $hand_full\ni32.eqz\nif\n{}\nend",
+        fail(InterpreterErrorKind::EmptyHandValue, token.location)
+    );
+    // keeps the value on the stack, failing when it is out of range.
+    let overflow = format!(
+        "local.tee $value\ni32.const {0}\ni32.gt_s\nlocal.get $value\ni32.const -{0}\ni32.lt_s\ni32.or\nif\n{1}\nend\nlocal.get $value",
+        MAX_VALUE,
+        fail(InterpreterErrorKind::Overflow, token.location)
+    );
+    let floor = |index: usize| {
+        format!(
+            "local.get $tile{}_full\ni32.eqz\nif\n{}\nend",
+            index,
+            fail(InterpreterErrorKind::EmptyFloorValue, token.location)
+        )
+    };
+    let code = match token.value {
+        TokenKind::InBox => format!(
+            "call $inbox\nlocal.tee $value\ni32.const {}\ni32.eq\nif\ni32.const 0\nreturn\nend\nlocal.get $value\nlocal.set $hand\ni32.const 1\nlocal.set $hand_full",
+            EMPTY_INBOX
+        ),
+        TokenKind::OutBox => format!(
+            "{}\nlocal.get $hand\ncall $outbox\ni32.const 0\nlocal.set $hand_full",
+            hand
+        ),
+        TokenKind::CopyFrom(index)
+        | TokenKind::CopyTo(index)
+        | TokenKind::Add(index)
+        | TokenKind::Sub(index)
+        | TokenKind::BumpPlus(index)
+        | TokenKind::BumpMinus(index)
+            if index >= FLOOR_SIZE =>
+        {
+            fail(InterpreterErrorKind::FloorOutOfRange, token.location)
+        }
+        TokenKind::CopyFrom(index) => format!(
+            "{}\nlocal.get $tile{}\nlocal.set $hand\ni32.const 1\nlocal.set $hand_full",
+            floor(index),
+            index
+        ),
+        TokenKind::CopyTo(index) => format!(
+            "{}\nlocal.get $hand\nlocal.set $tile{}\ni32.const 1\nlocal.set $tile{}_full",
+            hand, index, index
+        ),
+        TokenKind::Add(index) | TokenKind::Sub(index) => format!(
+            "{}\n{}\nlocal.get $hand\nlocal.get $tile{}\n{}\n{}\nlocal.set $hand",
+            floor(index),
+            hand,
+            index,
+            if let TokenKind::Add(_) = token.value {
+                "i32.add"
+            } else {
+                "i32.sub"
+            },
+            overflow
+        ),
+        TokenKind::BumpPlus(index) | TokenKind::BumpMinus(index) => format!(
+            "{}\nlocal.get $tile{}\ni32.const {}\ni32.add\n{}\nlocal.tee $tile{}\nlocal.set $hand\ni32.const 1\nlocal.set $hand_full",
+            floor(index),
+            index,
+            if let TokenKind::BumpPlus(_) = token.value {
+                1
+            } else {
+                -1
+            },
+            overflow,
+            index
+        ),
+        _ => return,
+    };
+    out.push_str(&format!(
+        "        ;; {}:{} {}\n",
+        token.location.line, token.location.col, token.value
+    ));
+    for line in code.lines() {
+        out.push_str(&format!("        {}\n", line));
+    }
+}
+
+fn goto(block: usize) -> String {
+    format!(
+        "        i32.const {}\n        local.set $block\n        br $dispatch\n",
+        block
+    )
+}
+
+/// Lowers `program` to a WebAssembly module in the text format.
+///
+/// The module imports `hrm.inbox: [] -> [i32]`, returning `EMPTY_INBOX` once the inbox
+/// is empty, and `hrm.outbox: [i32] -> []`. It exports `run: [] -> [i32]`, which returns
+/// `0` or an `error_code`, with the location of the error in the exported globals
+/// `error_line` and `error_col`. Values past ±999 fail with `Overflow` and floor tiles past
+/// the last one with `FloorOutOfRange`, like in `SimpleInterpreter`.
+pub fn to_wat(program: &Program) -> Result<String, InterpreterError> {
+    if let Some(&index) = ControlFlowGraph::new(program).unresolved_jumps().first() {
+        return Err(InterpreterError {
+            value: InterpreterErrorKind::UnexistedJumpTarget,
+            location: program[index].location,
+        });
+    }
+    let graph = BlockGraph::new(program);
+    let mut out = String::new();
+    out.push_str("(module\n");
+    out.push_str("  (import \"hrm\" \"inbox\" (func $inbox (result i32)))\n");
+    out.push_str("  (import \"hrm\" \"outbox\" (func $outbox (param i32)))\n");
+    out.push_str("  (global $error_line (export \"error_line\") (mut i32) (i32.const 0))\n");
+    out.push_str("  (global $error_col (export \"error_col\") (mut i32) (i32.const 0))\n");
+    out.push_str("  (func (export \"run\") (result i32)\n");
+    out.push_str(
+        "    (local $block i32) (local $value i32) (local $hand i32) (local $hand_full i32)\n",
+    );
+    for index in 0..FLOOR_SIZE {
+        out.push_str(&format!(
+            "    (local $tile{} i32) (local $tile{}_full i32)\n",
+            index, index
+        ));
+    }
+    out.push_str("    loop $dispatch\n");
+    out.push_str("    block $exit\n");
+    for i in (0..graph.blocks.len()).rev() {
+        out.push_str(&format!("    block $b{}\n", i));
+    }
+    out.push_str("        local.get $block\n        br_table");
+    for i in 0..graph.blocks.len() {
+        out.push_str(&format!(" $b{}", i));
+    }
+    out.push_str(" $exit\n");
+    for (i, block) in graph.blocks.iter().enumerate() {
+        out.push_str(&format!("    end ;; $b{}\n", i));
+        for token in program[block.start..block.end].iter() {
+            instruction(token, &mut out);
+        }
+        let mut fallthrough = graph.exit();
+        for edge in block.successors.iter() {
+            match edge.kind {
+                EdgeKind::Fallthrough | EdgeKind::Jump => fallthrough = edge.to,
+                EdgeKind::JumpIfZero | EdgeKind::JumpIfNeg => {
+                    out.push_str("        local.get $hand_full\n        local.get $hand\n");
+                    out.push_str(if edge.kind == EdgeKind::JumpIfZero {
+                        "        i32.eqz\n"
+                    } else {
+                        "        i32.const 0\n        i32.lt_s\n"
+                    });
+                    out.push_str("        i32.and\n        if\n");
+                    out.push_str(&goto(edge.to));
+                    out.push_str("        end\n");
+                }
+            }
+        }
+        out.push_str(&goto(fallthrough));
+    }
+    out.push_str("    end ;; $exit\n");
+    out.push_str("    end ;; $dispatch\n");
+    out.push_str("    i32.const 0\n");
+    out.push_str("  )\n");
+    out.push_str(")\n");
+    Ok(out)
+}
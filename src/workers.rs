@@ -0,0 +1,305 @@
+//! Several workers running their own programs over a shared floor, inbox and outbox.
+//!
+//! Each worker has its own hand and runs one instruction at a time, in the order chosen by a
+//! deterministic `Scheduler`. Instructions behave as with a single worker, except that reading
+//! an empty tile makes the worker wait until another worker puts a value there. The run ends
+//! when every worker has run past its last instruction or found the inbox empty, or in a
+//! deadlock when every worker left is waiting.
+//!
+//! A conflict is recorded when a worker overwrites a tile holding a value written by another
+//! worker without having read it first, an update the other worker loses.
+
+use crate::interpreter::{checked_value, InterpreterError, SimpleInterpreter, FLOOR_SIZE};
+use crate::lexer::{Location, Program, TokenKind};
+use crate::testcase::STEP_LIMIT;
+use std::collections::VecDeque;
+
+/// Order in which the workers take turns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scheduler {
+    /// Each worker in turn runs `quantum` instructions, or until it waits or ends.
+    RoundRobin { quantum: usize },
+    /// A worker picked at random among the ones able to run takes each step.
+    Random { seed: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Config {
+    pub scheduler: Scheduler,
+    /// Steps of all the workers after which the run is stopped.
+    pub max_steps: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            scheduler: Scheduler::RoundRobin { quantum: 1 },
+            max_steps: STEP_LIMIT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Conflict {
+    /// Step of the run at which the tile was overwritten.
+    pub step: usize,
+    pub worker: usize,
+    pub tile: usize,
+    /// Worker whose value was lost.
+    pub overwritten: usize,
+    pub location: Location,
+}
+
+/// A worker waiting for a value on `tile`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Waiting {
+    pub worker: usize,
+    pub tile: usize,
+    pub location: Location,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum End {
+    Finished,
+    Error {
+        worker: usize,
+        error: InterpreterError,
+    },
+    /// Every worker left waits for a tile no other worker will fill.
+    Deadlock(Vec<Waiting>),
+    StepLimitExceeded,
+}
+
+/// Why a run could not start.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SetupError {
+    /// The program of `worker` has a jump to an undefined label.
+    Program {
+        worker: usize,
+        error: InterpreterError,
+    },
+    /// A `(tile, value)` pair of the floor names a tile past the end of the floor, or a value
+    /// out of `-MAX_VALUE..=MAX_VALUE`.
+    InvalidFloor { tile: usize, value: i16 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Run {
+    pub end: End,
+    pub outbox: Vec<i16>,
+    pub floor: Vec<Option<i16>>,
+    /// Steps taken by each worker.
+    pub steps: Vec<usize>,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Tiles read and written by `kind`.
+fn accesses(kind: &TokenKind) -> (Option<usize>, Option<usize>) {
+    match *kind {
+        TokenKind::CopyFrom(index) | TokenKind::Add(index) | TokenKind::Sub(index) => {
+            (Some(index), None)
+        }
+        TokenKind::CopyTo(index) => (None, Some(index)),
+        TokenKind::BumpPlus(index) | TokenKind::BumpMinus(index) => (Some(index), Some(index)),
+        _ => (None, None),
+    }
+}
+
+struct Workers<'a> {
+    programs: &'a [Program],
+    interpreters: Vec<SimpleInterpreter>,
+    finished: Vec<bool>,
+    floor: Vec<Option<i16>>,
+    inbox: VecDeque<i16>,
+    outbox: Vec<i16>,
+    /// Last worker writing each tile.
+    writers: Vec<Option<usize>>,
+    /// Whether each worker has accessed each tile since another worker last wrote it.
+    seen: Vec<Vec<bool>>,
+    conflicts: Vec<Conflict>,
+    step: usize,
+}
+
+impl Workers<'_> {
+    /// Skips the labels at the cursor of `worker`, marking it finished past its last instruction.
+    fn skip_labels(&mut self, worker: usize) {
+        let program = &self.programs[worker];
+        let interpreter = &mut self.interpreters[worker];
+        while let Some(token) = program.get(interpreter.cursor()) {
+            if !matches!(token.value, TokenKind::JumpTarget(_)) {
+                return;
+            }
+            interpreter.set_cursor(interpreter.cursor() + 1);
+        }
+        self.finished[worker] = true;
+    }
+
+    /// The tile `worker` waits for, if its next instruction reads an empty tile.
+    fn waiting(&self, worker: usize) -> Option<Waiting> {
+        let token = &self.programs[worker][self.interpreters[worker].cursor()];
+        match accesses(&token.value) {
+            (Some(tile), _) if self.floor.get(tile) == Some(&None) => Some(Waiting {
+                worker,
+                tile,
+                location: token.location,
+            }),
+            _ => None,
+        }
+    }
+
+    fn runnable(&self) -> Vec<usize> {
+        (0..self.programs.len())
+            .filter(|&worker| !self.finished[worker] && self.waiting(worker).is_none())
+            .collect()
+    }
+
+    /// Runs the next instruction of `worker` on the shared floor, inbox and outbox.
+    fn step(&mut self, worker: usize) -> Result<(), InterpreterError> {
+        let token = &self.programs[worker][self.interpreters[worker].cursor()];
+        let (read, written) = accesses(&token.value);
+        let location = token.location;
+
+        let interpreter = &mut self.interpreters[worker];
+        let mut snapshot = interpreter.snapshot();
+        snapshot.cells = self.floor.clone();
+        // only the value an `inbox` could take is handed over.
+        snapshot.inbox = Some(self.inbox.front().copied().into_iter().collect());
+        snapshot.outbox = Vec::new();
+        interpreter.restore(&snapshot);
+        let running = interpreter.step()?;
+        let after = interpreter.snapshot();
+        if after.inbox.as_ref().is_some_and(|inbox| inbox.is_empty()) {
+            self.inbox.pop_front();
+        }
+        self.outbox.extend(after.outbox);
+        self.floor = after.cells;
+        if !running {
+            self.finished[worker] = true;
+            return Ok(());
+        }
+        self.step += 1;
+
+        if let Some(tile) = read {
+            self.seen[worker][tile] = true;
+        }
+        if let Some(tile) = written {
+            if let Some(writer) = self.writers[tile] {
+                if writer != worker && !self.seen[worker][tile] {
+                    self.conflicts.push(Conflict {
+                        step: self.step,
+                        worker,
+                        tile,
+                        overwritten: writer,
+                        location,
+                    });
+                }
+            }
+            self.writers[tile] = Some(worker);
+            for (other, seen) in self.seen.iter_mut().enumerate() {
+                seen[tile] = other == worker;
+            }
+        }
+        self.skip_labels(worker);
+        Ok(())
+    }
+}
+
+/// Runs `programs` side by side, each with its own worker, sharing `inbox`, the floor seeded
+/// with `floor`, and the outbox.
+///
+/// Fails when a program has a jump to an undefined label, or `floor` holds a tile or value
+/// no level has.
+pub fn run(
+    programs: &[Program],
+    inbox: &[i16],
+    floor: &[(usize, i16)],
+    config: &Config,
+) -> Result<Run, SetupError> {
+    let mut interpreters = Vec::new();
+    for (worker, program) in programs.iter().enumerate() {
+        let mut interpreter = SimpleInterpreter::new();
+        interpreter.set_print_outbox(false);
+        interpreter
+            .load(program)
+            .map_err(|error| SetupError::Program { worker, error })?;
+        interpreters.push(interpreter);
+    }
+    let mut cells = vec![None; FLOOR_SIZE];
+    for &(tile, value) in floor.iter() {
+        if tile >= FLOOR_SIZE || checked_value(value as i32).is_none() {
+            return Err(SetupError::InvalidFloor { tile, value });
+        }
+        cells[tile] = Some(value);
+    }
+    let mut workers = Workers {
+        programs,
+        interpreters,
+        finished: vec![false; programs.len()],
+        floor: cells,
+        inbox: inbox.iter().copied().collect(),
+        outbox: Vec::new(),
+        writers: vec![None; FLOOR_SIZE],
+        seen: vec![vec![false; FLOOR_SIZE]; programs.len()],
+        conflicts: Vec::new(),
+        step: 0,
+    };
+    for worker in 0..programs.len() {
+        workers.skip_labels(worker);
+    }
+
+    let mut seed = match config.scheduler {
+        Scheduler::Random { seed } => seed,
+        Scheduler::RoundRobin { .. } => 0,
+    };
+    let mut next = |bound: u32| {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        (seed >> 16) % bound
+    };
+    // worker whose turn it is, and steps left in its turn.
+    let mut turn = (programs.len(), 0);
+    let end = loop {
+        if workers.finished.iter().all(|finished| *finished) {
+            break End::Finished;
+        }
+        if workers.step >= config.max_steps {
+            break End::StepLimitExceeded;
+        }
+        let runnable = workers.runnable();
+        if runnable.is_empty() {
+            let waiting = (0..programs.len())
+                .filter(|&worker| !workers.finished[worker])
+                .filter_map(|worker| workers.waiting(worker))
+                .collect();
+            break End::Deadlock(waiting);
+        }
+        let worker = match config.scheduler {
+            Scheduler::RoundRobin { quantum } => {
+                let (current, left) = turn;
+                if left > 0 && runnable.contains(&current) {
+                    turn.1 -= 1;
+                    current
+                } else {
+                    let following = runnable.iter().find(|&&worker| worker > current);
+                    let worker = *following.unwrap_or(&runnable[0]);
+                    turn = (worker, quantum.max(1) - 1);
+                    worker
+                }
+            }
+            Scheduler::Random { .. } => runnable[next(runnable.len() as u32) as usize],
+        };
+        if let Err(error) = workers.step(worker) {
+            break End::Error { worker, error };
+        }
+    };
+    Ok(Run {
+        end,
+        outbox: workers.outbox,
+        floor: workers.floor,
+        steps: workers
+            .interpreters
+            .iter()
+            .map(|interpreter| interpreter.steps())
+            .collect(),
+        conflicts: workers.conflicts,
+    })
+}
@@ -0,0 +1,154 @@
+use hrm::asm::{assemble, AssembleError, AssembleErrorKind, Assembly};
+use hrm::lexer::Location;
+use hrm::printer;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn expanded(source: &str) -> String {
+    printer::print(&assemble(Path::new("test.hrm"), source).unwrap().program)
+}
+
+fn error(source: &str) -> (AssembleErrorKind, Location) {
+    let AssembleError { kind, location, .. } = assemble(Path::new("test.hrm"), source).unwrap_err();
+    (kind, location)
+}
+
+fn at(line: usize, col: usize) -> Location {
+    Location { line, col }
+}
+
+#[test]
+fn aliases_name_tiles_but_not_labels() {
+    assert_eq!(
+        expanded("alias n = 2\nalias top 3\njump_target n\ninbox\ncopyto n\nadd top\njump n\n"),
+        "jump_target n\n    inbox\n    copyto 2\n    add 3\n    jump n\n"
+    );
+}
+
+#[test]
+fn macros_take_arguments_and_uniquify_labels() {
+    let source = "macro twice tile
+    jump_target again
+    bump_plus tile
+    jump_if_neg again
+endmacro
+inbox
+copyto 0
+twice 0
+twice 1
+";
+    assert_eq!(
+        expanded(source),
+        "inbox
+copyto 0
+jump_target again__twice_1
+    bump_plus 0
+    jump_if_neg again__twice_1
+jump_target again__twice_2
+    bump_plus 1
+    jump_if_neg again__twice_2
+"
+    );
+}
+
+#[test]
+fn include_reads_macros_relative_to_the_file() {
+    let path = Path::new("sample_program/asm/main.hrm");
+    let Assembly {
+        program,
+        files,
+        origins,
+    } = assemble(path, &fs::read_to_string(path).unwrap()).unwrap();
+    assert_eq!(
+        files,
+        [
+            PathBuf::from("sample_program/asm/main.hrm"),
+            PathBuf::from("sample_program/asm/countdown.hrm")
+        ]
+    );
+    let locations: Vec<(usize, Location)> = origins
+        .into_iter()
+        .zip(program.iter().map(|token| token.location))
+        .collect();
+    // instructions expanded from the macro keep their place in countdown.hrm.
+    assert_eq!(
+        locations,
+        [
+            (0, at(4, 13)),
+            (0, at(5, 5)),
+            (0, at(6, 12)),
+            (1, at(3, 17)),
+            (1, at(4, 18)),
+            (1, at(5, 9)),
+            (1, at(6, 18)),
+            (1, at(7, 22)),
+            (1, at(8, 20)),
+            (1, at(9, 14)),
+            (1, at(10, 17)),
+            (0, at(8, 6)),
+        ]
+    );
+}
+
+#[test]
+fn instructions_may_continue_on_the_next_line() {
+    let assembly = assemble(
+        Path::new("test.hrm"),
+        "alias n 1\ncopyfrom\n  n outbox\njump\n\n end\n",
+    )
+    .unwrap();
+    assert_eq!(
+        printer::print(&assembly.program),
+        "copyfrom 1\noutbox\njump end\n"
+    );
+    let locations: Vec<Location> = assembly
+        .program
+        .iter()
+        .map(|token| token.location)
+        .collect();
+    assert_eq!(locations, [at(3, 3), at(3, 5), at(6, 2)]);
+}
+
+#[test]
+fn errors_point_at_the_offending_word() {
+    assert_eq!(
+        error("inbox\n  include \"missing.hrm\"\n"),
+        (
+            AssembleErrorKind::FileNotFound(PathBuf::from("missing.hrm")),
+            at(2, 11)
+        )
+    );
+    assert_eq!(
+        error("macro m\nendmacro\n   macro m\nendmacro\n"),
+        (
+            AssembleErrorKind::DuplicateMacro("m".to_string()),
+            at(3, 10)
+        )
+    );
+    assert_eq!(
+        error("macro m tile\n    add tile\nendmacro\n  m 1 2\n"),
+        (
+            AssembleErrorKind::ArgumentCount {
+                name: "m".to_string(),
+                expected: 1,
+                found: 2
+            },
+            at(4, 3)
+        )
+    );
+    assert_eq!(
+        error("inbox\n\t endmacro\n"),
+        (AssembleErrorKind::UnexpectedEndMacro, at(2, 3))
+    );
+    assert_eq!(
+        error("  macro m\n    inbox\n"),
+        (
+            AssembleErrorKind::UnterminatedMacro("m".to_string()),
+            at(1, 3)
+        )
+    );
+    assert_eq!(
+        error("macro m\n  m\nendmacro\nm\n"),
+        (AssembleErrorKind::RecursiveMacro("m".to_string()), at(2, 3))
+    );
+}
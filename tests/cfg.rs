@@ -0,0 +1,173 @@
+use hrm::cfg::{
+    dominators, immediate_dominator, BlockEdge, BlockGraph, ControlFlowGraph, Edge, EdgeKind,
+};
+use hrm::lexer::Lexer;
+
+/// A loop back to `a` from two places, a jump out of it to `b`, a jump to an undefined label
+/// and an instruction no path reaches.
+const SOURCE: &str = "jump_target a
+    inbox
+    jump_if_zero b
+    copyto 0
+    jump_if_neg a
+    outbox
+    jump a
+jump_target b
+    jump nowhere
+    outbox
+";
+
+fn edge(from: usize, to: usize, kind: EdgeKind) -> Edge {
+    Edge { from, to, kind }
+}
+
+#[test]
+fn edges_follow_jumps_and_fallthroughs() {
+    let cfg = ControlFlowGraph::new(&Lexer::lex(SOURCE));
+    assert_eq!(cfg.exit(), 10);
+    assert_eq!(
+        cfg.successors(2),
+        [
+            edge(2, 7, EdgeKind::JumpIfZero),
+            edge(2, 3, EdgeKind::Fallthrough)
+        ]
+    );
+    assert_eq!(
+        cfg.successors(4),
+        [
+            edge(4, 0, EdgeKind::JumpIfNeg),
+            edge(4, 5, EdgeKind::Fallthrough)
+        ]
+    );
+    assert_eq!(cfg.successors(6), [edge(6, 0, EdgeKind::Jump)]);
+    assert_eq!(cfg.successors(9), [edge(9, 10, EdgeKind::Fallthrough)]);
+    assert_eq!(
+        cfg.predecessors(0),
+        [edge(4, 0, EdgeKind::JumpIfNeg), edge(6, 0, EdgeKind::Jump)]
+    );
+}
+
+#[test]
+fn undefined_labels_have_no_edge() {
+    let cfg = ControlFlowGraph::new(&Lexer::lex(SOURCE));
+    assert_eq!(cfg.unresolved_jumps(), [8]);
+    assert!(cfg.successors(8).is_empty());
+
+    let cfg = ControlFlowGraph::new(&Lexer::lex("inbox\njump_if_zero nowhere\noutbox\n"));
+    assert_eq!(cfg.unresolved_jumps(), [1]);
+    assert_eq!(cfg.successors(1), [edge(1, 2, EdgeKind::Fallthrough)]);
+}
+
+#[test]
+fn dominators_and_reachability() {
+    let cfg = ControlFlowGraph::new(&Lexer::lex(SOURCE));
+    let reachable = cfg.reachable();
+    assert_eq!(reachable.iter().filter(|r| **r).count(), 9);
+    assert!(!reachable[9] && !reachable[10]);
+
+    let dominators = cfg.dominators();
+    let of = |node: usize| -> Vec<usize> {
+        (0..dominators[node].len())
+            .filter(|&other| dominators[node][other])
+            .collect()
+    };
+    assert_eq!(of(0), [0]);
+    assert_eq!(of(4), [0, 1, 2, 3, 4]);
+    assert_eq!(of(6), [0, 1, 2, 3, 4, 5, 6]);
+    assert_eq!(of(7), [0, 1, 2, 7]);
+    assert!(dominators[9].is_empty());
+
+    assert_eq!(immediate_dominator(&dominators, 0), None);
+    assert_eq!(immediate_dominator(&dominators, 3), Some(2));
+    assert_eq!(immediate_dominator(&dominators, 7), Some(2));
+    assert_eq!(immediate_dominator(&dominators, 9), None);
+}
+
+#[test]
+fn post_dominators_over_reversed_edges() {
+    // 0 branches to 1 and 2, which both go on to 3.
+    let reversed = vec![vec![], vec![0], vec![0], vec![1, 2]];
+    let post = dominators(&reversed, 3);
+    assert_eq!(immediate_dominator(&post, 0), Some(3));
+    assert_eq!(immediate_dominator(&post, 1), Some(3));
+    assert_eq!(immediate_dominator(&post, 3), None);
+}
+
+#[test]
+fn natural_loops_merge_back_edges_to_a_header() {
+    let cfg = ControlFlowGraph::new(&Lexer::lex(SOURCE));
+    let loops = cfg.natural_loops();
+    assert_eq!(loops.len(), 1);
+    assert_eq!(loops[0].header, 0);
+    assert_eq!(loops[0].latches, [4, 6]);
+    assert_eq!(loops[0].body, [0, 1, 2, 3, 4, 5, 6]);
+
+    let nested = "inbox
+jump_target outer
+    copyto 0
+jump_target inner
+    bump_minus 0
+    jump_if_neg outer
+    jump_if_zero done
+    jump inner
+jump_target done
+    outbox
+";
+    let loops = ControlFlowGraph::new(&Lexer::lex(nested)).natural_loops();
+    let shapes: Vec<(usize, Vec<usize>)> = loops
+        .iter()
+        .map(|natural| (natural.header, natural.body.clone()))
+        .collect();
+    assert_eq!(
+        shapes,
+        [(1, vec![1, 2, 3, 4, 5, 6, 7]), (3, vec![3, 4, 5, 6, 7])]
+    );
+    assert!(ControlFlowGraph::new(&Lexer::lex("inbox\noutbox\n"))
+        .natural_loops()
+        .is_empty());
+}
+
+#[test]
+fn blocks_split_at_labels_and_after_jumps() {
+    let program = Lexer::lex(SOURCE);
+    let graph = BlockGraph::new(&program);
+    let shapes: Vec<(usize, usize, Option<&str>)> = graph
+        .blocks
+        .iter()
+        .map(|block| (block.start, block.end, block.label.as_deref()))
+        .collect();
+    assert_eq!(
+        shapes,
+        [
+            (0, 3, Some("a")),
+            (3, 5, None),
+            (5, 7, None),
+            (7, 9, Some("b")),
+            (9, 10, None)
+        ]
+    );
+    assert_eq!(graph.exit(), 5);
+    assert_eq!(graph.block_of(4), 1);
+    assert_eq!(graph.block_of(10), 5);
+
+    let to = |to: usize, kind: EdgeKind| BlockEdge { to, kind };
+    assert_eq!(
+        graph.blocks[0].successors,
+        [to(3, EdgeKind::JumpIfZero), to(1, EdgeKind::Fallthrough)]
+    );
+    assert_eq!(
+        graph.blocks[1].successors,
+        [to(0, EdgeKind::JumpIfNeg), to(2, EdgeKind::Fallthrough)]
+    );
+    assert_eq!(graph.blocks[2].successors, [to(0, EdgeKind::Jump)]);
+    assert!(graph.blocks[3].successors.is_empty());
+    assert_eq!(graph.blocks[4].successors, [to(5, EdgeKind::Fallthrough)]);
+
+    let dot = graph.to_dot(&program, None);
+    assert!(
+        dot.contains("    b0 -> b3 [label=\"if zero\"];\n"),
+        "{}",
+        dot
+    );
+    assert!(dot.contains("    b4 -> exit;\n"), "{}", dot);
+}
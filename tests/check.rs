@@ -0,0 +1,84 @@
+use hrm::check::{analyze, Content};
+use hrm::lexer::Lexer;
+
+fn diagnostics(source: &str) -> Vec<String> {
+    analyze(&Lexer::lex(source))
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect()
+}
+
+#[test]
+fn hand_always_empty() {
+    assert_eq!(
+        diagnostics("outbox\n"),
+        ["1:1: error: hand is always empty (EmptyHandValue)"]
+    );
+    assert_eq!(
+        diagnostics("inbox\ncopyto 0\noutbox\n"),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn hand_possibly_empty() {
+    // `outbox` empties the hand, except on the jump around the first one.
+    let source = "inbox\njump_if_zero skip\noutbox\njump_target skip\noutbox\n";
+    assert_eq!(
+        diagnostics(source),
+        ["5:1: warning: hand is possibly empty (EmptyHandValue)"]
+    );
+    // a conditional jump is only taken with a value in the hand.
+    let source = "inbox\njump_if_zero skip\ninbox\njump_target skip\noutbox\n";
+    assert_eq!(diagnostics(source), Vec::<String>::new());
+}
+
+#[test]
+fn tile_always_empty() {
+    assert_eq!(
+        diagnostics("inbox\nadd 2\n"),
+        ["2:5: error: tile 2 is always empty (EmptyFloorValue)"]
+    );
+    assert_eq!(
+        diagnostics("inbox\ncopyto 2\nadd 2\nbump_plus 2\n"),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn tile_possibly_empty() {
+    let source = "inbox\njump_if_zero skip\ncopyto 0\njump_target skip\ncopyfrom 0\n";
+    assert_eq!(
+        diagnostics(source),
+        ["5:10: warning: tile 0 is possibly empty (EmptyFloorValue)"]
+    );
+    let source = "inbox\ncopyto 0\njump_if_zero skip\ncopyto 0\njump_target skip\ncopyfrom 0\n";
+    assert_eq!(diagnostics(source), Vec::<String>::new());
+}
+
+#[test]
+fn undefined_jump_target() {
+    assert_eq!(
+        diagnostics("inbox\njump_if_neg nowhere\noutbox\n"),
+        ["2:13: error: jump target is not defined (UnexistedJumpTarget)"]
+    );
+    assert_eq!(
+        diagnostics("jump_target start\ninbox\noutbox\njump start\n"),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn states_stop_at_errors_and_join_at_labels() {
+    let analysis = analyze(&Lexer::lex("outbox\ninbox\n"));
+    assert!(analysis.states[0].is_some());
+    assert!(analysis.states[1].is_none());
+
+    let source = "inbox\njump_if_zero skip\ncopyto 0\njump_target skip\ncopyfrom 0\n";
+    let analysis = analyze(&Lexer::lex(source));
+    let state = analysis.states[4].as_ref().unwrap();
+    assert_eq!(state.hand, Content::Full);
+    assert_eq!(state.tile(0), Content::Unknown);
+    assert_eq!(state.tile(5), Content::Empty);
+}
@@ -0,0 +1,91 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Directory for the files of one test, removed and created again.
+fn scratch(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("hrm-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn hrm(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_hrm"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn expected_outbox_mismatch_fails_the_run() {
+    let dir = scratch("expected");
+    let program = dir.join("echo.hrm");
+    fs::write(&program, "jump_target a\ninbox\noutbox\njump a\n").unwrap();
+    fs::write(dir.join("input.txt"), "1\n2\n").unwrap();
+    let run = |expected: &str| {
+        fs::write(dir.join("expected.txt"), expected).unwrap();
+        hrm(&[
+            program.to_str().unwrap(),
+            "-i",
+            dir.join("input.txt").to_str().unwrap(),
+            "-e",
+            dir.join("expected.txt").to_str().unwrap(),
+        ])
+    };
+
+    let output = run("1 2\n");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "1\n2\n");
+
+    let output = run("1 3\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "1\n");
+
+    let output = run("1 2 3\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "1\n2\n");
+}
+
+#[test]
+fn saved_runs_resume_with_the_saved_inbox() {
+    let dir = scratch("state");
+    let state = dir.join("state.txt");
+    let state = state.to_str().unwrap();
+    let program = "sample_program/19.hrm";
+    let input = "sample_program/input.txt";
+
+    let uninterrupted = hrm(&[program, "-i", input]);
+    let paused = hrm(&[
+        program,
+        "-i",
+        input,
+        "--stop-after",
+        "10",
+        "--save-state",
+        state,
+    ]);
+    assert!(paused.status.success());
+    assert_eq!(stdout(&paused), "1\n0\n");
+    let resumed = hrm(&[program, "--load-state", state]);
+    assert!(resumed.status.success());
+    assert_eq!(stdout(&paused) + &stdout(&resumed), stdout(&uninterrupted));
+
+    let both = hrm(&[program, "-i", input, "--load-state", state]);
+    assert_eq!(both.status.code(), Some(64));
+    assert_eq!(stdout(&both), "");
+}
+
+#[test]
+fn floor_values_outside_the_level_are_rejected() {
+    let count = "sample_program/workers/count.hrm";
+    for floor in ["0=0,1=1000", "6=1", "0=-1000"] {
+        let output = hrm(&["workers", count, count, "--floor", floor]);
+        assert_eq!(output.status.code(), Some(64), "{}", floor);
+    }
+}
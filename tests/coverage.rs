@@ -0,0 +1,83 @@
+use hrm::coverage::Coverage;
+use hrm::interpreter::{RunReport, SimpleInterpreter};
+use hrm::lexer::{Lexer, Program};
+
+const SOURCE: &str = "jump_target top
+    inbox
+    jump_if_neg negative # x < 0 && x > -1000
+    outbox
+    jump top
+jump_target negative
+    copyto 0
+";
+
+fn report(program: &Program, inbox: &[i16]) -> RunReport {
+    let mut interpreter = SimpleInterpreter::new();
+    interpreter.set_print_outbox(false);
+    interpreter.set_inbox_values(inbox);
+    interpreter.eval(program).unwrap();
+    interpreter.report()
+}
+
+fn coverage(inboxes: &[&[i16]]) -> (Program, Coverage) {
+    let program = Lexer::lex(SOURCE);
+    let mut coverage = Coverage::new(&program);
+    for inbox in inboxes {
+        coverage.add(&report(&program, inbox));
+    }
+    (program, coverage)
+}
+
+#[test]
+fn counts_add_up_over_runs() {
+    let (program, coverage) = coverage(&[&[1, 2], &[3, -4]]);
+    assert_eq!(coverage.runs, 2);
+    assert_eq!(coverage.hits, [5, 5, 4, 3, 3, 1, 1]);
+    assert_eq!(coverage.branches[2], (1, 3));
+    assert_eq!(coverage.executed(&program), (5, 5));
+    assert_eq!(coverage.directions(&program), (2, 2));
+}
+
+#[test]
+fn text_marks_lines_never_run_and_jumps_going_one_way() {
+    let (program, coverage) = coverage(&[&[1, 2]]);
+    assert_eq!(coverage.directions(&program), (1, 2));
+    assert_eq!(
+        coverage.to_text(&program, SOURCE),
+        "    hits   taken/not   |
+                       | jump_target top
+       3               |     inbox
+       2         0/2 ! |     jump_if_neg negative # x < 0 && x > -1000
+       2               |     outbox
+       2               |     jump top
+                       | jump_target negative
+       0             ! |     copyto 0
+4 of 5 instructions executed, 1 of 2 jump directions taken, over 1 run
+"
+    );
+}
+
+#[test]
+fn html_escapes_the_source_and_title() {
+    let (program, coverage) = coverage(&[&[1, 2], &[-4]]);
+    let html = coverage.to_html(&program, SOURCE, "<solution> & more");
+    assert!(
+        html.contains("<title>&lt;solution&gt; &amp; more</title>"),
+        "{}",
+        html
+    );
+    assert!(
+        html.contains(
+            "<tr class=\"covered\"><td class=\"count\">3</td><td class=\"count\">1/2</td>\
+             <td>    jump_if_neg negative # x &lt; 0 &amp;&amp; x &gt; -1000</td></tr>\n"
+        ),
+        "{}",
+        html
+    );
+    assert!(html.contains(
+        "<tr><td class=\"count\"></td><td class=\"count\"></td><td>jump_target top</td></tr>\n"
+    ));
+    assert!(html.contains(
+        "<p>5 of 5 instructions executed, 2 of 2 jump directions taken, over 2 runs</p>"
+    ));
+}
@@ -0,0 +1,102 @@
+use hrm::decompiler::decompile;
+use hrm::interpreter::InterpreterErrorKind;
+use hrm::lexer::{Lexer, Location};
+use std::fs;
+
+fn sample(name: &str) -> String {
+    let source = fs::read_to_string(format!("sample_program/{}.hrm", name)).unwrap();
+    decompile(&Lexer::lex(&source)).unwrap()
+}
+
+#[test]
+fn swaps_pairs_in_one_loop() {
+    assert_eq!(
+        sample("04"),
+        "loop {
+    hand = inbox();
+    t0 = hand;
+    hand = inbox();
+    outbox(hand);
+    hand = t0;
+    outbox(hand);
+}
+"
+    );
+}
+
+#[test]
+fn keeps_every_tile_of_sample_12() {
+    assert_eq!(
+        sample("12"),
+        "loop {
+    hand = inbox();
+    t0 = hand;
+    hand = hand + t0;
+    t1 = hand;
+    hand = hand + t1;
+    t2 = hand;
+    hand = hand + t2;
+    t3 = hand;
+    hand = hand + t3;
+    t4 = hand;
+    hand = hand + t4;
+    hand = hand + t3;
+    outbox(hand);
+}
+"
+    );
+}
+
+#[test]
+fn nests_a_loop_with_an_else_if_chain() {
+    assert_eq!(
+        sample("19"),
+        "loop {
+    hand = inbox();
+    t0 = hand;
+    loop {
+        outbox(hand);
+        hand = t0;
+        if hand == 0 {
+            break;
+        } else if hand < 0 {
+            hand = ++t0;
+        } else {
+            hand = --t0;
+        }
+    }
+}
+"
+    );
+}
+
+#[test]
+fn irreducible_flow_falls_back_to_goto() {
+    // the loop between `a` and `b` is entered at both labels.
+    let source = "inbox\njump_if_zero b\njump_target a\noutbox\ninbox\njump_target b\njump_if_neg a\noutbox\n";
+    assert_eq!(
+        decompile(&Lexer::lex(source)).unwrap(),
+        "hand = inbox();
+if hand == 0 {
+b:
+    if hand < 0 {
+a:
+        outbox(hand);
+        hand = inbox();
+        goto b;
+    } else {
+        outbox(hand);
+    }
+} else {
+    goto a;
+}
+"
+    );
+}
+
+#[test]
+fn undefined_label_is_an_error() {
+    let error = decompile(&Lexer::lex("inbox\njump_if_zero nowhere\noutbox\n")).unwrap_err();
+    assert_eq!(error.value, InterpreterErrorKind::UnexistedJumpTarget);
+    assert_eq!(error.location, Location { line: 2, col: 14 });
+}
@@ -0,0 +1,76 @@
+use hrm::equiv::{compare, Behaviour, Difference, Equivalence};
+use hrm::interpreter::InterpreterErrorKind;
+use hrm::lexer::{Lexer, Location};
+use hrm::superopt::exhaustive_inboxes;
+
+const ECHO: &str = "jump_target a\ninbox\noutbox\njump a\n";
+
+fn equivalence(left: &str, right: &str) -> Equivalence {
+    compare(&Lexer::lex(left), &Lexer::lex(right), &[], 1000).unwrap()
+}
+
+#[test]
+fn equivalent_programs() {
+    let equivalence = equivalence(
+        ECHO,
+        "jump_target a\ninbox\ncopyto 0\ncopyfrom 0\noutbox\njump a\n",
+    );
+    assert_eq!(equivalence.difference, None);
+    assert_eq!(
+        equivalence.inboxes,
+        exhaustive_inboxes().len() + hrm::equiv::RANDOM_INBOXES
+    );
+}
+
+#[test]
+fn smallest_counterexample_is_reported() {
+    let skip_zeros = "jump_target a\ninbox\njump_if_zero a\noutbox\njump a\n";
+    assert_eq!(
+        equivalence(ECHO, skip_zeros).difference,
+        Some(Difference {
+            inbox: vec![0],
+            left: Behaviour {
+                outbox: vec![0],
+                error: None
+            },
+            right: Behaviour {
+                outbox: vec![],
+                error: None
+            },
+        })
+    );
+
+    let difference = equivalence(ECHO, "jump_target a\ninbox\noutbox\noutbox\njump a\n")
+        .difference
+        .unwrap();
+    assert_eq!(difference.inbox, [0]);
+    let error = difference.right.error.unwrap();
+    assert_eq!(error.value, InterpreterErrorKind::EmptyHandValue);
+    assert_eq!(difference.left.error, None);
+}
+
+#[test]
+fn errors_match_by_kind_not_location() {
+    let left = "inbox\noutbox\noutbox\n";
+    let right = "inbox\ncopyto 0\noutbox\njump_target b\noutbox\n";
+    assert_eq!(equivalence(left, right).difference, None);
+
+    // runs stopped at the step limit are alike whatever they put into the outbox.
+    let endless = Lexer::lex("jump_target a\njump a\n");
+    let looping = Lexer::lex("copyfrom 0\noutbox\njump_target b\njump b\n");
+    let equivalence = compare(&endless, &looping, &[(0, 5)], 100).unwrap();
+    assert_eq!(equivalence.difference, None);
+}
+
+#[test]
+fn undefined_label_is_an_error() {
+    let error = compare(
+        &Lexer::lex(ECHO),
+        &Lexer::lex("inbox\njump nowhere\n"),
+        &[],
+        1000,
+    )
+    .unwrap_err();
+    assert_eq!(error.value, InterpreterErrorKind::UnexistedJumpTarget);
+    assert_eq!(error.location, Location { line: 2, col: 6 });
+}
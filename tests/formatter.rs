@@ -0,0 +1,64 @@
+use hrm::formatter::{format, FormatErrorKind};
+use hrm::lexer::{Lexer, Location};
+use std::fs;
+
+const SAMPLES: [&str; 8] = ["01", "02", "03", "04", "06", "09", "12", "19"];
+
+#[test]
+fn canonical_spelling_spacing_and_indentation() {
+    let source = "  INBOX   # first\n\n\n\nJUMPZ   end\tcopyto    0\n#note\njump_target end\n  BumpUp 0 outbox\n";
+    assert_eq!(
+        format(source).unwrap(),
+        "inbox # first
+
+jump_if_zero end
+copyto 0
+# note
+jump_target end
+    bump_plus 0
+    outbox
+"
+    );
+}
+
+#[test]
+fn comments_take_the_indentation_of_the_next_instruction() {
+    assert_eq!(
+        format("jump_target a\n# loop body\ninbox\n#\noutbox\n").unwrap(),
+        "jump_target a\n    # loop body\n    inbox\n    #\n    outbox\n"
+    );
+}
+
+#[test]
+fn formatting_is_idempotent_and_keeps_the_program() {
+    for name in SAMPLES.iter() {
+        let source = fs::read_to_string(format!("sample_program/{}.hrm", name)).unwrap();
+        let formatted = format(&source).unwrap();
+        assert_eq!(format(&formatted).unwrap(), formatted, "{}", name);
+        let kinds = |source: &str| -> Vec<_> {
+            Lexer::lex(source)
+                .into_iter()
+                .map(|token| token.value)
+                .collect()
+        };
+        assert_eq!(kinds(&formatted), kinds(&source), "{}", name);
+    }
+}
+
+#[test]
+fn input_the_lexer_would_skip_is_rejected() {
+    for (source, text, location) in [
+        ("inbox\n  bump 0\n", "bump 0", Location { line: 2, col: 3 }),
+        ("copyfrom x\n", "copyfrom x", Location { line: 1, col: 1 }),
+        ("inbox\ncopyto\n0\n", "copyto", Location { line: 2, col: 1 }),
+    ] {
+        let error = format(source).unwrap_err();
+        assert_eq!(
+            error.value,
+            FormatErrorKind::UnrecognisedInput(text.to_string()),
+            "{}",
+            source
+        );
+        assert_eq!(error.location, location, "{}", source);
+    }
+}
@@ -0,0 +1,206 @@
+use hrm::bytecode::{Bytecode, BytecodeInterpreter};
+use hrm::interpreter::{InterpreterError, InterpreterErrorKind, SimpleInterpreter};
+use hrm::lexer::{Lexer, Location};
+use std::fs;
+
+/// A program failing at `location` after putting `outbox` into the outbox.
+struct Failure {
+    source: &'static str,
+    inbox: &'static [i16],
+    kind: InterpreterErrorKind,
+    location: Location,
+    outbox: &'static [i16],
+}
+
+const FAILURES: [Failure; 5] = [
+    Failure {
+        source: "inbox\ncopyto 0\nadd 0\ncopyto 1\noutbox\ncopyfrom 1\nadd 0\n",
+        inbox: &[400],
+        kind: InterpreterErrorKind::Overflow,
+        location: Location { line: 7, col: 5 },
+        outbox: &[800],
+    },
+    Failure {
+        source: "inbox\ncopyto 0\nsub 0\nsub 0\ncopyto 1\noutbox\ncopyfrom 1\nsub 0\n",
+        inbox: &[-500],
+        kind: InterpreterErrorKind::Overflow,
+        location: Location { line: 8, col: 5 },
+        outbox: &[500],
+    },
+    Failure {
+        source: "inbox\ncopyto 0\nbump_minus 0\n",
+        inbox: &[-999],
+        kind: InterpreterErrorKind::Overflow,
+        location: Location { line: 3, col: 12 },
+        outbox: &[],
+    },
+    Failure {
+        source: "inbox\noutbox\ninbox\ncopyto 6\n",
+        inbox: &[999, 3],
+        kind: InterpreterErrorKind::FloorOutOfRange,
+        location: Location { line: 4, col: 8 },
+        outbox: &[999],
+    },
+    Failure {
+        source: "copyfrom 24\n",
+        inbox: &[],
+        kind: InterpreterErrorKind::FloorOutOfRange,
+        location: Location { line: 1, col: 10 },
+        outbox: &[],
+    },
+];
+
+#[test]
+fn overflow_and_floor_out_of_range() {
+    for Failure {
+        source,
+        inbox,
+        kind,
+        location,
+        outbox,
+    } in FAILURES.iter()
+    {
+        let program = Lexer::lex(source);
+        let location = *location;
+
+        let mut simple = SimpleInterpreter::new();
+        simple.set_print_outbox(false);
+        simple.set_inbox_values(inbox);
+        let error = simple.eval(&program).unwrap_err();
+        assert_eq!(
+            (&error.value, error.location),
+            (kind, location),
+            "{}",
+            source
+        );
+        assert_eq!(simple.outbox(), *outbox, "{}", source);
+
+        let mut bytecode = BytecodeInterpreter::new();
+        bytecode.set_inbox_values(inbox);
+        let error = bytecode
+            .eval(&Bytecode::compile(&program).unwrap())
+            .unwrap_err();
+        assert_eq!(
+            (&error.value, error.location),
+            (kind, location),
+            "{}",
+            source
+        );
+        assert_eq!(bytecode.report().outbox, *outbox, "{}", source);
+    }
+}
+
+const SAMPLES: [&str; 8] = ["01", "02", "03", "04", "06", "09", "12", "19"];
+
+/// Result and outbox of both interpreters on `source`.
+fn both(source: &str, inbox: &[i16]) -> [(Result<usize, InterpreterError>, Vec<i16>); 2] {
+    let program = Lexer::lex(source);
+    let mut simple = SimpleInterpreter::new();
+    simple.set_print_outbox(false);
+    simple.set_inbox_values(inbox);
+    let simple = (simple.eval(&program), simple.report().outbox);
+
+    let mut bytecode = BytecodeInterpreter::new();
+    bytecode.set_inbox_values(inbox);
+    let bytecode = match Bytecode::compile(&program) {
+        Ok(code) => (bytecode.eval(&code), bytecode.report().outbox),
+        Err(e) => (Err(e), Vec::new()),
+    };
+    [simple, bytecode]
+}
+
+#[test]
+fn bytecode_matches_simple_interpreter() {
+    let mut seed: u32 = 29;
+    for name in SAMPLES.iter() {
+        let source = fs::read_to_string(format!("sample_program/{}.hrm", name)).unwrap();
+        for _ in 0..50 {
+            let inbox: Vec<i16> = (0..8)
+                .map(|_| {
+                    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                    ((seed >> 16) % 41) as i16 - 20
+                })
+                .collect();
+            let [simple, bytecode] = both(&source, &inbox);
+            assert_eq!(simple, bytecode, "{} on {:?}", name, inbox);
+        }
+    }
+
+    let failing: [(&str, &[i16]); 6] = [
+        ("outbox\n", &[]),
+        ("inbox\ncopyfrom 3\n", &[1]),
+        ("inbox\noutbox\nadd 0\n", &[1]),
+        (
+            "inbox\ncopyto 0\nbump_plus 0\njump_if_neg nowhere\njump missing\n",
+            &[1],
+        ),
+        ("inbox\ncopyto 0\nadd 0\ncopyto 0\nadd 0\n", &[300]),
+        ("inbox\nbump_minus 7\n", &[1]),
+    ];
+    for (source, inbox) in failing.iter() {
+        let [simple, bytecode] = both(source, inbox);
+        assert!(simple.0.is_err(), "{}", source);
+        assert_eq!(simple, bytecode, "{}", source);
+    }
+}
+
+#[test]
+fn first_undefined_label_is_reported() {
+    let source = "jump b\njump_if_zero c\njump_if_neg d\njump_target e\njump a\n";
+    for _ in 0..20 {
+        let mut interpreter = SimpleInterpreter::new();
+        let error = interpreter.eval(&Lexer::lex(source)).unwrap_err();
+        assert_eq!(error.value, InterpreterErrorKind::UnexistedJumpTarget);
+        assert_eq!(error.location, Location { line: 1, col: 6 });
+    }
+}
+
+/// Runs an echo program on `inbox`, expecting `expected` in the outbox.
+fn echo_expecting(inbox: &[i16], expected: &[i16]) -> (Result<usize, InterpreterError>, Vec<i16>) {
+    let mut interpreter = SimpleInterpreter::new();
+    interpreter.set_print_outbox(false);
+    interpreter.set_inbox_values(inbox);
+    interpreter.set_expected_outbox(expected);
+    let result = interpreter.eval(&Lexer::lex("jump_target a\ninbox\noutbox\njump a\n"));
+    (result, interpreter.outbox().to_vec())
+}
+
+#[test]
+fn expected_outbox() {
+    assert_eq!(echo_expecting(&[1, 2], &[1, 2]), (Ok(0), vec![1, 2]));
+
+    let (result, outbox) = echo_expecting(&[1, 2, 3], &[1, 5, 3]);
+    let error = result.unwrap_err();
+    assert_eq!(
+        error.value,
+        InterpreterErrorKind::WrongOutput {
+            index: 1,
+            expected: Some(5),
+            actual: 2
+        }
+    );
+    assert_eq!(error.location, Location { line: 3, col: 1 });
+    assert_eq!(outbox, [1]);
+
+    let (result, _) = echo_expecting(&[1, 2], &[1]);
+    assert_eq!(
+        result.unwrap_err().value,
+        InterpreterErrorKind::WrongOutput {
+            index: 1,
+            expected: None,
+            actual: 2
+        }
+    );
+
+    let (result, outbox) = echo_expecting(&[1], &[1, 2]);
+    let error = result.unwrap_err();
+    assert_eq!(
+        error.value,
+        InterpreterErrorKind::NotEnoughOutput {
+            expected: 2,
+            actual: 1
+        }
+    );
+    assert_eq!(error.location, Location { line: 2, col: 1 });
+    assert_eq!(outbox, [1]);
+}
@@ -0,0 +1,156 @@
+use hrm::interval::{analyze, Interval};
+use hrm::lexer::Lexer;
+use std::fs;
+
+/// The state before each instruction and the diagnostics, as `hrm ranges -v` prints them.
+fn ranges(source: &str, inbox: Interval, floor: &[(usize, i16)]) -> (Vec<String>, Vec<String>) {
+    let program = Lexer::lex(source);
+    let analysis = analyze(&program, inbox, floor);
+    let states = program
+        .iter()
+        .zip(analysis.states.iter())
+        .map(|(token, state)| {
+            let values = state
+                .as_ref()
+                .map_or("unreachable".to_string(), |state| state.to_string());
+            format!("{}: {}", token.value, values)
+        })
+        .collect();
+    let diagnostics = analysis
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect();
+    (states, diagnostics)
+}
+
+#[test]
+fn loops_widen_to_the_limits() {
+    let source = "jump_target top\n    bump_minus 0\n    jump_if_zero done\n    jump top\njump_target done\n    outbox\n";
+    assert_eq!(
+        ranges(source, Interval::FULL, &[(0, 10)]),
+        (
+            vec![
+                "jump_target top: hand -999..9, [0] -999..10".to_string(),
+                "bump_minus 0: hand -999..9, [0] -999..10".to_string(),
+                "jump_if_zero done: hand -999..9, [0] -999..9".to_string(),
+                "jump top: hand -999..9, [0] -999..9".to_string(),
+                "jump_target done: hand 0, [0] 0".to_string(),
+                "outbox: hand 0, [0] 0".to_string(),
+            ],
+            vec!["2:16: warning: result is possibly out of range (Overflow)".to_string()]
+        )
+    );
+}
+
+#[test]
+fn conditional_jumps_narrow_the_hand_and_its_copies() {
+    let source = "inbox
+copyto 0
+jump_if_neg neg
+copyfrom 0
+jump_if_zero zero
+outbox
+jump end
+jump_target zero
+copyto 1
+jump end
+jump_target neg
+copyfrom 0
+jump_target end
+";
+    let (states, diagnostics) = ranges(source, Interval::new(-5, 5), &[]);
+    assert_eq!(
+        states,
+        [
+            "inbox: hand -",
+            "copyto 0: hand -5..5",
+            "jump_if_neg neg: hand -5..5, [0] -5..5",
+            "copyfrom 0: hand 0..5, [0] 0..5",
+            "jump_if_zero zero: hand 0..5, [0] 0..5",
+            "outbox: hand 1..5, [0] 1..5",
+            "jump end: hand -, [0] 1..5",
+            "jump_target zero: hand 0, [0] 0",
+            "copyto 1: hand 0, [0] 0",
+            "jump end: hand 0, [0] 0, [1] 0",
+            "jump_target neg: hand -5..-1, [0] -5..-1",
+            "copyfrom 0: hand -5..-1, [0] -5..-1",
+            "jump_target end: hand -5..0, [0] -5..5, [1] 0",
+        ]
+    );
+    assert!(diagnostics.is_empty());
+}
+
+#[test]
+fn overflow_and_floor_out_of_range_are_reported() {
+    let source = "inbox\ncopyto 0\nadd 0\ncopyfrom 1\nbump_plus 1\noutbox\ncopyfrom 7\n";
+    let (states, diagnostics) = ranges(source, Interval::new(0, 600), &[(1, 999)]);
+    assert_eq!(
+        diagnostics,
+        [
+            "3:5: warning: result is possibly out of range (Overflow)",
+            "5:11: error: result is always out of range (Overflow)",
+        ]
+    );
+    assert_eq!(states[3], "copyfrom 1: hand 0..999, [0] 0..600, [1] 999");
+    assert_eq!(
+        states[5..],
+        ["outbox: unreachable", "copyfrom 7: unreachable"]
+    );
+
+    let (_, diagnostics) = ranges("inbox\ncopyto 6\n", Interval::FULL, &[]);
+    assert_eq!(
+        diagnostics,
+        ["2:8: error: tile 6 is past the end of the floor (FloorOutOfRange)"]
+    );
+}
+
+#[test]
+fn floor_entries_no_level_has_are_ignored() {
+    let (states, _) = ranges("inbox\n", Interval::FULL, &[(9, 1), (2, 5000), (3, -4)]);
+    assert_eq!(states, ["inbox: hand -, [3] -4"]);
+}
+
+#[test]
+fn sample_12() {
+    let source = fs::read_to_string("sample_program/12.hrm").unwrap();
+    let (_, diagnostics) = ranges(&source, Interval::FULL, &[]);
+    assert_eq!(
+        diagnostics,
+        [
+            "4:9: warning: result is possibly out of range (Overflow)",
+            "6:9: warning: result is possibly out of range (Overflow)",
+            "8:9: warning: result is possibly out of range (Overflow)",
+            "10:9: warning: result is possibly out of range (Overflow)",
+            "12:9: warning: result is possibly out of range (Overflow)",
+            "13:9: warning: result is possibly out of range (Overflow)",
+        ]
+    );
+
+    let (states, diagnostics) = ranges(&source, Interval::new(-10, 10), &[]);
+    let tiles = "[0] -10..10, [1] -20..20, [2] -40..40, [3] -80..80, [4] -160..160";
+    let hands = [
+        "-",
+        "-",
+        "-10..10",
+        "-10..10",
+        "-20..20",
+        "-20..20",
+        "-40..40",
+        "-40..40",
+        "-80..80",
+        "-80..80",
+        "-160..160",
+        "-160..160",
+        "-320..320",
+        "-400..400",
+        "-",
+    ];
+    let expected: Vec<String> = Lexer::lex(&source)
+        .iter()
+        .zip(hands.iter())
+        .map(|(token, hand)| format!("{}: hand {}, {}", token.value, hand, tiles))
+        .collect();
+    assert_eq!(states, expected);
+    assert!(diagnostics.is_empty());
+}
@@ -0,0 +1,25 @@
+use hrm::interpreter::SimpleInterpreter;
+use hrm::lang::compile;
+
+fn outbox(source: &str, inbox: &[i16]) -> Vec<i16> {
+    let program = compile(source).unwrap();
+    let mut interpreter = SimpleInterpreter::new();
+    interpreter.set_print_outbox(false);
+    interpreter.set_inbox_values(inbox);
+    interpreter.eval(&program).unwrap();
+    interpreter.outbox().to_vec()
+}
+
+#[test]
+fn temporaries_keep_off_assigned_variables() {
+    let source = "loop {
+    x = inbox();
+    outbox(x - (x + x));
+    if x == 0 {
+        z = x;
+    }
+    outbox(z);
+}
+";
+    assert_eq!(outbox(source, &[0, 5]), vec![0, 0, -5, 0]);
+}
@@ -0,0 +1,53 @@
+use hrm::lexer::{canonical_mnemonic, Lexer, Location, TokenKind};
+
+fn kinds(source: &str) -> Vec<TokenKind> {
+    Lexer::lex(source)
+        .into_iter()
+        .map(|token| token.value)
+        .collect()
+}
+
+#[test]
+fn mnemonics_ignore_case_and_accept_game_names() {
+    assert_eq!(
+        kinds("INBOX\nBumpUp 0\nbumpdn 1\nJUMPZ Loop\njumpn Loop\nJump_Target Loop\nOutBox\n"),
+        vec![
+            TokenKind::InBox,
+            TokenKind::BumpPlus(0),
+            TokenKind::BumpMinus(1),
+            TokenKind::JumpIfZero("Loop".to_string()),
+            TokenKind::JumpIfNeg("Loop".to_string()),
+            TokenKind::JumpTarget("Loop".to_string()),
+            TokenKind::OutBox,
+        ]
+    );
+    assert_eq!(canonical_mnemonic("CopyFrom"), Some("copyfrom"));
+    assert_eq!(canonical_mnemonic("bumpup"), Some("bump_plus"));
+    assert_eq!(canonical_mnemonic("jumpz"), Some("jump_if_zero"));
+    assert_eq!(canonical_mnemonic("bump"), None);
+    assert_eq!(canonical_mnemonic("main"), None);
+}
+
+#[test]
+fn comments_are_skipped_keeping_locations() {
+    let program = Lexer::lex("inbox # copyto 0\n# outbox\n  copyto 1 # jump x\n#\n");
+    let tokens: Vec<(TokenKind, Location)> = program
+        .into_iter()
+        .map(|token| (token.value, token.location))
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            (TokenKind::InBox, Location { line: 1, col: 1 }),
+            (TokenKind::CopyTo(1), Location { line: 3, col: 10 }),
+        ]
+    );
+}
+
+#[test]
+fn comment_needs_no_space_before_it() {
+    assert_eq!(
+        kinds("inbox#first\noutbox#"),
+        vec![TokenKind::InBox, TokenKind::OutBox]
+    );
+}
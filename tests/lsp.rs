@@ -0,0 +1,246 @@
+#![cfg(feature = "lsp")]
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+const URI: &str = "file:///tmp/solution.hrm";
+
+const SOURCE: &str = "jump_target main
+    inbox
+    copyto 0
+    jump_if_zero main # zero
+    fetch 0
+    jump missing
+    jump main
+";
+
+struct Client {
+    server: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    id: u64,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut server = Command::new(env!("CARGO_BIN_EXE_hrm-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = server.stdin.take().unwrap();
+        let stdout = BufReader::new(server.stdout.take().unwrap());
+        Self {
+            server,
+            stdin,
+            stdout,
+            id: 0,
+        }
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            self.stdout.read_line(&mut header).unwrap();
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length:") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.id += 1;
+        let id = self.id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        let response = self.receive();
+        assert_eq!(response["id"], json!(id));
+        response
+    }
+
+    fn at(&mut self, method: &str, line: usize, character: usize, extra: Value) -> Value {
+        let mut params = json!({
+            "textDocument": { "uri": URI },
+            "position": { "line": line, "character": character },
+        });
+        for (key, value) in extra.as_object().unwrap() {
+            params[key] = value.clone();
+        }
+        self.request(method, params)["result"].clone()
+    }
+}
+
+fn lines(locations: &Value) -> Vec<u64> {
+    let mut lines: Vec<u64> = locations
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|location| location["range"]["start"]["line"].as_u64().unwrap())
+        .collect();
+    lines.sort_unstable();
+    lines
+}
+
+#[test]
+fn scripted_session() {
+    let mut client = Client::start();
+    let initialize = client.request("initialize", json!({ "capabilities": {} }));
+    let capabilities = &initialize["result"]["capabilities"];
+    for provider in [
+        "definitionProvider",
+        "referencesProvider",
+        "renameProvider",
+        "hoverProvider",
+        "completionProvider",
+    ]
+    .iter()
+    {
+        assert!(!capabilities[provider].is_null(), "{}", provider);
+    }
+    client.notify("initialized", json!({}));
+
+    client.notify(
+        "textDocument/didOpen",
+        json!({ "textDocument": { "uri": URI, "languageId": "hrm", "version": 1, "text": SOURCE } }),
+    );
+    let published = client.receive();
+    assert_eq!(published["method"], "textDocument/publishDiagnostics");
+    let messages: Vec<(u64, &str)> = published["params"]["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| {
+            (
+                d["range"]["start"]["line"].as_u64().unwrap(),
+                d["message"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert!(messages.contains(&(
+        4,
+        "unknown instruction `fetch`, `0` after it is skipped too"
+    )));
+    assert!(messages.contains(&(5, "label `missing` is not defined")));
+
+    // `jump main` on line 6 goes to `jump_target main` on line 0.
+    let definition = client.at("textDocument/definition", 6, 10, json!({}));
+    assert_eq!(
+        definition["range"]["start"],
+        json!({ "line": 0, "character": 12 })
+    );
+    assert_eq!(definition["uri"], URI);
+
+    let references = client.at(
+        "textDocument/references",
+        3,
+        18,
+        json!({ "context": { "includeDeclaration": true } }),
+    );
+    assert_eq!(lines(&references), [0, 3, 6]);
+    let references = client.at(
+        "textDocument/references",
+        3,
+        18,
+        json!({ "context": { "includeDeclaration": false } }),
+    );
+    assert_eq!(lines(&references), [3, 6]);
+
+    let rename = client.at("textDocument/rename", 0, 13, json!({ "newName": "top" }));
+    let edits = &rename["changes"][URI];
+    assert_eq!(lines(edits), [0, 3, 6]);
+    assert!(edits
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|edit| edit["newText"] == "top"));
+    let invalid = client.request(
+        "textDocument/rename",
+        json!({
+            "textDocument": { "uri": URI },
+            "position": { "line": 0, "character": 13 },
+            "newName": "two words",
+        }),
+    );
+    assert!(invalid["error"]["code"].is_i64());
+
+    let hover = client.at("textDocument/hover", 2, 5, json!({}));
+    let text = hover["contents"]["value"].as_str().unwrap();
+    assert!(text.contains("floor tile"), "{}", text);
+
+    let completion = client.at("textDocument/completion", 6, 9, json!({}));
+    let labels: Vec<&str> = completion
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap())
+        .collect();
+    assert_eq!(labels, ["main"]);
+    let completion = client.at("textDocument/completion", 7, 0, json!({}));
+    assert_eq!(completion.as_array().unwrap().len(), 12);
+
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": URI, "version": 2 },
+            "contentChanges": [{ "text": "inbox\noutbox\n" }],
+        }),
+    );
+    let published = client.receive();
+    assert_eq!(published["params"]["diagnostics"], json!([]));
+
+    assert_eq!(
+        client.request("shutdown", Value::Null)["result"],
+        Value::Null
+    );
+    client.notify("exit", Value::Null);
+    assert!(client.server.wait().unwrap().success());
+}
+
+#[test]
+fn non_ascii_words() {
+    let mut client = Client::start();
+    client.request("initialize", json!({ "capabilities": {} }));
+    client.notify("initialized", json!({}));
+
+    client.notify(
+        "textDocument/didOpen",
+        json!({ "textDocument": { "uri": URI, "languageId": "hrm", "version": 1, "text": "inbox\nラ x\noutbox\n" } }),
+    );
+    let published = client.receive();
+    let diagnostics = published["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(
+        diagnostics[0]["range"]["start"],
+        json!({ "line": 1, "character": 0 })
+    );
+    assert!(diagnostics[0]["message"].as_str().unwrap().contains('ラ'));
+
+    let hover = client.at("textDocument/hover", 2, 2, json!({}));
+    let text = hover["contents"]["value"].as_str().unwrap();
+    assert!(text.contains("outbox"), "{}", text);
+
+    assert_eq!(
+        client.request("shutdown", Value::Null)["result"],
+        Value::Null
+    );
+    client.notify("exit", Value::Null);
+    assert!(client.server.wait().unwrap().success());
+}
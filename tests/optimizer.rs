@@ -0,0 +1,153 @@
+use hrm::interpreter::SimpleInterpreter;
+use hrm::lexer::{Lexer, Program};
+use hrm::optimizer::optimize;
+use std::fs;
+
+const SAMPLES: [&str; 8] = ["01", "02", "03", "04", "06", "09", "12", "19"];
+
+/// Instructions of the optimised `source`, and the rules applied with their locations.
+fn optimized(source: &str) -> (Vec<String>, Vec<String>) {
+    let (program, rewrites) = optimize(&Lexer::lex(source));
+    (
+        program
+            .iter()
+            .map(|token| token.value.to_string())
+            .collect(),
+        rewrites
+            .iter()
+            .map(|rewrite| {
+                format!(
+                    "{} {}:{}",
+                    rewrite.value, rewrite.location.line, rewrite.location.col
+                )
+            })
+            .collect(),
+    )
+}
+
+#[test]
+fn redundant_copy_from() {
+    assert_eq!(
+        optimized("inbox\ncopyto 0\ncopyfrom 0\noutbox\n"),
+        (
+            vec!["inbox".into(), "copyto 0".into(), "outbox".into()],
+            vec!["redundant-copyfrom 3:10".into()]
+        )
+    );
+    let (program, rewrites) = optimized("inbox\ncopyto 0\ncopyfrom 1\noutbox\n");
+    assert_eq!(program.len(), 4);
+    assert!(rewrites.is_empty());
+}
+
+#[test]
+fn jump_to_next() {
+    assert_eq!(
+        optimized("inbox\njump next\njump_target next\noutbox\n"),
+        (
+            vec!["inbox".into(), "outbox".into()],
+            vec!["jump-to-next 2:6".into(), "unused-label 3:13".into()]
+        )
+    );
+}
+
+#[test]
+fn jump_chains() {
+    let source = "jump_target top
+    inbox
+    jump_if_zero skip
+    outbox
+    jump top
+jump_target skip
+    jump top
+";
+    assert_eq!(
+        optimized(source),
+        (
+            vec![
+                "jump_target top".into(),
+                "inbox".into(),
+                "jump_if_zero top".into(),
+                "outbox".into(),
+                "jump top".into()
+            ],
+            vec![
+                "jump-chain 3:18".into(),
+                "unreachable 7:10".into(),
+                "unused-label 6:13".into()
+            ]
+        )
+    );
+}
+
+#[test]
+fn jump_cycles_are_kept() {
+    let source = "inbox\njump_if_zero a\noutbox\njump_target a\njump b\njump_target b\njump a\n";
+    assert_eq!(
+        optimized(source),
+        (
+            vec![
+                "inbox".into(),
+                "jump_if_zero a".into(),
+                "outbox".into(),
+                "jump_target a".into(),
+                "jump a".into()
+            ],
+            vec!["jump-to-next 5:6".into(), "unused-label 6:13".into()]
+        )
+    );
+}
+
+#[test]
+fn unreachable_code_and_unused_labels() {
+    assert_eq!(
+        optimized("inbox\noutbox\njump end\ncopyfrom 0\noutbox\njump_target end\n"),
+        (
+            vec!["inbox".into(), "outbox".into()],
+            vec![
+                "unreachable 4:10".into(),
+                "unreachable 5:1".into(),
+                "jump-to-next 3:6".into(),
+                "unused-label 6:13".into()
+            ]
+        )
+    );
+    assert_eq!(
+        optimized("jump_target unused\ninbox\noutbox\n"),
+        (
+            vec!["inbox".into(), "outbox".into()],
+            vec!["unused-label 1:13".into()]
+        )
+    );
+}
+
+#[test]
+fn unresolved_jumps_are_left_alone() {
+    let source = "inbox\ncopyto 0\ncopyfrom 0\njump_target unused\njump nowhere\n";
+    let program = Lexer::lex(source);
+    let (optimized, rewrites) = optimize(&program);
+    assert_eq!(optimized, program);
+    assert!(rewrites.is_empty());
+}
+
+fn run(program: &Program, input: &str) -> (Vec<i16>, Option<String>) {
+    let mut interpreter = SimpleInterpreter::new();
+    interpreter.set_print_outbox(false);
+    interpreter.set_inbox(input.to_string());
+    let error = interpreter
+        .eval(program)
+        .err()
+        .map(|e| format!("{:?}", e.value));
+    (interpreter.report().outbox, error)
+}
+
+#[test]
+fn samples_behave_the_same_after_optimisation() {
+    let input = fs::read_to_string("sample_program/input.txt").unwrap();
+    for name in SAMPLES.iter() {
+        let source = fs::read_to_string(format!("sample_program/{}.hrm", name)).unwrap();
+        let program = Lexer::lex(&source);
+        let (optimized, _) = optimize(&program);
+        assert_eq!(run(&optimized, &input), run(&program, &input), "{}", name);
+        assert!(optimized.len() <= program.len(), "{}", name);
+    }
+}
@@ -0,0 +1,81 @@
+use hrm::lexer::{Lexer, Program};
+use hrm::profile;
+use hrm::testcase::{self, TestCase};
+use std::fs;
+
+fn sample() -> (Program, Vec<TestCase>) {
+    let source = fs::read_to_string("sample_program/19.hrm").unwrap();
+    let cases = fs::read_to_string("sample_program/tests/19.hrmtest").unwrap();
+    (Lexer::lex(&source), testcase::parse(&cases, "19").unwrap())
+}
+
+fn case(inbox: &[i16]) -> TestCase {
+    TestCase {
+        inbox: inbox.to_vec(),
+        ..TestCase::default()
+    }
+}
+
+#[test]
+fn steps_of_each_instruction_and_loop() {
+    let (program, cases) = sample();
+    let profile = profile::profile(&program, &cases[..1]).unwrap();
+    // `inbox` runs twice but the second one, finding the inbox empty, takes no step.
+    assert_eq!(profile.steps, [0, 1, 1, 0, 4, 4, 4, 3, 3, 3, 0, 0, 0]);
+    assert_eq!(profile.total(), 23);
+    let loops: Vec<(usize, usize)> = profile
+        .loops
+        .iter()
+        .map(|natural| (natural.header, profile.loop_steps(natural)))
+        .collect();
+    assert_eq!(loops, [(0, 23), (3, 21)]);
+}
+
+#[test]
+fn steps_match_the_runs_of_the_cases() {
+    let (program, cases) = sample();
+    let profile = profile::profile(&program, &cases).unwrap();
+    let steps: usize = cases
+        .iter()
+        .map(|case| testcase::run(&program, case).report.steps)
+        .sum();
+    assert_eq!(profile.runs, 4);
+    assert_eq!(profile.total(), steps);
+    assert_eq!(
+        profile.report(&program, 2),
+        "21.0 steps per run over 4 runs
+
+hottest instructions:
+  share  steps/run  location  instruction
+  17.9%        3.8  6:1       outbox
+  17.9%        3.8  7:10      copyfrom 0
+
+hottest loops (nested loops count in the loops around them):
+  share  steps/run  lines     header
+ 100.0%       21.0  1-14      jump_target input
+  85.7%       18.0  5-14      jump_target main
+"
+    );
+}
+
+#[test]
+fn failing_and_finished_runs_count_their_steps() {
+    // the failing `outbox` takes no step.
+    let program = Lexer::lex("inbox\noutbox\noutbox\n");
+    let profile = profile::profile(&program, &[case(&[1])]).unwrap();
+    assert_eq!(profile.steps, [1, 1, 0]);
+    assert!(profile.loops.is_empty());
+
+    // a run falling off the end of the program counts every instruction.
+    let program = Lexer::lex("inbox\noutbox\n");
+    let profile = profile::profile(&program, &[case(&[1]), case(&[2, 3])]).unwrap();
+    assert_eq!(profile.steps, [2, 2]);
+    assert_eq!(profile.total(), 4);
+}
+
+#[test]
+fn endless_runs_stop_at_the_step_limit() {
+    let program = Lexer::lex("inbox\njump_target a\ncopyto 0\njump a\n");
+    let profile = profile::profile(&program, &[case(&[1])]).unwrap();
+    assert_eq!(profile.total(), testcase::STEP_LIMIT);
+}
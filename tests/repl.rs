@@ -0,0 +1,131 @@
+use hrm::repl::{run, Session};
+
+/// Output of `session` for each of `lines`.
+fn handle(session: &mut Session, lines: &[&str]) -> Vec<String> {
+    lines
+        .iter()
+        .map(|line| {
+            let mut out = Vec::new();
+            assert!(session.handle(line, &mut out).unwrap());
+            String::from_utf8(out).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn instructions_run_as_they_are_entered() {
+    let mut session = Session::new();
+    assert_eq!(
+        handle(
+            &mut session,
+            &[
+                ":inbox 3 -2",
+                "inbox copyto 0",
+                "add 0 outbox",
+                ":hand",
+                ":floor",
+                ":outbox",
+                ":inbox",
+            ]
+        ),
+        [
+            "",
+            "",
+            "outbox: 6\n",
+            "hand: empty\n",
+            "0: 3\n1: empty\n2: empty\n3: empty\n4: empty\n5: empty\n",
+            "outbox: 6\n",
+            "inbox: -2\n",
+        ]
+    );
+    assert_eq!(
+        handle(&mut session, &[":program"]),
+        ["inbox\ncopyto 0\nadd 0\noutbox\n"]
+    );
+}
+
+#[test]
+fn jumps_repeat_earlier_lines() {
+    let mut session = Session::new();
+    assert_eq!(
+        handle(
+            &mut session,
+            &[":inbox 1 2", "jump_target top", "inbox outbox", "jump top"]
+        ),
+        [
+            "",
+            "",
+            "outbox: 1\n",
+            "outbox: 2\nprogram ended: the inbox is empty\n"
+        ]
+    );
+}
+
+#[test]
+fn undo_reverts_instructions_and_commands() {
+    let mut session = Session::new();
+    assert_eq!(
+        handle(
+            &mut session,
+            &[
+                ":inbox 4 5",
+                "inbox",
+                "copyto 1",
+                ":undo",
+                ":floor",
+                ":hand",
+                ":undo",
+                ":hand",
+                ":inbox",
+                ":undo",
+                ":inbox",
+                ":undo",
+            ]
+        ),
+        [
+            "",
+            "",
+            "",
+            "",
+            "0: empty\n1: empty\n2: empty\n3: empty\n4: empty\n5: empty\n",
+            "hand: 4\n",
+            "",
+            "hand: empty\n",
+            "inbox: 4 5\n",
+            "",
+            "inbox: not set\n",
+            "nothing to undo\n",
+        ]
+    );
+    assert_eq!(handle(&mut session, &[":program"]), [""]);
+}
+
+#[test]
+fn failing_lines_are_discarded() {
+    let mut session = Session::new();
+    assert_eq!(
+        handle(
+            &mut session,
+            &["outbox", "fetch 0", ":inbox x", ":bogus", ":program"]
+        ),
+        [
+            "error: EmptyHandValue, the line is discarded\n",
+            "error: not an instruction: fetch 0\n",
+            "error: inbox values must be numbers\n",
+            "unknown command :bogus, see :help\n",
+            "",
+        ]
+    );
+    assert!(!Session::new().handle(":quit", &mut Vec::new()).unwrap());
+}
+
+#[test]
+fn run_prompts_for_each_line() {
+    let mut out = Vec::new();
+    run(
+        ":inbox 7\ninbox outbox\n:quit\n:hand\n".as_bytes(),
+        &mut out,
+    )
+    .unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "> > outbox: 7\n> ");
+}
@@ -0,0 +1,76 @@
+#![cfg(feature = "serde")]
+
+use hrm::interpreter::{InterpreterError, InterpreterErrorKind, RunReport, SimpleInterpreter};
+use hrm::lexer::{Lexer, Location, Program};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+
+const SOURCE: &str = "jump_target main
+    inbox
+    copyto 0
+    jump_if_zero main
+    jump_if_neg main
+    bump_minus 0
+    add 0
+    sub 0
+    copyfrom 0
+    bump_plus 0
+    outbox
+    jump main
+";
+
+fn round_trip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T) {
+    let json = serde_json::to_string(value).unwrap();
+    let back: T = serde_json::from_str(&json).unwrap();
+    assert_eq!(&back, value, "{}", json);
+}
+
+#[test]
+fn program_round_trips() {
+    let program: Program = Lexer::lex(SOURCE);
+    assert_eq!(program.len(), 12);
+    round_trip(&program);
+}
+
+#[test]
+fn errors_round_trip() {
+    let location = Location { line: 3, col: 5 };
+    for kind in [
+        InterpreterErrorKind::UnexistedJumpTarget,
+        InterpreterErrorKind::EmptyInBox,
+        InterpreterErrorKind::WrongOutput {
+            index: 2,
+            expected: None,
+            actual: -7,
+        },
+        InterpreterErrorKind::NotEnoughOutput {
+            expected: 4,
+            actual: 1,
+        },
+        InterpreterErrorKind::Overflow,
+    ] {
+        round_trip(&InterpreterError::new(kind, location));
+    }
+}
+
+#[test]
+fn snapshot_and_report_round_trip() {
+    let program = Lexer::lex(SOURCE);
+    let mut interpreter = SimpleInterpreter::new();
+    interpreter.set_print_outbox(false);
+    interpreter.set_inbox_values(&[3, -2, 0, 5]);
+    interpreter.load(&program).unwrap();
+    for _ in 0..14 {
+        assert!(interpreter.step().unwrap());
+    }
+    let snapshot = interpreter.snapshot();
+    assert_eq!(snapshot.inbox, Some(vec![0, 5]));
+    assert_eq!(snapshot.outbox, vec![3]);
+    round_trip(&snapshot);
+
+    while interpreter.step().unwrap() {}
+    let report: RunReport = interpreter.report();
+    assert_eq!(report.outbox, vec![3, 5]);
+    round_trip(&report);
+}
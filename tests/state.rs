@@ -0,0 +1,118 @@
+use hrm::interpreter::{SimpleInterpreter, Snapshot};
+use hrm::lexer::{Lexer, Location, Program};
+use hrm::state::{parse, write, StateFileErrorKind};
+use std::fs;
+
+fn program() -> Program {
+    Lexer::lex(&fs::read_to_string("sample_program/19.hrm").unwrap())
+}
+
+fn interpreter() -> SimpleInterpreter {
+    let mut interpreter = SimpleInterpreter::new();
+    interpreter.set_print_outbox(false);
+    interpreter
+}
+
+#[test]
+fn paused_runs_resume_where_they_stopped() {
+    let program = program();
+    let inbox = [3, -2, 0, 5];
+    let mut uninterrupted = interpreter();
+    uninterrupted.set_inbox_values(&inbox);
+    uninterrupted.eval(&program).unwrap();
+    let expected = uninterrupted.report();
+
+    for pause in 0..=expected.steps {
+        let mut first = interpreter();
+        first.set_inbox_values(&inbox);
+        first.load(&program).unwrap();
+        while first.steps() < pause && first.step().unwrap() {}
+        let text = write(&first.snapshot());
+        let snapshot = parse(&text).unwrap();
+        assert_eq!(snapshot, first.snapshot(), "{}", text);
+
+        let mut second = interpreter();
+        second.restore(&snapshot);
+        second.load(&program).unwrap();
+        while second.step().unwrap() {}
+        assert_eq!(second.outbox(), expected.outbox.as_slice(), "{}", text);
+        assert_eq!(second.steps(), expected.steps, "{}", text);
+    }
+}
+
+#[test]
+fn write_and_parse_the_state_file_format() {
+    let snapshot = Snapshot {
+        hand: Some(-4),
+        cells: vec![Some(4), None, None, Some(-1), None, None],
+        inbox: Some(vec![2, -5]),
+        outbox: vec![1, 8],
+        cursor: 7,
+        steps: 12,
+    };
+    let text = "cursor: 7\nsteps: 12\nhand: -4\nfloor: 0=4 3=-1\ninbox: 2 -5\noutbox: 1 8\n";
+    assert_eq!(write(&snapshot), text);
+    assert_eq!(parse(text).unwrap(), snapshot);
+
+    // an empty hand and no inbox are left out.
+    let snapshot = parse("# paused\nsteps: 0\ncursor: 0 # first\n").unwrap();
+    assert_eq!(snapshot.hand, None);
+    assert_eq!(snapshot.inbox, None);
+    assert_eq!(snapshot.cells, [None; 6]);
+    assert_eq!(write(&snapshot), "cursor: 0\nsteps: 0\nfloor: \noutbox: \n");
+}
+
+#[test]
+fn malformed_state_files_are_reported() {
+    let at = |line: usize| Location { line, col: 1 };
+    for (source, kind, location) in [
+        (
+            "steps: 3\n",
+            StateFileErrorKind::MissingKey("cursor".to_string()),
+            at(1),
+        ),
+        (
+            "cursor: 1\n",
+            StateFileErrorKind::MissingKey("steps".to_string()),
+            at(1),
+        ),
+        (
+            "cursor: 1\nsteps: 2\ncursor: 3\n",
+            StateFileErrorKind::DuplicateKey("cursor".to_string()),
+            at(3),
+        ),
+        (
+            "cursor: 1\nsteps: 2\nfloor: 0=1 6=2\n",
+            StateFileErrorKind::InvalidTile("6=2".to_string()),
+            at(3),
+        ),
+        (
+            "cursor: 1\nsteps: 2\nfloor: 0-1\n",
+            StateFileErrorKind::InvalidTile("0-1".to_string()),
+            at(3),
+        ),
+        (
+            "cursor: 1\nsteps: 2 3\n",
+            StateFileErrorKind::InvalidNumber("2 3".to_string()),
+            at(2),
+        ),
+        (
+            "cursor: x\n",
+            StateFileErrorKind::InvalidNumber("x".to_string()),
+            at(1),
+        ),
+        (
+            "cursor: 1\nsteps: 2\nspeed: 3\n",
+            StateFileErrorKind::UnknownKey("speed".to_string()),
+            at(3),
+        ),
+    ] {
+        let error = parse(source).unwrap_err();
+        assert_eq!(
+            (error.value, error.location),
+            (kind, location),
+            "{}",
+            source
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use hrm::equiv::compare;
+use hrm::interpreter::InterpreterErrorKind;
+use hrm::lexer::Lexer;
+use hrm::printer;
+use hrm::superopt::{superoptimize, Budget, Level};
+use std::time::Duration;
+
+fn level(instructions: &[&'static str]) -> Level {
+    Level {
+        instructions: instructions.to_vec(),
+        ..Level::default()
+    }
+}
+
+fn budget(max_size: usize, time: Duration) -> Budget {
+    Budget {
+        max_size,
+        time,
+        ..Budget::default()
+    }
+}
+
+#[test]
+fn finds_a_shorter_equivalent_program() {
+    let reference = Lexer::lex(
+        "jump_target a\n    inbox\n    copyto 0\n    copyfrom 0\n    copyto 1\n    outbox\n    jump a\n",
+    );
+    let search = superoptimize(
+        &reference,
+        &level(&["inbox", "outbox", "copyfrom", "copyto", "jump"]),
+        &budget(3, Duration::from_secs(30)),
+    )
+    .unwrap();
+    assert!(!search.timed_out);
+    let program = search.program.unwrap();
+    assert_eq!(
+        printer::print(&program),
+        "jump_target a\n    inbox\n    outbox\n    jump a\n"
+    );
+    let equivalence = compare(&reference, &program, &[], 1000).unwrap();
+    assert_eq!(equivalence.difference, None);
+}
+
+#[test]
+fn stops_when_the_time_runs_out() {
+    let reference = Lexer::lex("jump_target a\ninbox\noutbox\njump a\n");
+    let search = superoptimize(&reference, &Level::default(), &budget(6, Duration::ZERO)).unwrap();
+    assert!(search.timed_out);
+    assert_eq!(search.program, None);
+}
+
+#[test]
+fn reference_failing_on_every_inbox_is_an_error() {
+    let error = superoptimize(
+        &Lexer::lex("outbox\n"),
+        &Level::default(),
+        &Budget::default(),
+    )
+    .unwrap_err();
+    assert_eq!(error.value, InterpreterErrorKind::EmptyHandValue);
+}
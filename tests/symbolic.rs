@@ -0,0 +1,160 @@
+use hrm::interpreter::InterpreterErrorKind;
+use hrm::lexer::Lexer;
+use hrm::symbolic::{explore, End, Exploration, Limits, Path};
+
+fn explored(source: &str, floor: &[(usize, i16)]) -> Exploration {
+    let limits = Limits {
+        depth: 20,
+        ..Limits::default()
+    };
+    explore(&Lexer::lex(source), floor, &limits).unwrap()
+}
+
+/// The end, witness, outbox and constraints of `path`, as `hrm symbolic -v` prints them.
+fn describe(path: &Path) -> String {
+    let end = match &path.end {
+        End::Finished => "finished".to_string(),
+        End::Error(e) => format!("{:?} at {}:{}", e.value, e.location.line, e.location.col),
+        End::DepthExceeded => "cut".to_string(),
+        End::Abandoned => "abandoned".to_string(),
+    };
+    let outbox: Vec<String> = path.outbox.iter().map(|value| value.to_string()).collect();
+    let constraints: Vec<String> = path.constraints.iter().map(|c| c.to_string()).collect();
+    format!(
+        "{}, inbox {:?}, outbox [{}] when [{}]",
+        end,
+        path.witness.as_ref().unwrap(),
+        outbox.join(", "),
+        constraints.join(", ")
+    )
+}
+
+fn describe_all(exploration: &Exploration) -> Vec<String> {
+    for path in exploration.paths.iter() {
+        let witness = path.witness.as_ref().unwrap();
+        assert!(
+            path.constraints.iter().all(|c| c.holds(witness)),
+            "{}",
+            describe(path)
+        );
+    }
+    exploration.paths.iter().map(describe).collect()
+}
+
+#[test]
+fn branches_fork_with_their_conditions() {
+    let source = "inbox\njump_if_neg negative\njump_if_zero zero\noutbox\njump_target zero\njump_target negative\n";
+    let exploration = explored(source, &[]);
+    assert_eq!(
+        describe_all(&exploration),
+        [
+            "finished, inbox [], outbox [] when []",
+            "finished, inbox [-1], outbox [] when [x0 < 0]",
+            "finished, inbox [0], outbox [] when [x0 >= 0, x0 == 0]",
+            "finished, inbox [1], outbox [x0] when [x0 >= 0, x0 != 0]",
+        ]
+    );
+    assert_eq!(exploration.paths[3].branches, [(1, false), (2, false)]);
+    assert!(exploration.errors().is_empty());
+}
+
+#[test]
+fn overflow_forks_both_ways() {
+    let exploration = explored("inbox\ncopyto 0\nadd 0\noutbox\n", &[]);
+    assert_eq!(
+        describe_all(&exploration),
+        [
+            "finished, inbox [], outbox [] when []",
+            "Overflow at 3:5, inbox [500], outbox [] when [2*x0 - 1000 >= 0]",
+            "Overflow at 3:5, inbox [-500], outbox [] when [2*x0 + 999 < 0]",
+            "finished, inbox [0], outbox [2*x0] when [2*x0 + 999 >= 0, 2*x0 - 1000 < 0]",
+        ]
+    );
+    let errors: Vec<String> = exploration.errors().into_iter().map(describe).collect();
+    assert_eq!(
+        errors,
+        ["Overflow at 3:5, inbox [500], outbox [] when [2*x0 - 1000 >= 0]"]
+    );
+}
+
+#[test]
+fn solver_finds_inboxes_for_several_symbols() {
+    let source = "inbox
+copyto 0
+inbox
+sub 0
+sub 0
+add 1
+jump_if_zero found
+jump_target end
+jump end
+jump_target found
+outbox
+";
+    let exploration = explored(source, &[(1, -7)]);
+    let paths = describe_all(&exploration);
+    assert_eq!(paths.len(), 9);
+    assert_eq!(
+        paths[6],
+        "Overflow at 6:5, inbox [0, -993], outbox [] when [-x0 + x1 + 999 >= 0, -x0 + x1 - 1000 < 0, \
+         -2*x0 + x1 + 999 >= 0, -2*x0 + x1 - 1000 < 0, -2*x0 + x1 + 992 < 0]"
+    );
+    assert_eq!(
+        paths[7],
+        "finished, inbox [0, 7], outbox [-2*x0 + x1 - 7] when [-x0 + x1 + 999 >= 0, \
+         -x0 + x1 - 1000 < 0, -2*x0 + x1 + 999 >= 0, -2*x0 + x1 - 1000 < 0, \
+         -2*x0 + x1 + 992 >= 0, -2*x0 + x1 - 1007 < 0, -2*x0 + x1 - 7 == 0]"
+    );
+    assert_eq!(exploration.paths[8].end, End::DepthExceeded);
+    assert!(exploration.covered.iter().all(|&covered| covered));
+}
+
+#[test]
+fn infeasible_branches_are_not_followed() {
+    // the second test of the same value can only fall through.
+    let source = "inbox\njump_if_neg a\njump_if_neg b\noutbox\njump_target b\njump_target a\n";
+    let exploration = explored(source, &[]);
+    assert_eq!(exploration.paths.len(), 3);
+    assert_eq!(exploration.jumped, [false, true, false, false]);
+    assert_eq!(exploration.fell_through, [false, true, true, false]);
+    assert!(exploration.covered.iter().all(|&covered| covered));
+
+    // after adding a constant, the hand is zero for one inbox value only.
+    let exploration = explored(
+        "inbox\nadd 0\njump_if_zero a\noutbox\njump_target a\n",
+        &[(0, 3)],
+    );
+    assert_eq!(
+        describe_all(&exploration)[1..],
+        [
+            "Overflow at 2:5, inbox [997], outbox [] when [x0 - 997 >= 0]",
+            "finished, inbox [-3], outbox [] when [x0 - 997 < 0, x0 + 3 == 0]",
+            "finished, inbox [0], outbox [x0 + 3] when [x0 - 997 < 0, x0 + 3 != 0]",
+        ]
+    );
+}
+
+#[test]
+fn errors_and_limits() {
+    let exploration = explored(
+        "inbox\njump_if_zero a\noutbox\njump_target a\noutbox\n",
+        &[],
+    );
+    let errors: Vec<String> = exploration.errors().into_iter().map(describe).collect();
+    assert_eq!(
+        errors,
+        ["EmptyHandValue at 5:1, inbox [-1], outbox [x0] when [x0 != 0]"]
+    );
+
+    let limits = Limits {
+        depth: 100,
+        paths: 2,
+    };
+    let program = Lexer::lex("jump_target a\ninbox\njump_if_zero a\noutbox\njump a\n");
+    let exploration = explore(&program, &[], &limits).unwrap();
+    assert!(exploration.truncated);
+    assert_eq!(exploration.paths.len(), 2);
+
+    let error = explore(&Lexer::lex("jump nowhere\n"), &[], &limits).unwrap_err();
+    assert_eq!(error.value, InterpreterErrorKind::UnexistedJumpTarget);
+}
@@ -0,0 +1,81 @@
+use hrm::bytecode::Op;
+use hrm::interpreter::SimpleInterpreter;
+use hrm::lexer::Program;
+use hrm::printer;
+use hrm::superopt::{Budget, Example, Level};
+use hrm::synth::{loop_sketches, synthesize};
+use hrm::testcase;
+use std::fs;
+use std::time::Duration;
+
+fn level(instructions: &[&'static str]) -> Level {
+    Level {
+        instructions: instructions.to_vec(),
+        ..Level::default()
+    }
+}
+
+fn budget(max_size: usize) -> Budget {
+    Budget {
+        max_size,
+        time: Duration::from_secs(30),
+        ..Budget::default()
+    }
+}
+
+fn outbox(program: &Program, inbox: &[i16]) -> Vec<i16> {
+    let mut interpreter = SimpleInterpreter::new();
+    interpreter.set_print_outbox(false);
+    interpreter.set_inbox_values(inbox);
+    interpreter.eval(program).unwrap();
+    interpreter.outbox().to_vec()
+}
+
+#[test]
+fn loop_sketches_read_the_inbox_after_the_setup() {
+    assert_eq!(
+        loop_sketches(3),
+        [
+            vec![Some(Op::InBox), None, Some(Op::Jump(0))],
+            vec![None, Some(Op::InBox), Some(Op::Jump(1))],
+        ]
+    );
+    assert!(loop_sketches(1).is_empty());
+}
+
+#[test]
+fn synthesizes_an_echo() {
+    let examples: Vec<Example> = vec![(vec![1, -2, 3], vec![1, -2, 3]), (vec![], vec![])];
+    let search = synthesize(
+        &examples,
+        &level(&["inbox", "outbox", "copyto", "add", "jump"]),
+        &budget(4),
+    );
+    assert!(!search.timed_out);
+    let program = search.program.unwrap();
+    assert_eq!(
+        printer::print(&program),
+        "jump_target a\n    inbox\n    outbox\n    jump a\n"
+    );
+    assert_eq!(outbox(&program, &[5, 0, -7]), [5, 0, -7]);
+}
+
+#[test]
+fn synthesizes_the_doubling_sample() {
+    let source = fs::read_to_string("sample_program/synth/double.hrmtest").unwrap();
+    let examples: Vec<Example> = testcase::parse(&source, "double")
+        .unwrap()
+        .into_iter()
+        .map(|case| (case.inbox, case.outbox))
+        .collect();
+    let search = synthesize(
+        &examples,
+        &level(&["inbox", "outbox", "copyto", "add", "jump"]),
+        &budget(5),
+    );
+    let program = search.program.unwrap();
+    for (inbox, expected) in examples.iter() {
+        assert_eq!(outbox(&program, inbox), *expected);
+    }
+    assert_eq!(outbox(&program, &[11, -1]), [22, -2]);
+}
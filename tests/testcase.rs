@@ -0,0 +1,155 @@
+use hrm::interpreter::InterpreterErrorKind;
+use hrm::lexer::{Lexer, Location};
+use hrm::testcase::{diff, parse, run, Outcome, TestCase, TestFileErrorKind, STEP_LIMIT};
+use std::fs;
+
+fn case(inbox: &[i16], outbox: &[i16], max_steps: Option<usize>) -> TestCase {
+    TestCase {
+        name: "case".to_string(),
+        inbox: inbox.to_vec(),
+        floor: Vec::new(),
+        outbox: outbox.to_vec(),
+        max_steps,
+    }
+}
+
+#[test]
+fn parse_reads_named_and_unnamed_cases() {
+    let cases = parse(
+        "# doubles\ninbox: 1 -2\nfloor: 5=0 1=-3 # tiles\noutbox: 2 -4\nsteps: 12\n",
+        "double",
+    )
+    .unwrap();
+    assert_eq!(
+        cases,
+        [TestCase {
+            name: "double".to_string(),
+            inbox: vec![1, -2],
+            floor: vec![(5, 0), (1, -3)],
+            outbox: vec![2, -4],
+            max_steps: Some(12),
+        }]
+    );
+
+    let source = fs::read_to_string("sample_program/tests/19.hrmtest").unwrap();
+    let cases = parse(&source, "19").unwrap();
+    let names: Vec<&str> = cases.iter().map(|case| case.name.as_str()).collect();
+    assert_eq!(names, ["positive", "negative", "zero", "several"]);
+    assert_eq!(
+        cases[1],
+        TestCase {
+            name: "negative".to_string(),
+            ..case(&[-2], &[-2, -1, 0], None)
+        }
+    );
+    assert_eq!(cases[3].max_steps, Some(60));
+}
+
+#[test]
+fn parse_reports_malformed_files() {
+    let at = |line: usize| Location { line, col: 1 };
+    for (source, kind, location) in [
+        (
+            "inbox 1\n",
+            TestFileErrorKind::UnknownKey("inbox 1".to_string()),
+            at(1),
+        ),
+        (
+            "case a\noutbox: 1\nsteps: 3\nhand: 1\n",
+            TestFileErrorKind::UnknownKey("hand".to_string()),
+            at(4),
+        ),
+        (
+            "outbox: 1\n\noutbox: 2\n",
+            TestFileErrorKind::DuplicateKey("outbox".to_string()),
+            at(3),
+        ),
+        (
+            "inbox: 1 x\noutbox:\n",
+            TestFileErrorKind::InvalidNumber("x".to_string()),
+            at(1),
+        ),
+        (
+            "outbox:\nsteps: 1 2\n",
+            TestFileErrorKind::InvalidNumber("1 2".to_string()),
+            at(2),
+        ),
+        (
+            "floor: 6=1\noutbox:\n",
+            TestFileErrorKind::InvalidTile("6=1".to_string()),
+            at(1),
+        ),
+        (
+            "floor: 0:1\noutbox:\n",
+            TestFileErrorKind::InvalidTile("0:1".to_string()),
+            at(1),
+        ),
+        (
+            "case a\noutbox: 1\ncase b\ninbox: 1\n",
+            TestFileErrorKind::MissingOutbox("b".to_string()),
+            at(3),
+        ),
+    ] {
+        let error = parse(source, "default").unwrap_err();
+        assert_eq!(
+            (error.value, error.location),
+            (kind, location),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn run_compares_the_outbox() {
+    let program = Lexer::lex("jump_target a\ninbox\nadd 0\noutbox\njump a\n");
+    let mut doubled = case(&[1, -2], &[3, 0], None);
+    doubled.floor = vec![(0, 2)];
+    let result = run(&program, &doubled);
+    assert_eq!(result.outcome, Outcome::Passed);
+    assert_eq!(result.report.steps, 8);
+    assert_eq!(result.report.outbox, [3, 0]);
+
+    doubled.outbox = vec![3];
+    assert_eq!(run(&program, &doubled).outcome, Outcome::WrongOutbox);
+
+    doubled.floor = Vec::new();
+    match run(&program, &doubled).outcome {
+        Outcome::Error(e) => assert_eq!(e.value, InterpreterErrorKind::EmptyFloorValue),
+        outcome => panic!("{:?}", outcome),
+    }
+}
+
+#[test]
+fn step_budget_and_step_limit_are_told_apart() {
+    let program = Lexer::lex("jump_target a\ninbox\noutbox\njump a\n");
+    assert_eq!(
+        run(&program, &case(&[1, 2], &[1, 2], Some(6))).outcome,
+        Outcome::Passed
+    );
+    assert_eq!(
+        run(&program, &case(&[1, 2], &[1, 2], Some(5))).outcome,
+        Outcome::OverBudget
+    );
+
+    let endless = Lexer::lex("inbox\njump_target a\ncopyto 0\njump a\n");
+    let result = run(&endless, &case(&[1], &[], None));
+    assert_eq!(result.outcome, Outcome::StepLimit);
+    assert_eq!(result.report.steps, STEP_LIMIT + 1);
+    assert_eq!(
+        run(&endless, &case(&[1], &[], Some(10))).outcome,
+        Outcome::OverBudget
+    );
+}
+
+#[test]
+fn diff_marks_rows_which_differ() {
+    assert_eq!(
+        diff(&[1, 2, 3], &[1, 5]),
+        "  index  expected    actual
+      0         1         1
+!     1         2         5
+!     2         3         -
+"
+    );
+}
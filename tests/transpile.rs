@@ -0,0 +1,124 @@
+use hrm::interpreter::{InterpreterErrorKind, SimpleInterpreter};
+use hrm::lexer::Lexer;
+use hrm::transpile::{transpile, Target};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+const SAMPLES: [&str; 8] = ["01", "02", "03", "04", "06", "09", "12", "19"];
+
+/// Programs failing with `Overflow` and `FloorOutOfRange`, with their input and error.
+const FAILURES: [(&str, &str, &str, InterpreterErrorKind); 2] = [
+    (
+        "overflow",
+        "inbox\ncopyto 0\nadd 0\ncopyto 0\nadd 0\ncopyto 0\noutbox\ncopyfrom 0\nadd 0\n",
+        "200\n",
+        InterpreterErrorKind::Overflow,
+    ),
+    (
+        "floor_out_of_range",
+        "inbox\noutbox\ninbox\ncopyto 6\n",
+        "99\n3\n",
+        InterpreterErrorKind::FloorOutOfRange,
+    ),
+];
+
+/// Output of the interpreter in the shape the generated programs print it.
+fn expected(source: &str, input: &str) -> (String, String) {
+    let mut interpreter = SimpleInterpreter::new();
+    interpreter.set_print_outbox(false);
+    interpreter.set_inbox(input.to_string());
+    let result = interpreter.eval(&Lexer::lex(source));
+    let stdout: String = interpreter
+        .report()
+        .outbox
+        .iter()
+        .map(|value| format!("{}\n", value))
+        .collect();
+    let stderr = match result {
+        Ok(_) => String::new(),
+        Err(e) => format!(
+            "error: {:?} at {}:{}\n",
+            e.value, e.location.line, e.location.col
+        ),
+    };
+    (stdout, stderr)
+}
+
+fn run(binary: &Path, input: &str) -> (String, String) {
+    let mut child = Command::new(binary)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // programs failing before their first `inbox` exit without reading stdin.
+    let _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+    let output = child.wait_with_output().unwrap();
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+/// Compiles every sample with `compiler` and compares its output with `SimpleInterpreter`.
+/// Skipped when the compiler is not installed.
+fn compare(target: Target, extension: &str, compiler: &str, args: &[&str]) {
+    if Command::new(compiler).arg("--version").output().is_err() {
+        eprintln!("{} is not available, skipped", compiler);
+        return;
+    }
+    let dir: PathBuf = env::temp_dir().join(format!(
+        "hrm-transpile-{}-{}",
+        extension,
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    let input = fs::read_to_string("sample_program/input.txt").unwrap();
+    let mut programs: Vec<(&str, String, &str)> = SAMPLES
+        .iter()
+        .map(|sample| {
+            let path = format!("sample_program/{}.hrm", sample);
+            (*sample, fs::read_to_string(path).unwrap(), input.as_str())
+        })
+        .collect();
+    for (name, source, input, kind) in FAILURES.iter() {
+        let (_, stderr) = expected(source, input);
+        assert!(
+            stderr.starts_with(&format!("error: {:?} ", kind)),
+            "{}",
+            stderr
+        );
+        programs.push((name, source.to_string(), input));
+    }
+    for (sample, source, input) in programs.iter() {
+        let generated = transpile(&Lexer::lex(source), target).unwrap();
+        let source_path = dir.join(format!("{}.{}", sample, extension));
+        let binary = dir.join(sample);
+        fs::write(&source_path, generated).unwrap();
+        let status = Command::new(compiler)
+            .args(args)
+            .arg("-o")
+            .arg(&binary)
+            .arg(&source_path)
+            .status()
+            .unwrap();
+        assert!(status.success(), "{} failed to compile", sample);
+        assert_eq!(run(&binary, input), expected(source, input), "{}", sample);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn rust_matches_interpreter() {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    compare(Target::Rust, "rs", &rustc, &["--edition", "2018"]);
+}
+
+#[test]
+fn c_matches_interpreter() {
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    compare(Target::C, "c", &cc, &[]);
+}
@@ -0,0 +1,111 @@
+use hrm::interpreter::SimpleInterpreter;
+use hrm::lexer::Lexer;
+use hrm::tui::{Status, Visualiser};
+
+const SOURCE: &str = "jump_target top\n    inbox\n    copyto 4\n    outbox\n    jump top\n";
+
+fn visualiser(inbox: &[i16]) -> Visualiser {
+    let mut interpreter = SimpleInterpreter::new();
+    interpreter.set_inbox_values(inbox);
+    Visualiser::new(&Lexer::lex(SOURCE), interpreter).unwrap()
+}
+
+fn texts(visualiser: &Visualiser, height: usize) -> Vec<String> {
+    visualiser
+        .frame(height)
+        .into_iter()
+        .map(|(text, _)| text)
+        .collect()
+}
+
+#[test]
+fn frame_shows_conveyors_hand_floor_and_program() {
+    let mut visualiser = visualiser(&[7, -30]);
+    for _ in 0..3 {
+        visualiser.step();
+    }
+    let frame = visualiser.frame(40);
+    let highlighted: Vec<&str> = frame
+        .iter()
+        .filter(|(_, highlighted)| *highlighted)
+        .map(|(text, _)| text.as_str())
+        .collect();
+    assert_eq!(highlighted, [">   4     outbox"]);
+    assert_eq!(
+        texts(&visualiser, 40),
+        [
+            "step 2  speed 4/s  paused",
+            "",
+            "inbox  < [ -30]",
+            "outbox > ",
+            "",
+            "hand   [  7   ]",
+            "",
+            "+------+------+------+",
+            "|0     |1     |2     |",
+            "|      |      |      |",
+            "+------+------+------+",
+            "|3     |4     |5     |",
+            "|      |  7   |      |",
+            "+------+------+------+",
+            "",
+            "    1 jump_target top",
+            "    2     inbox",
+            "    3     copyto 4",
+            ">   4     outbox",
+            "    5     jump top",
+            "",
+            "space play/pause  s step  +/- speed  r restart  q quit",
+        ]
+    );
+}
+
+#[test]
+fn program_scrolls_to_the_current_instruction() {
+    let mut visualiser = visualiser(&[7, -30]);
+    for _ in 0..3 {
+        visualiser.step();
+    }
+    let frame = texts(&visualiser, 20);
+    assert_eq!(
+        frame[15..18],
+        [
+            "    3     copyto 4",
+            ">   4     outbox",
+            "    5     jump top"
+        ]
+    );
+}
+
+#[test]
+fn status_line_follows_the_run() {
+    let mut visualiser = visualiser(&[7, -30]);
+    while *visualiser.status() == Status::Running {
+        visualiser.step();
+    }
+    assert_eq!(*visualiser.status(), Status::Finished);
+    let frame = texts(&visualiser, 40);
+    assert_eq!(frame[0], "step 8  speed 4/s  finished");
+    assert_eq!(frame[3], "outbox > [ -30][   7]");
+
+    visualiser.restart();
+    assert_eq!(*visualiser.status(), Status::Running);
+    assert_eq!(
+        texts(&visualiser, 40)[..4],
+        [
+            "step 0  speed 4/s  paused",
+            "",
+            "inbox  < [   7][ -30]",
+            "outbox > "
+        ]
+    );
+
+    let mut interpreter = SimpleInterpreter::new();
+    interpreter.set_inbox_values(&[1]);
+    let mut visualiser = Visualiser::new(&Lexer::lex("outbox\n"), interpreter).unwrap();
+    visualiser.step();
+    assert_eq!(
+        texts(&visualiser, 40)[0],
+        "step 0  speed 4/s  error: EmptyHandValue at 1:1"
+    );
+}
@@ -0,0 +1,150 @@
+use hrm::interpreter::SimpleInterpreter;
+use hrm::lexer::Lexer;
+use hrm::wasm::{error_code, to_wat};
+use std::env;
+use std::fs;
+use std::process::Command;
+use wasmparser::{Parser, Payload, Validator};
+
+const SAMPLES: [&str; 8] = ["01", "02", "03", "04", "06", "09", "12", "19"];
+
+/// Programs failing with `Overflow` and `FloorOutOfRange`, with their inbox and error code.
+const FAILURES: [(&str, &str, &[i16], i32); 2] = [
+    (
+        "overflow",
+        "inbox\ncopyto 0\nadd 0\ncopyto 1\noutbox\ncopyfrom 1\nadd 0\n",
+        &[400],
+        9,
+    ),
+    (
+        "floor_out_of_range",
+        "inbox\noutbox\ninbox\ncopyto 6\n",
+        &[999, 3],
+        10,
+    ),
+];
+
+const RUNNER: &str = "
+const fs = require('fs');
+const inbox = process.argv.slice(3).map(Number);
+const outbox = [];
+let cursor = 0;
+const imports = {
+    hrm: {
+        inbox: () => (cursor < inbox.length ? inbox[cursor++] : -2147483648),
+        outbox: (value) => outbox.push(value),
+    },
+};
+WebAssembly.instantiate(fs.readFileSync(process.argv[2]), imports).then(({ instance }) => {
+    const status = instance.exports.run();
+    const { error_line, error_col } = instance.exports;
+    console.log([status, error_line.value, error_col.value].join(' '));
+    console.log(outbox.join(' '));
+});
+";
+
+fn sample(name: &str) -> String {
+    fs::read_to_string(format!("sample_program/{}.hrm", name)).unwrap()
+}
+
+#[test]
+fn modules_are_valid() {
+    for name in SAMPLES.iter() {
+        let wasm = wat::parse_str(to_wat(&Lexer::lex(&sample(name))).unwrap()).unwrap();
+        Validator::new().validate_all(&wasm).unwrap();
+
+        let mut imports = Vec::new();
+        let mut exports = Vec::new();
+        for payload in Parser::new(0).parse_all(&wasm) {
+            match payload.unwrap() {
+                Payload::ImportSection(reader) => {
+                    for import in reader.into_imports() {
+                        let import = import.unwrap();
+                        imports.push(format!("{}.{}", import.module, import.name));
+                    }
+                }
+                Payload::ExportSection(reader) => {
+                    for export in reader {
+                        exports.push(export.unwrap().name.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(imports, ["hrm.inbox", "hrm.outbox"], "{}", name);
+        assert_eq!(exports, ["error_line", "error_col", "run"], "{}", name);
+    }
+}
+
+#[test]
+fn undefined_label_is_rejected() {
+    assert!(to_wat(&Lexer::lex("jump nowhere")).is_err());
+}
+
+/// Runs the modules with Node.js and compares them with `SimpleInterpreter`.
+/// Skipped when `node` is not installed.
+#[test]
+fn node_matches_interpreter() {
+    if Command::new("node").arg("--version").output().is_err() {
+        eprintln!("node is not available, skipped");
+        return;
+    }
+    let dir = env::temp_dir().join(format!("hrm-wasm-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let runner = dir.join("runner.js");
+    fs::write(&runner, RUNNER).unwrap();
+    let input = fs::read_to_string("sample_program/input.txt").unwrap();
+    let mut interpreter = SimpleInterpreter::new();
+    interpreter.set_inbox(input);
+    let values: Vec<i16> = interpreter.show_inbox().clone().unwrap().into();
+    let mut programs: Vec<(&str, String, &[i16])> = SAMPLES
+        .iter()
+        .map(|name| (*name, sample(name), values.as_slice()))
+        .collect();
+    for &(name, source, inbox, _) in FAILURES.iter() {
+        programs.push((name, source.to_string(), inbox));
+    }
+    for (name, source, values) in programs.iter() {
+        let program = Lexer::lex(source);
+        let module = dir.join(format!("{}.wasm", name));
+        fs::write(&module, wat::parse_str(to_wat(&program).unwrap()).unwrap()).unwrap();
+
+        let mut interpreter = SimpleInterpreter::new();
+        interpreter.set_print_outbox(false);
+        interpreter.set_inbox_values(values);
+        let inbox: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        let status = match interpreter.eval(&program) {
+            Ok(_) => "0 0 0".to_string(),
+            Err(e) => format!(
+                "{} {} {}",
+                error_code(e.value),
+                e.location.line,
+                e.location.col
+            ),
+        };
+        let outbox: Vec<String> = interpreter
+            .report()
+            .outbox
+            .iter()
+            .map(|value| value.to_string())
+            .collect();
+
+        let output = Command::new("node")
+            .arg(&runner)
+            .arg(&module)
+            .args(&inbox)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", name);
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            format!("{}\n{}\n", status, outbox.join(" ")),
+            "{}",
+            name
+        );
+        if let Some((_, _, _, code)) = FAILURES.iter().find(|failure| failure.0 == *name) {
+            assert!(status.starts_with(&format!("{} ", code)), "{}", status);
+        }
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,138 @@
+use hrm::lexer::{Lexer, Location, Program};
+use hrm::workers::{run, Config, Conflict, End, Scheduler, SetupError, Waiting};
+use std::fs;
+
+fn programs(sources: &[&str]) -> Vec<Program> {
+    sources.iter().map(|source| Lexer::lex(source)).collect()
+}
+
+fn round_robin(quantum: usize) -> Config {
+    Config {
+        scheduler: Scheduler::RoundRobin { quantum },
+        ..Config::default()
+    }
+}
+
+#[test]
+fn workers_waiting_for_each_other_deadlock() {
+    let run = run(
+        &programs(&["copyfrom 0\ncopyto 1\n", "copyfrom 1\ncopyto 0\n"]),
+        &[],
+        &[],
+        &Config::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        run.end,
+        End::Deadlock(vec![
+            Waiting {
+                worker: 0,
+                tile: 0,
+                location: Location { line: 1, col: 10 }
+            },
+            Waiting {
+                worker: 1,
+                tile: 1,
+                location: Location { line: 1, col: 10 }
+            },
+        ])
+    );
+    assert_eq!(run.steps, [0, 0]);
+}
+
+#[test]
+fn interleaved_counters_lose_updates() {
+    let count = fs::read_to_string("sample_program/workers/count.hrm").unwrap();
+    let run = run(
+        &programs(&[&count, &count]),
+        &[5, 6, 7, 8],
+        &[(0, 0), (1, 1)],
+        &round_robin(1),
+    )
+    .unwrap();
+    assert_eq!(run.end, End::Finished);
+    // four values were counted, but each worker overwrote the count of the other.
+    assert_eq!(run.floor[0], Some(2));
+    let conflict = |step| Conflict {
+        step,
+        worker: 1,
+        tile: 0,
+        overwritten: 0,
+        location: Location { line: 6, col: 12 },
+    };
+    assert_eq!(run.conflicts, [conflict(8), conflict(18)]);
+}
+
+#[test]
+fn round_robin_turns_last_a_quantum() {
+    let sources = [
+        "copyfrom 0\noutbox\ncopyfrom 0\noutbox\ncopyfrom 0\noutbox\n",
+        "copyfrom 1\noutbox\ncopyfrom 1\noutbox\ncopyfrom 1\noutbox\n",
+    ];
+    let outbox = |quantum| {
+        let run = run(
+            &programs(&sources),
+            &[],
+            &[(0, 1), (1, 2)],
+            &round_robin(quantum),
+        )
+        .unwrap();
+        assert_eq!(run.end, End::Finished);
+        assert_eq!(run.steps, [6, 6]);
+        run.outbox
+    };
+    assert_eq!(outbox(1), [1, 2, 1, 2, 1, 2]);
+    assert_eq!(outbox(4), [1, 1, 2, 2, 1, 2]);
+    // a quantum of zero is taken as one.
+    assert_eq!(outbox(0), outbox(1));
+}
+
+#[test]
+fn random_scheduling_follows_the_seed() {
+    let sources = [
+        "jump_target a\ninbox\nadd 0\noutbox\njump a\n",
+        "jump_target a\ninbox\nadd 1\noutbox\njump a\n",
+    ];
+    let config = Config {
+        scheduler: Scheduler::Random { seed: 7 },
+        ..Config::default()
+    };
+    let trace = || {
+        run(
+            &programs(&sources),
+            &[1, 2, 3, 4, 5, 6],
+            &[(0, 100), (1, 200)],
+            &config,
+        )
+        .unwrap()
+    };
+    let first = trace();
+    assert_eq!(first.end, End::Finished);
+    assert_eq!(first.outbox, [101, 202, 103, 204, 105, 106]);
+    assert_eq!(first.steps, [16, 8]);
+    assert!(first.conflicts.is_empty());
+    assert_eq!(trace(), first);
+}
+
+#[test]
+fn floor_seeds_must_fit_the_floor() {
+    let programs = programs(&["inbox\noutbox\n"]);
+    for (tile, value) in [(6, 1), (0, 1000), (2, -1000)] {
+        assert_eq!(
+            run(&programs, &[1], &[(tile, value)], &Config::default()),
+            Err(SetupError::InvalidFloor { tile, value })
+        );
+    }
+}
+
+#[test]
+fn undefined_labels_name_the_worker() {
+    let error = run(
+        &programs(&["inbox\n", "jump nowhere\n"]),
+        &[],
+        &[],
+        &Config::default(),
+    )
+    .unwrap_err();
+    assert!(matches!(error, SetupError::Program { worker: 1, .. }));
+}